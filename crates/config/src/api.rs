@@ -0,0 +1,377 @@
+//! Types the daemon's API exchanges, shared by the daemon and its clients.
+
+use std::{env, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{RetryErrorClass, RootConfig, Spec, TriggerSchedule, quantity::ResourceQuantity};
+
+/// Number of stdout bytes kept in an invocation record.
+const OUTPUT_LIMIT: usize = 4096;
+
+/// Resources a store actually used, as observed by its resource limiter.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Largest size any linear memory was allowed to grow to, in bytes.
+    pub peak_memory: usize,
+    /// Largest number of elements any table was allowed to grow to.
+    #[serde(default)]
+    pub peak_table_elements: usize,
+    /// Memory and table growths refused by the limits.
+    #[serde(default)]
+    pub denied_growths: u64,
+    /// Fuel the guest burned, roughly one unit per executed instruction.
+    #[serde(default)]
+    pub fuel_consumed: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropletExecutionResult {
+    pub stdout: String,
+    /// Value returned by the entrypoint, converted to JSON.
+    #[serde(default)]
+    pub result: serde_json::Value,
+    pub usage: ResourceUsage,
+}
+
+/// What caused a droplet to be invoked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum InvocationSource {
+    /// A synchronous API call.
+    Api,
+    /// An asynchronous job.
+    Job { id: String },
+    /// A scheduled trigger.
+    Trigger { name: String },
+}
+
+impl InvocationSource {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Api => "api",
+            Self::Job { .. } => "job",
+            Self::Trigger { .. } => "trigger",
+        }
+    }
+}
+
+/// Who made an API request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Caller {
+    /// Principal the request authenticated as, `admin` or `namespace:<name>`; unset when
+    /// authentication is disabled.
+    #[serde(rename = "caller")]
+    pub principal: Option<String>,
+    /// Address the request came from.
+    pub peer: Option<String>,
+}
+
+/// Who invoked a droplet and why.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvocationOrigin {
+    pub source: InvocationSource,
+    /// Caller of the API request behind the invocation, empty for triggers.
+    #[serde(flatten)]
+    pub caller: Caller,
+}
+
+impl InvocationOrigin {
+    pub fn new(source: InvocationSource, caller: Caller) -> Self {
+        Self { source, caller }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvocationOutcome {
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvocationRecord {
+    pub id: String,
+    pub droplet: String,
+    pub revision: u64,
+    #[serde(flatten)]
+    pub origin: InvocationOrigin,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub usage: ResourceUsage,
+    pub outcome: InvocationOutcome,
+    pub error_class: Option<RetryErrorClass>,
+    pub error: Option<String>,
+    /// Start of the droplet's stdout, cut to a fixed size.
+    pub output: String,
+    pub output_truncated: bool,
+}
+
+impl InvocationRecord {
+    /// Stores `output` in the record, cutting it down to the output limit.
+    pub fn set_output(&mut self, output: &str) {
+        let mut end = output.len().min(OUTPUT_LIMIT);
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        self.output = output[..end].to_string();
+        self.output_truncated = end < output.len();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    pub droplet: String,
+    pub status: JobStatus,
+    /// Arguments the droplet's entrypoint is called with.
+    #[serde(default)]
+    pub args: serde_json::Value,
+    /// URL the finished job is POSTed to.
+    pub webhook: Option<String>,
+    /// Whoever queued the job.
+    #[serde(flatten)]
+    pub caller: Caller,
+    pub result: Option<DropletExecutionResult>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedAttempt {
+    pub at: DateTime<Utc>,
+    pub class: RetryErrorClass,
+    pub error: String,
+}
+
+/// An invocation that failed after exhausting its retry policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: String,
+    pub droplet: String,
+    #[serde(flatten)]
+    pub origin: InvocationOrigin,
+    /// Arguments of the failed invocation, reused when it is replayed.
+    #[serde(default)]
+    pub args: serde_json::Value,
+    pub attempts: Vec<FailedAttempt>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerInfo {
+    pub droplet: String,
+    pub trigger: String,
+    pub schedule: TriggerSchedule,
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Namespace {
+    pub name: String,
+    #[serde(default)]
+    pub quota: NamespaceQuota,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Limits on what the droplets of a namespace may use together; unset limits are unbounded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NamespaceQuota {
+    /// Sum of the droplets' `resources.memory`, e.g. `2Gi`.
+    pub memory: Option<String>,
+    pub droplets: Option<usize>,
+    pub concurrent_invocations: Option<usize>,
+}
+
+impl NamespaceQuota {
+    pub fn memory_bytes(&self) -> anyhow::Result<Option<u64>> {
+        self.memory
+            .as_deref()
+            .map(|memory| {
+                ResourceQuantity::try_from(memory)?
+                    .as_memory()
+                    .ok_or_else(|| anyhow::anyhow!("Invalid memory quota \"{memory}\"."))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blob {
+    /// Content digest, `sha256:<hex>`.
+    pub digest: String,
+    pub size: u64,
+}
+
+/// Digest `bytes` are stored under, `sha256:<hex>`.
+pub fn digest(bytes: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(bytes))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WasmKind {
+    Component,
+    /// A core module, such as a `wasm32-wasip1` binary.
+    Module,
+}
+
+/// What a component or module imports and exports, and what it costs to compile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmReport {
+    pub kind: WasmKind,
+    /// Size of the binary, in bytes.
+    pub size: usize,
+    /// Size of the precompiled artifact, in bytes.
+    pub artifact_size: usize,
+    pub compile_ms: u64,
+    pub imports: Vec<ImportReport>,
+    pub exports: Vec<ExportReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Import name, `<module>::<name>` for core modules.
+    pub name: String,
+    pub kind: String,
+    /// Whether the host linker provides this import.
+    pub satisfied: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportReport {
+    /// Function name, `<interface>#<function>` for functions exported from an interface.
+    pub name: String,
+    pub signature: String,
+}
+
+/// How long an invocation waits for capacity by default before it is rejected.
+const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Capacity of the node shared by every running invocation; unset limits are unbounded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResourceBudget {
+    /// Bytes of linear memory all running droplets may declare together.
+    pub memory: Option<u64>,
+    /// Millicores all running droplets may declare together.
+    pub cpu: Option<u64>,
+    /// How long an invocation waits for capacity to free up before being rejected.
+    #[serde(with = "duration_text")]
+    pub queue_timeout: Duration,
+}
+
+impl Default for ResourceBudget {
+    fn default() -> Self {
+        Self {
+            memory: None,
+            cpu: None,
+            queue_timeout: DEFAULT_QUEUE_TIMEOUT,
+        }
+    }
+}
+
+impl ResourceBudget {
+    /// Reads the budget from `MIST_NODE_MEMORY` (e.g. `8Gi`), `MIST_NODE_CPU` (e.g. `4` or
+    /// `3500m`) and `MIST_ADMISSION_TIMEOUT` (e.g. `10s`, `0s` to never queue).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let mut budget = Self::default();
+
+        if let Some(memory) = var("MIST_NODE_MEMORY") {
+            budget.memory = ResourceQuantity::try_from(memory.as_str())?.as_memory();
+        }
+        if let Some(cpu) = var("MIST_NODE_CPU") {
+            budget.cpu = ResourceQuantity::parse_cpu(&cpu)?.as_cpu();
+        }
+        if let Some(timeout) = var("MIST_ADMISSION_TIMEOUT") {
+            budget.queue_timeout = humantime::parse_duration(&timeout)?;
+        }
+
+        Ok(budget)
+    }
+}
+
+/// Memory and CPU a droplet declares in `spec.runtime.resources`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resources {
+    /// Bytes.
+    pub memory: u64,
+    /// Millicores.
+    pub cpu: u64,
+}
+
+impl Resources {
+    pub fn declared(config: &RootConfig) -> anyhow::Result<Self> {
+        let Spec::Droplet { runtime, .. } = &config.spec;
+
+        Ok(Self {
+            memory: ResourceQuantity::try_from(runtime.resources.memory.as_str())?
+                .as_memory()
+                .unwrap_or(0),
+            cpu: ResourceQuantity::parse_cpu(&runtime.resources.cpu)?
+                .as_cpu()
+                .unwrap_or(0),
+        })
+    }
+
+    pub fn fits(&self, budget: &ResourceBudget) -> bool {
+        budget.memory.is_none_or(|memory| self.memory <= memory)
+            && budget.cpu.is_none_or(|cpu| self.cpu <= cpu)
+    }
+}
+
+/// A running invocation holding part of the budget.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    pub droplet: String,
+    #[serde(flatten)]
+    pub resources: Resources,
+    pub since: DateTime<Utc>,
+}
+
+/// The budget, what is reserved from it, and by whom.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdmissionReport {
+    pub budget: ResourceBudget,
+    pub reserved: Resources,
+    /// Invocations waiting for capacity.
+    pub queued: usize,
+    pub reservations: Vec<Reservation>,
+}
+
+/// Durations as human-readable text, e.g. `30s`.
+mod duration_text {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&humantime::format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        humantime::parse_duration(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
@@ -1,69 +1,258 @@
+pub mod api;
+pub mod quantity;
+pub mod selector;
+pub mod v1;
+pub mod validate;
+pub mod versions;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A droplet config in the latest schema; older versions are converted by [`versions::migrate`].
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct RootConfig {
-    pub api_version: String,
+    pub api_version: ApiVersion,
     pub metadata: Metadata,
     #[serde(flatten)]
     pub spec: Spec,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(
+    Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum ApiVersion {
+    #[serde(rename = "hm/v1")]
+    V1,
+    /// Environment variables are a map and `secrets` may be omitted.
+    #[serde(rename = "hm/v2")]
+    V2,
+}
+
+impl ApiVersion {
+    pub const ALL: &[Self] = &[Self::V1, Self::V2];
+    pub const LATEST: Self = Self::V2;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::V1 => "hm/v1",
+            Self::V2 => "hm/v2",
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiVersion {
+    type Err = versions::MigrationError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|version| version.as_str() == value)
+            .copied()
+            .ok_or_else(|| versions::MigrationError::UnknownVersion(value.to_string()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(tag = "kind", content = "spec")]
 pub enum Spec {
     Droplet {
         source: SpecSource,
         runtime: SpecRuntime,
+        #[serde(default)]
         secrets: Vec<SpecSecret>,
+        triggers: Option<Vec<SpecTrigger>>,
+        retry: Option<SpecRetry>,
+        /// Exported function to call, `handler` for components and `_start` for
+        /// core modules by default. Functions exported from a component
+        /// interface are named `<interface>#<function>`.
+        entrypoint: Option<String>,
     },
 }
 
 impl Spec {
+    pub const DEFAULT_ENTRYPOINT: &str = "handler";
+    pub const DEFAULT_MODULE_ENTRYPOINT: &str = "_start";
+
     pub fn as_droplet(&self) -> Option<(&SpecSource, &SpecRuntime, &Vec<SpecSecret>)> {
         match self {
             Self::Droplet {
                 source,
                 runtime,
                 secrets,
+                ..
             } => Some((source, runtime, secrets)),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct SpecSecret {
     pub name: String,
     pub mount_path: PathBuf,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpecTrigger {
+    pub name: String,
+    #[serde(flatten)]
+    pub schedule: TriggerSchedule,
+    /// IANA time zone the cron expression is evaluated in, UTC by default.
+    pub timezone: Option<String>,
+    /// Upper bound of the random delay added to every run, e.g. `30s`.
+    pub jitter: Option<String>,
+    #[serde(default)]
+    pub catch_up: TriggerCatchUp,
+    /// Arguments the entrypoint is called with, as for a JSON invocation.
+    pub args: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerSchedule {
+    /// Cron expression, either 5 fields (minute precision) or 6-7 fields (with seconds).
+    Cron(String),
+    /// Fixed interval between runs, e.g. `5m` or `1h 30m`.
+    Interval(String),
+}
+
+/// What to do with runs that were missed while the daemon was down.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TriggerCatchUp {
+    /// Drop missed runs and wait for the next occurrence.
+    #[default]
+    Skip,
+    /// Run once immediately, regardless of how many runs were missed.
+    RunOnce,
+}
+
+/// Retry policy applied to asynchronous and scheduled invocations.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpecRetry {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    #[serde(default)]
+    pub backoff: RetryBackoff,
+    #[serde(default = "RetryErrorClass::retryable_by_default")]
+    pub retry_on: Vec<RetryErrorClass>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct RetryBackoff {
+    pub initial: String,
+    pub multiplier: f64,
+    pub max: String,
+}
+
+impl Default for RetryBackoff {
+    fn default() -> Self {
+        Self {
+            initial: "1s".to_string(),
+            multiplier: 2.0,
+            max: "1m".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+pub enum RetryErrorClass {
+    /// The guest trapped (unreachable, out-of-bounds access, failed allocation, ...).
+    Trap,
+    /// The guest was interrupted for running past the daemon's execution time.
+    Timeout,
+    /// The host failed to set up or drive the invocation, or stopped it at a resource limit.
+    Host,
+}
+
+impl RetryErrorClass {
+    fn retryable_by_default() -> Vec<Self> {
+        vec![Self::Trap, Self::Timeout]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(untagged)]
 pub enum SpecSource {
     #[serde(rename = "File")]
-    File { path: PathBuf },
+    File {
+        path: PathBuf,
+        /// Recompile the droplet whenever the file changes; the daemon must see the same path.
+        #[serde(default)]
+        watch: bool,
+    },
+    /// Binary uploaded to the daemon's blob store, referenced as `sha256:<hex>`.
+    #[serde(rename = "Blob")]
+    Blob { digest: String },
+    /// Binary downloaded over HTTP(S) and verified against its SHA-256 hex digest.
+    #[serde(rename = "Url")]
+    Url {
+        url: String,
+        sha256: String,
+        /// Secret holding credentials for the server, `user:password` or a bearer token.
+        pull_secret: Option<String>,
+    },
+    /// Binary pulled from an OCI registry, e.g. `ghcr.io/org/droplet:1.0`.
+    #[serde(rename = "Oci")]
+    Oci {
+        reference: String,
+        /// Secret holding credentials for the registry, `user:password` or a bearer token.
+        pull_secret: Option<String>,
+    },
+    /// Binary embedded in the config as base64.
+    #[serde(rename = "Inline")]
+    Inline { base64: String },
+    /// Component or module written in the WebAssembly text format.
+    #[serde(rename = "Wat")]
+    Wat { text: String },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct Metadata {
+    /// DNS label style identifier, used in file names and metric labels.
+    #[schemars(pattern(validate::NAME_PATTERN))]
     pub name: String,
+    /// Namespace the droplet belongs to; names only have to be unique within a namespace.
+    #[serde(default = "Metadata::default_namespace")]
+    #[schemars(pattern(validate::NAME_PATTERN))]
+    pub namespace: String,
+    /// Identifying key/value pairs, matched by label selectors such as `team=payments,env!=prod`.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Free-form notes for people and tools; unlike labels they cannot be selected on.
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    pub const DEFAULT_NAMESPACE: &str = "default";
+
+    fn default_namespace() -> String {
+        Self::DEFAULT_NAMESPACE.to_string()
+    }
+
+    /// Identifier unique across namespaces, `<namespace>/<name>`.
+    pub fn id(&self) -> String {
+        format!("{}/{}", self.namespace, self.name)
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct SpecRuntime {
-    pub env: Option<Vec<RuntimeEnv>>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
     pub filesystem: Option<Vec<RuntimeFilesystemMount>>,
     pub network: Option<RuntimeNetwork>,
     pub resources: RuntimeResources,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct RuntimeEnv {
-    pub name: String,
-    pub value: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct RuntimeFilesystemMount {
     pub name: String,
     pub guest_path: String,
@@ -71,13 +260,35 @@ pub struct RuntimeFilesystemMount {
     pub read_only: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Hosts the guest may reach. Not enforced yet: guests are given no sockets and `wasi:http`
+/// is not linked, so they have no network access at all.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct RuntimeNetwork {
     pub allowed_hosts: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 pub struct RuntimeResources {
+    /// Memory limit, e.g. `64Mi` or `1.5G`.
+    #[schemars(pattern(quantity::MEMORY_PATTERN))]
     pub memory: String,
+    /// vCPUs, e.g. `1` or `0.5`, or millicores, e.g. `100m`.
+    #[schemars(pattern(quantity::CPU_PATTERN))]
     pub cpu: String,
+    /// Elements each table may grow to; unset limits are capped by the daemon only.
+    #[serde(default)]
+    pub table_elements: Option<usize>,
+    /// Instances the droplet may create, counting nested component instances.
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub instances: Option<usize>,
+    /// Tables the droplet may create across its instances.
+    #[serde(default)]
+    pub tables: Option<usize>,
+    /// Linear memories the droplet may create across its instances.
+    #[serde(default)]
+    pub memories: Option<usize>,
+    /// Trap when a memory or table cannot grow instead of letting the guest see the failure.
+    #[serde(default)]
+    pub trap_on_oom: bool,
 }
@@ -0,0 +1,244 @@
+use thiserror::Error;
+
+/// Memory quantities accepted by [`ResourceQuantity::try_from`], as a regular expression.
+pub const MEMORY_PATTERN: &str =
+    "^[0-9]+(\\.[0-9]*)?(k|M|G|T|P|E|Z|Y|R|Q|Ki|Mi|Gi|Ti|Pi|Ei|Zi|Yi|Ri|Qi)$";
+/// CPU quantities accepted by [`ResourceQuantity::parse_cpu`], as a regular expression.
+pub const CPU_PATTERN: &str = "^(0*[1-9][0-9]*(\\.[0-9]*)?|0*\\.[0-9]*[1-9][0-9]*)m?$";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ResourceQuantity {
+    Memory {
+        bytes: u64,
+    },
+    /// Thousandths of a vCPU.
+    Cpu {
+        millicores: u64,
+    },
+}
+
+impl ResourceQuantity {
+    pub fn as_memory(&self) -> Option<u64> {
+        match self {
+            Self::Memory { bytes } => Some(*bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_cpu(&self) -> Option<u64> {
+        match self {
+            Self::Cpu { millicores } => Some(*millicores),
+            _ => None,
+        }
+    }
+
+    /// Parses a CPU quantity, either in vCPUs (`1`, `0.5`) or in millicores (`500m`).
+    ///
+    /// Zero is refused, and any other amount is at least one millicore.
+    pub fn parse_cpu(value: &str) -> Result<Self, QuantityParseError> {
+        let (num, scale) = match value.strip_suffix('m') {
+            Some(num) => (num, 1.0),
+            None => (value, 1000.0),
+        };
+        if num.is_empty() || !num.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return Err(QuantityParseError::InvalidFormat);
+        }
+
+        let num: f64 = num.parse().or(Err(QuantityParseError::InvalidFormat))?;
+        if num == 0.0 {
+            return Err(QuantityParseError::InvalidFormat);
+        }
+        let millicores = (num * scale).round().max(1.0);
+        if millicores >= 2f64.powi(64) {
+            return Err(QuantityParseError::TooLarge);
+        }
+        let millicores = millicores as u64;
+
+        Ok(Self::Cpu { millicores })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum QuantityParseError {
+    #[error("Invalid quantity format.")]
+    InvalidFormat,
+    #[error("Invalid quantity unit.")]
+    InvalidUnit,
+    #[error("Quantity is too large.")]
+    TooLarge,
+}
+
+impl TryFrom<&str> for ResourceQuantity {
+    type Error = QuantityParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(QuantityParseError::InvalidFormat);
+        }
+
+        let mut num_len = 0;
+        let mut is_float = false;
+
+        for c in value.chars() {
+            if !(c.is_ascii_digit() || c == '.' && !is_float) {
+                break;
+            }
+
+            if c == '.' {
+                is_float = true;
+            }
+
+            num_len += 1;
+        }
+
+        if num_len == 0 {
+            return Err(QuantityParseError::InvalidFormat);
+        }
+
+        let num: f64 = value[..num_len]
+            .parse()
+            .or(Err(QuantityParseError::InvalidFormat))?;
+
+        let unit: DataUnit = value[num_len..]
+            .try_into()
+            .or(Err(QuantityParseError::InvalidUnit))?;
+
+        let bytes = unit.to_bytes().ok_or(QuantityParseError::TooLarge)? as f64 * num;
+        // `as` saturates, so anything from 2^64 up has to be refused before the cast.
+        if !bytes.is_finite() || bytes.round() >= 2f64.powi(64) {
+            return Err(QuantityParseError::TooLarge);
+        }
+
+        Ok(Self::Memory {
+            bytes: bytes.round() as u64,
+        })
+    }
+}
+
+pub enum DataUnit {
+    /// 1024 ^ n
+    Kibibyte,
+    Mebibyte,
+    Gibibyte,
+    Tebibyte,
+    Pebibyte,
+    Exbibyte,
+    Zebibyte,
+    Yobibyte,
+    Robibyte,
+    Quebibyte,
+
+    /// 1000 ^ n
+    Kilobyte,
+    Megabyte,
+    Gigabyte,
+    Terabyte,
+    Petabyte,
+    Exabyte,
+    Zettabyte,
+    Yottabyte,
+    Ronnabyte,
+    Quettabyte,
+}
+
+impl TryFrom<&str> for DataUnit {
+    type Error = &'static str;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "k" => Ok(Self::Kilobyte),
+            "M" => Ok(Self::Megabyte),
+            "G" => Ok(Self::Gigabyte),
+            "T" => Ok(Self::Terabyte),
+            "P" => Ok(Self::Petabyte),
+            "E" => Ok(Self::Exabyte),
+            "Z" => Ok(Self::Zettabyte),
+            "Y" => Ok(Self::Yottabyte),
+            "R" => Ok(Self::Ronnabyte),
+            "Q" => Ok(Self::Quettabyte),
+            "Ki" => Ok(Self::Kibibyte),
+            "Mi" => Ok(Self::Mebibyte),
+            "Gi" => Ok(Self::Gibibyte),
+            "Ti" => Ok(Self::Tebibyte),
+            "Pi" => Ok(Self::Pebibyte),
+            "Ei" => Ok(Self::Exbibyte),
+            "Zi" => Ok(Self::Zebibyte),
+            "Yi" => Ok(Self::Yobibyte),
+            "Ri" => Ok(Self::Robibyte),
+            "Qi" => Ok(Self::Quebibyte),
+            _ => Err("Invalid unit."),
+        }
+    }
+}
+
+impl DataUnit {
+    /// Bytes in one unit, `None` from zettabytes and zebibytes up, which do not fit in a `u64`.
+    pub fn to_bytes(&self) -> Option<u64> {
+        match self {
+            Self::Kilobyte => Some(1000),
+            Self::Megabyte => 1000u64.checked_pow(2),
+            Self::Gigabyte => 1000u64.checked_pow(3),
+            Self::Terabyte => 1000u64.checked_pow(4),
+            Self::Petabyte => 1000u64.checked_pow(5),
+            Self::Exabyte => 1000u64.checked_pow(6),
+            Self::Zettabyte => 1000u64.checked_pow(7),
+            Self::Yottabyte => 1000u64.checked_pow(8),
+            Self::Ronnabyte => 1000u64.checked_pow(9),
+            Self::Quettabyte => 1000u64.checked_pow(10),
+            Self::Kibibyte => Some(1024),
+            Self::Mebibyte => 1024u64.checked_pow(2),
+            Self::Gibibyte => 1024u64.checked_pow(3),
+            Self::Tebibyte => 1024u64.checked_pow(4),
+            Self::Pebibyte => 1024u64.checked_pow(5),
+            Self::Exbibyte => 1024u64.checked_pow(6),
+            Self::Zebibyte => 1024u64.checked_pow(7),
+            Self::Yobibyte => 1024u64.checked_pow(8),
+            Self::Robibyte => 1024u64.checked_pow(9),
+            Self::Quebibyte => 1024u64.checked_pow(10),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(value: &str) -> Result<u64, QuantityParseError> {
+        Ok(ResourceQuantity::try_from(value)?.as_memory().unwrap())
+    }
+
+    #[test]
+    fn largest_units_that_fit() {
+        assert_eq!(memory("1E").unwrap(), 1000u64.pow(6));
+        assert_eq!(memory("1Ei").unwrap(), 1024u64.pow(6));
+        assert_eq!(memory("15Ei").unwrap(), 15 * 1024u64.pow(6));
+        assert_eq!(memory("18E").unwrap(), 18 * 1000u64.pow(6));
+    }
+
+    #[test]
+    fn units_beyond_u64_are_too_large() {
+        for value in ["1Z", "1Zi", "1Y", "1Yi", "1R", "1Ri", "1Q", "1Qi", "0.5Qi"] {
+            assert!(
+                matches!(memory(value), Err(QuantityParseError::TooLarge)),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn products_beyond_u64_are_too_large() {
+        assert!(matches!(memory("16Ei"), Err(QuantityParseError::TooLarge)));
+        assert!(matches!(memory("19E"), Err(QuantityParseError::TooLarge)));
+        assert!(matches!(
+            memory("99999999999999999999999k"),
+            Err(QuantityParseError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn data_units() {
+        assert_eq!(DataUnit::Exbibyte.to_bytes(), Some(1 << 60));
+        assert_eq!(DataUnit::Zebibyte.to_bytes(), None);
+        assert_eq!(DataUnit::Quettabyte.to_bytes(), None);
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A label selector, e.g. `team=payments,env!=prod,tier in (web,api),!canary`.
+///
+/// A droplet matches when it satisfies every requirement; the empty selector matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct LabelSelector {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Requirement {
+    Equals(String, String),
+    /// Also matched by droplets without the label.
+    NotEquals(String, String),
+    In(String, BTreeSet<String>),
+    /// Also matched by droplets without the label.
+    NotIn(String, BTreeSet<String>),
+    Exists(String),
+    DoesNotExist(String),
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid selector requirement \"{requirement}\": {reason}")]
+pub struct SelectorError {
+    requirement: String,
+    reason: &'static str,
+}
+
+impl LabelSelector {
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+
+    pub fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.requirements
+            .iter()
+            .all(|requirement| requirement.matches(labels))
+    }
+}
+
+impl Requirement {
+    fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        match self {
+            Self::Equals(key, value) => labels.get(key) == Some(value),
+            Self::NotEquals(key, value) => labels.get(key) != Some(value),
+            Self::In(key, values) => labels.get(key).is_some_and(|v| values.contains(v)),
+            Self::NotIn(key, values) => !labels.get(key).is_some_and(|v| values.contains(v)),
+            Self::Exists(key) => labels.contains_key(key),
+            Self::DoesNotExist(key) => !labels.contains_key(key),
+        }
+    }
+
+    fn parse(requirement: &str) -> Result<Self, SelectorError> {
+        let error = |reason| SelectorError {
+            requirement: requirement.to_string(),
+            reason,
+        };
+        let key = |key: &str| {
+            let key = key.trim();
+            if key.is_empty() || key.contains(|c: char| c.is_whitespace() || "!=(),".contains(c)) {
+                return Err(error("invalid label key"));
+            }
+            Ok(key.to_string())
+        };
+
+        if let Some(open) = requirement.find('(') {
+            let values = requirement[open..]
+                .strip_prefix('(')
+                .and_then(|values| values.strip_suffix(')'))
+                .ok_or_else(|| error("unbalanced parentheses"))?
+                .split(',')
+                .map(|value| value.trim().to_string())
+                .collect::<BTreeSet<_>>();
+
+            let (name, operator) = requirement[..open]
+                .trim()
+                .rsplit_once(char::is_whitespace)
+                .ok_or_else(|| error("expected `key in (...)` or `key notin (...)`"))?;
+            return match operator {
+                "in" => Ok(Self::In(key(name)?, values)),
+                "notin" => Ok(Self::NotIn(key(name)?, values)),
+                _ => Err(error("expected `in` or `notin`")),
+            };
+        }
+
+        if let Some((name, value)) = requirement.split_once("!=") {
+            return Ok(Self::NotEquals(key(name)?, value.trim().to_string()));
+        }
+        if let Some((name, value)) = requirement
+            .split_once("==")
+            .or_else(|| requirement.split_once('='))
+        {
+            return Ok(Self::Equals(key(name)?, value.trim().to_string()));
+        }
+        if let Some(name) = requirement.trim().strip_prefix('!') {
+            return Ok(Self::DoesNotExist(key(name)?));
+        }
+
+        Ok(Self::Exists(key(requirement)?))
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = SelectorError;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        // Commas separate requirements, except inside the value set of `in`/`notin`.
+        let mut requirements = vec![];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in selector.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    requirements.push(&selector[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        requirements.push(&selector[start..]);
+
+        Ok(Self {
+            requirements: requirements
+                .into_iter()
+                .filter(|requirement| !requirement.trim().is_empty())
+                .map(|requirement| Requirement::parse(requirement.trim()))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<String> for LabelSelector {
+    type Error = SelectorError;
+
+    fn try_from(selector: String) -> Result<Self, Self::Error> {
+        selector.parse()
+    }
+}
+
+impl From<LabelSelector> for String {
+    fn from(selector: LabelSelector) -> Self {
+        selector.to_string()
+    }
+}
+
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let requirements = self
+            .requirements
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        f.write_str(&requirements.join(","))
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let set = |values: &BTreeSet<String>| values.iter().cloned().collect::<Vec<_>>().join(",");
+        match self {
+            Self::Equals(key, value) => write!(f, "{key}={value}"),
+            Self::NotEquals(key, value) => write!(f, "{key}!={value}"),
+            Self::In(key, values) => write!(f, "{key} in ({})", set(values)),
+            Self::NotIn(key, values) => write!(f, "{key} notin ({})", set(values)),
+            Self::Exists(key) => write!(f, "{key}"),
+            Self::DoesNotExist(key) => write!(f, "!{key}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn matches(selector: &str, pairs: &[(&str, &str)]) -> bool {
+        selector
+            .parse::<LabelSelector>()
+            .unwrap()
+            .matches(&labels(pairs))
+    }
+
+    #[test]
+    fn equality() {
+        assert!(matches("team=payments", &[("team", "payments")]));
+        assert!(matches("team == payments", &[("team", "payments")]));
+        assert!(!matches("team=payments", &[("team", "search")]));
+        assert!(!matches("team=payments", &[]));
+    }
+
+    #[test]
+    fn inequality_matches_missing_labels() {
+        assert!(matches("env!=prod", &[("env", "dev")]));
+        assert!(matches("env!=prod", &[]));
+        assert!(!matches("env!=prod", &[("env", "prod")]));
+    }
+
+    #[test]
+    fn set_membership() {
+        assert!(matches("tier in (web, api)", &[("tier", "api")]));
+        assert!(!matches("tier in (web,api)", &[("tier", "db")]));
+        assert!(!matches("tier in (web,api)", &[]));
+
+        assert!(matches("tier notin (web,api)", &[("tier", "db")]));
+        assert!(matches("tier notin (web,api)", &[]));
+        assert!(!matches("tier notin (web,api)", &[("tier", "web")]));
+    }
+
+    #[test]
+    fn existence() {
+        assert!(matches("canary", &[("canary", "")]));
+        assert!(!matches("canary", &[]));
+        assert!(matches("!canary", &[]));
+        assert!(!matches("!canary", &[("canary", "true")]));
+    }
+
+    #[test]
+    fn commas_inside_sets_do_not_split_requirements() {
+        let selector = "team=payments,tier in (web,api),env notin (prod,staging),!canary"
+            .parse::<LabelSelector>()
+            .unwrap();
+        assert_eq!(selector.requirements.len(), 4);
+        assert!(selector.matches(&labels(&[("team", "payments"), ("tier", "web")])));
+        assert!(!selector.matches(&labels(&[
+            ("team", "payments"),
+            ("tier", "web"),
+            ("env", "prod")
+        ])));
+        assert!(!selector.matches(&labels(&[
+            ("team", "payments"),
+            ("tier", "web"),
+            ("canary", "")
+        ])));
+    }
+
+    #[test]
+    fn empty_selector_matches_everything() {
+        let selector = " , ".parse::<LabelSelector>().unwrap();
+        assert!(selector.is_empty());
+        assert!(selector.matches(&labels(&[("team", "payments")])));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let selector =
+            "team = payments, env!=prod,tier in (web, api),tier notin (db),canary,!legacy"
+                .parse::<LabelSelector>()
+                .unwrap();
+        assert_eq!(
+            selector.to_string(),
+            "team=payments,env!=prod,tier in (api,web),tier notin (db),canary,!legacy"
+        );
+        assert_eq!(
+            selector.to_string().parse::<LabelSelector>().unwrap(),
+            selector
+        );
+    }
+
+    #[test]
+    fn invalid_selectors_are_rejected() {
+        for selector in [
+            "tier in (web,api",
+            "tier (web)",
+            "tier within (web)",
+            "=payments",
+            "!",
+            "team name=payments",
+        ] {
+            assert!(
+                selector.parse::<LabelSelector>().is_err(),
+                "{selector} was accepted"
+            );
+        }
+    }
+}
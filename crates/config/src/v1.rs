@@ -0,0 +1,476 @@
+//! The `hm/v1` schema, where environment variables are a list of name/value pairs.
+//!
+//! The types are copies of the shared ones as they were when `hm/v2` was introduced, so
+//! fields added to the latest schema since do not change what `hm/v1` accepts.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{ApiVersion, quantity, validate, versions::MigrationError};
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RootConfig {
+    pub api_version: ApiVersion,
+    pub metadata: Metadata,
+    #[serde(flatten)]
+    pub spec: Spec,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(tag = "kind", content = "spec")]
+pub enum Spec {
+    Droplet {
+        source: SpecSource,
+        runtime: SpecRuntime,
+        secrets: Vec<SpecSecret>,
+        triggers: Option<Vec<SpecTrigger>>,
+        retry: Option<SpecRetry>,
+        entrypoint: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Metadata {
+    #[schemars(pattern(validate::NAME_PATTERN))]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(untagged)]
+pub enum SpecSource {
+    #[serde(rename = "File")]
+    File { path: PathBuf },
+    #[serde(rename = "Blob")]
+    Blob { digest: String },
+    #[serde(rename = "Url")]
+    Url {
+        url: String,
+        sha256: String,
+        pull_secret: Option<String>,
+    },
+    #[serde(rename = "Oci")]
+    Oci {
+        reference: String,
+        pull_secret: Option<String>,
+    },
+    #[serde(rename = "Inline")]
+    Inline { base64: String },
+    #[serde(rename = "Wat")]
+    Wat { text: String },
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpecSecret {
+    pub name: String,
+    pub mount_path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpecTrigger {
+    pub name: String,
+    #[serde(flatten)]
+    pub schedule: TriggerSchedule,
+    pub timezone: Option<String>,
+    pub jitter: Option<String>,
+    #[serde(default)]
+    pub catch_up: TriggerCatchUp,
+    pub args: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerSchedule {
+    Cron(String),
+    Interval(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, Default)]
+pub enum TriggerCatchUp {
+    #[default]
+    Skip,
+    RunOnce,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpecRetry {
+    pub max_attempts: u32,
+    #[serde(default)]
+    pub backoff: RetryBackoff,
+    #[serde(default = "RetryErrorClass::retryable_by_default")]
+    pub retry_on: Vec<RetryErrorClass>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct RetryBackoff {
+    pub initial: String,
+    pub multiplier: f64,
+    pub max: String,
+}
+
+impl Default for RetryBackoff {
+    fn default() -> Self {
+        Self {
+            initial: "1s".to_string(),
+            multiplier: 2.0,
+            max: "1m".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy)]
+pub enum RetryErrorClass {
+    Trap,
+    Timeout,
+    Host,
+}
+
+impl RetryErrorClass {
+    fn retryable_by_default() -> Vec<Self> {
+        vec![Self::Trap, Self::Timeout]
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct SpecRuntime {
+    pub env: Option<Vec<RuntimeEnv>>,
+    pub filesystem: Option<Vec<RuntimeFilesystemMount>>,
+    pub network: Option<RuntimeNetwork>,
+    pub resources: RuntimeResources,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RuntimeEnv {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RuntimeFilesystemMount {
+    pub name: String,
+    pub guest_path: String,
+    pub host_path: String,
+    pub read_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RuntimeNetwork {
+    pub allowed_hosts: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct RuntimeResources {
+    #[schemars(pattern(quantity::MEMORY_PATTERN))]
+    pub memory: String,
+    #[schemars(pattern(quantity::CPU_PATTERN))]
+    pub cpu: String,
+}
+
+impl TryFrom<RootConfig> for crate::RootConfig {
+    type Error = MigrationError;
+
+    fn try_from(config: RootConfig) -> Result<Self, Self::Error> {
+        let Spec::Droplet {
+            source,
+            runtime,
+            secrets,
+            triggers,
+            retry,
+            entrypoint,
+        } = config.spec;
+
+        let mut env = BTreeMap::new();
+        for (i, var) in runtime.env.into_iter().flatten().enumerate() {
+            if env.insert(var.name.clone(), var.value).is_some() {
+                return Err(MigrationError::DuplicateEnv {
+                    index: i,
+                    name: var.name,
+                });
+            }
+        }
+
+        Ok(Self {
+            api_version: ApiVersion::V2,
+            metadata: crate::Metadata {
+                name: config.metadata.name,
+                namespace: crate::Metadata::DEFAULT_NAMESPACE.to_string(),
+                labels: BTreeMap::new(),
+                annotations: BTreeMap::new(),
+            },
+            spec: crate::Spec::Droplet {
+                source: source.into(),
+                runtime: crate::SpecRuntime {
+                    env,
+                    filesystem: runtime
+                        .filesystem
+                        .map(|mounts| mounts.into_iter().map(Into::into).collect()),
+                    network: runtime.network.map(Into::into),
+                    resources: runtime.resources.into(),
+                },
+                secrets: secrets.into_iter().map(Into::into).collect(),
+                triggers: triggers.map(|triggers| triggers.into_iter().map(Into::into).collect()),
+                retry: retry.map(Into::into),
+                entrypoint,
+            },
+        })
+    }
+}
+
+/// Fields that do not exist in `hm/v1` are dropped.
+impl From<crate::RootConfig> for RootConfig {
+    fn from(config: crate::RootConfig) -> Self {
+        let crate::Spec::Droplet {
+            source,
+            runtime,
+            secrets,
+            triggers,
+            retry,
+            entrypoint,
+        } = config.spec;
+
+        let env = runtime
+            .env
+            .into_iter()
+            .map(|(name, value)| RuntimeEnv { name, value })
+            .collect::<Vec<_>>();
+
+        Self {
+            api_version: ApiVersion::V1,
+            metadata: Metadata {
+                name: config.metadata.name,
+            },
+            spec: Spec::Droplet {
+                source: source.into(),
+                runtime: SpecRuntime {
+                    env: (!env.is_empty()).then_some(env),
+                    filesystem: runtime
+                        .filesystem
+                        .map(|mounts| mounts.into_iter().map(Into::into).collect()),
+                    network: runtime.network.map(Into::into),
+                    resources: runtime.resources.into(),
+                },
+                secrets: secrets.into_iter().map(Into::into).collect(),
+                triggers: triggers.map(|triggers| triggers.into_iter().map(Into::into).collect()),
+                retry: retry.map(Into::into),
+                entrypoint,
+            },
+        }
+    }
+}
+
+impl From<SpecSource> for crate::SpecSource {
+    fn from(source: SpecSource) -> Self {
+        match source {
+            SpecSource::File { path } => Self::File { path, watch: false },
+            SpecSource::Blob { digest } => Self::Blob { digest },
+            SpecSource::Url {
+                url,
+                sha256,
+                pull_secret,
+            } => Self::Url {
+                url,
+                sha256,
+                pull_secret,
+            },
+            SpecSource::Oci {
+                reference,
+                pull_secret,
+            } => Self::Oci {
+                reference,
+                pull_secret,
+            },
+            SpecSource::Inline { base64 } => Self::Inline { base64 },
+            SpecSource::Wat { text } => Self::Wat { text },
+        }
+    }
+}
+
+impl From<crate::SpecSource> for SpecSource {
+    fn from(source: crate::SpecSource) -> Self {
+        match source {
+            crate::SpecSource::File { path, .. } => Self::File { path },
+            crate::SpecSource::Blob { digest } => Self::Blob { digest },
+            crate::SpecSource::Url {
+                url,
+                sha256,
+                pull_secret,
+            } => Self::Url {
+                url,
+                sha256,
+                pull_secret,
+            },
+            crate::SpecSource::Oci {
+                reference,
+                pull_secret,
+            } => Self::Oci {
+                reference,
+                pull_secret,
+            },
+            crate::SpecSource::Inline { base64 } => Self::Inline { base64 },
+            crate::SpecSource::Wat { text } => Self::Wat { text },
+        }
+    }
+}
+
+impl From<SpecSecret> for crate::SpecSecret {
+    fn from(secret: SpecSecret) -> Self {
+        Self {
+            name: secret.name,
+            mount_path: secret.mount_path,
+        }
+    }
+}
+
+impl From<crate::SpecSecret> for SpecSecret {
+    fn from(secret: crate::SpecSecret) -> Self {
+        Self {
+            name: secret.name,
+            mount_path: secret.mount_path,
+        }
+    }
+}
+
+impl From<SpecTrigger> for crate::SpecTrigger {
+    fn from(trigger: SpecTrigger) -> Self {
+        Self {
+            name: trigger.name,
+            schedule: match trigger.schedule {
+                TriggerSchedule::Cron(cron) => crate::TriggerSchedule::Cron(cron),
+                TriggerSchedule::Interval(interval) => crate::TriggerSchedule::Interval(interval),
+            },
+            timezone: trigger.timezone,
+            jitter: trigger.jitter,
+            catch_up: match trigger.catch_up {
+                TriggerCatchUp::Skip => crate::TriggerCatchUp::Skip,
+                TriggerCatchUp::RunOnce => crate::TriggerCatchUp::RunOnce,
+            },
+            args: trigger.args,
+        }
+    }
+}
+
+impl From<crate::SpecTrigger> for SpecTrigger {
+    fn from(trigger: crate::SpecTrigger) -> Self {
+        Self {
+            name: trigger.name,
+            schedule: match trigger.schedule {
+                crate::TriggerSchedule::Cron(cron) => TriggerSchedule::Cron(cron),
+                crate::TriggerSchedule::Interval(interval) => TriggerSchedule::Interval(interval),
+            },
+            timezone: trigger.timezone,
+            jitter: trigger.jitter,
+            catch_up: match trigger.catch_up {
+                crate::TriggerCatchUp::Skip => TriggerCatchUp::Skip,
+                crate::TriggerCatchUp::RunOnce => TriggerCatchUp::RunOnce,
+            },
+            args: trigger.args,
+        }
+    }
+}
+
+impl From<SpecRetry> for crate::SpecRetry {
+    fn from(retry: SpecRetry) -> Self {
+        Self {
+            max_attempts: retry.max_attempts,
+            backoff: crate::RetryBackoff {
+                initial: retry.backoff.initial,
+                multiplier: retry.backoff.multiplier,
+                max: retry.backoff.max,
+            },
+            retry_on: retry
+                .retry_on
+                .into_iter()
+                .map(|class| match class {
+                    RetryErrorClass::Trap => crate::RetryErrorClass::Trap,
+                    RetryErrorClass::Timeout => crate::RetryErrorClass::Timeout,
+                    RetryErrorClass::Host => crate::RetryErrorClass::Host,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::SpecRetry> for SpecRetry {
+    fn from(retry: crate::SpecRetry) -> Self {
+        Self {
+            max_attempts: retry.max_attempts,
+            backoff: RetryBackoff {
+                initial: retry.backoff.initial,
+                multiplier: retry.backoff.multiplier,
+                max: retry.backoff.max,
+            },
+            retry_on: retry
+                .retry_on
+                .into_iter()
+                .map(|class| match class {
+                    crate::RetryErrorClass::Trap => RetryErrorClass::Trap,
+                    crate::RetryErrorClass::Timeout => RetryErrorClass::Timeout,
+                    crate::RetryErrorClass::Host => RetryErrorClass::Host,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<RuntimeFilesystemMount> for crate::RuntimeFilesystemMount {
+    fn from(mount: RuntimeFilesystemMount) -> Self {
+        Self {
+            name: mount.name,
+            guest_path: mount.guest_path,
+            host_path: mount.host_path,
+            read_only: mount.read_only,
+        }
+    }
+}
+
+impl From<crate::RuntimeFilesystemMount> for RuntimeFilesystemMount {
+    fn from(mount: crate::RuntimeFilesystemMount) -> Self {
+        Self {
+            name: mount.name,
+            guest_path: mount.guest_path,
+            host_path: mount.host_path,
+            read_only: mount.read_only,
+        }
+    }
+}
+
+impl From<RuntimeNetwork> for crate::RuntimeNetwork {
+    fn from(network: RuntimeNetwork) -> Self {
+        Self {
+            allowed_hosts: network.allowed_hosts,
+        }
+    }
+}
+
+impl From<crate::RuntimeNetwork> for RuntimeNetwork {
+    fn from(network: crate::RuntimeNetwork) -> Self {
+        Self {
+            allowed_hosts: network.allowed_hosts,
+        }
+    }
+}
+
+impl From<RuntimeResources> for crate::RuntimeResources {
+    fn from(resources: RuntimeResources) -> Self {
+        Self {
+            memory: resources.memory,
+            cpu: resources.cpu,
+            table_elements: None,
+            instances: None,
+            tables: None,
+            memories: None,
+            trap_on_oom: false,
+        }
+    }
+}
+
+impl From<crate::RuntimeResources> for RuntimeResources {
+    fn from(resources: crate::RuntimeResources) -> Self {
+        Self {
+            memory: resources.memory,
+            cpu: resources.cpu,
+        }
+    }
+}
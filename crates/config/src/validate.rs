@@ -0,0 +1,436 @@
+use std::{collections::BTreeSet, fmt};
+
+use thiserror::Error;
+
+use crate::{RootConfig, Spec, SpecSource, quantity::ResourceQuantity};
+
+/// Longest droplet name, as for a DNS label.
+const MAX_NAME_LEN: usize = 63;
+/// Longest label value, and name part of a label key.
+const MAX_LABEL_LEN: usize = 63;
+/// Longest prefix of a label key, as for a DNS subdomain.
+const MAX_LABEL_PREFIX_LEN: usize = 253;
+/// Names accepted by the validator, as a regular expression.
+pub const NAME_PATTERN: &str = "^[a-z0-9]([a-z0-9-]{0,61}[a-z0-9])?$";
+
+/// A single problem found in a config, located by its YAML path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// Path to the offending field, e.g. `spec.runtime.env[1].name`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Every problem found in a config, one per line when displayed.
+#[derive(Debug, Clone, Error)]
+pub struct ValidationError {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issues = self
+            .issues
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "{}", issues.join("\n"))
+    }
+}
+
+/// Checks a config, already migrated to the latest schema, without touching the daemon.
+///
+/// Secret references are only checked when `secrets`, the set of secrets that
+/// exist, is known.
+pub fn validate(
+    config: &RootConfig,
+    secrets: Option<&BTreeSet<String>>,
+) -> Result<(), ValidationError> {
+    let mut issues = Issues::default();
+
+    if let Err(message) = check_name(&config.metadata.name) {
+        issues.push("metadata.name", message);
+    }
+    if let Err(message) = check_name(&config.metadata.namespace) {
+        issues.push("metadata.namespace", message);
+    }
+    for (key, value) in &config.metadata.labels {
+        if let Err(message) = check_label_key(key).and_then(|()| check_label_value(value)) {
+            issues.push(format!("metadata.labels.{key}"), message);
+        }
+    }
+
+    let Spec::Droplet {
+        source,
+        runtime,
+        secrets: mounts,
+        ..
+    } = &config.spec;
+
+    let pull_secret = match source {
+        SpecSource::Url { pull_secret, .. } | SpecSource::Oci { pull_secret, .. } => {
+            pull_secret.as_deref()
+        }
+        _ => None,
+    };
+    if let (Some(name), Some(secrets)) = (pull_secret, secrets)
+        && !secrets.contains(name)
+    {
+        issues.push(
+            "spec.source.pull_secret",
+            format!("Secret \"{name}\" does not exist."),
+        );
+    }
+
+    if let Err(e) = ResourceQuantity::try_from(runtime.resources.memory.as_str()) {
+        issues.push(
+            "spec.runtime.resources.memory",
+            format!(
+                "Invalid memory quantity \"{}\": {e}",
+                runtime.resources.memory
+            ),
+        );
+    }
+    if let Err(e) = ResourceQuantity::parse_cpu(&runtime.resources.cpu) {
+        issues.push(
+            "spec.runtime.resources.cpu",
+            format!("Invalid CPU quantity \"{}\": {e}", runtime.resources.cpu),
+        );
+    }
+
+    if runtime.resources.instances == Some(0) {
+        issues.push(
+            "spec.runtime.resources.instances",
+            "At least one instance is needed to run the droplet.",
+        );
+    }
+
+    for name in runtime.env.keys() {
+        if name.is_empty() || name.contains('=') {
+            issues.push(
+                format!("spec.runtime.env.{name}"),
+                format!("Invalid environment variable name \"{name}\"."),
+            );
+        }
+    }
+
+    for (i, mount) in runtime.filesystem.iter().flatten().enumerate() {
+        if !mount.guest_path.starts_with('/') {
+            issues.push(
+                format!("spec.runtime.filesystem[{i}].guest_path"),
+                format!("Mount path \"{}\" is not absolute.", mount.guest_path),
+            );
+        }
+    }
+
+    for (i, secret) in mounts.iter().enumerate() {
+        if let Some(secrets) = secrets
+            && !secrets.contains(&secret.name)
+        {
+            issues.push(
+                format!("spec.secrets[{i}].name"),
+                format!("Secret \"{}\" does not exist.", secret.name),
+            );
+        }
+        if !secret.mount_path.to_string_lossy().starts_with('/') {
+            issues.push(
+                format!("spec.secrets[{i}].mount_path"),
+                format!(
+                    "Mount path \"{}\" is not absolute.",
+                    secret.mount_path.display()
+                ),
+            );
+        }
+    }
+
+    issues.finish()
+}
+
+/// Names end up in file names and metric labels, so they follow DNS label rules.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!(
+            "Name must be between 1 and {MAX_NAME_LEN} characters long."
+        ));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(format!(
+            "Invalid name \"{name}\", only lowercase letters, digits and '-' are allowed."
+        ));
+    }
+    if name.starts_with('-') || name.ends_with('-') {
+        return Err(format!(
+            "Invalid name \"{name}\", must start and end with a letter or digit."
+        ));
+    }
+
+    Ok(())
+}
+
+/// Label keys are `[prefix/]name`, where the prefix is a DNS subdomain such as `hotmist.dev`.
+fn check_label_key(key: &str) -> Result<(), String> {
+    let name = match key.split_once('/') {
+        Some((prefix, name)) => {
+            if prefix.is_empty()
+                || prefix.len() > MAX_LABEL_PREFIX_LEN
+                || !prefix.split('.').all(|part| check_name(part).is_ok())
+            {
+                return Err(format!(
+                    "Invalid label key prefix \"{prefix}\", must be a DNS subdomain."
+                ));
+            }
+            name
+        }
+        None => key,
+    };
+
+    if name.is_empty() {
+        return Err(format!(
+            "Invalid label key \"{key}\", name must not be empty."
+        ));
+    }
+    check_label_value(name).map_err(|_| format!("Invalid label key \"{key}\"."))
+}
+
+/// Label values are up to 63 alphanumerics, '-', '_' or '.', starting and ending with an alphanumeric.
+fn check_label_value(value: &str) -> Result<(), String> {
+    if value.len() > MAX_LABEL_LEN {
+        return Err(format!(
+            "Label value \"{value}\" is longer than {MAX_LABEL_LEN} characters."
+        ));
+    }
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        || value.starts_with(|c: char| !c.is_ascii_alphanumeric())
+        || value.ends_with(|c: char| !c.is_ascii_alphanumeric())
+    {
+        return Err(format!(
+            "Invalid label value \"{value}\", only alphanumerics, '-', '_' and '.' are allowed, \
+             starting and ending with an alphanumeric."
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Issues(Vec<ValidationIssue>);
+
+impl Issues {
+    fn push(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.0.push(ValidationIssue {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn finish(self) -> Result<(), ValidationError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError { issues: self.0 })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::versions;
+
+    fn config() -> Value {
+        json!({
+            "api_version": "hm/v2",
+            "metadata": { "name": "hello" },
+            "kind": "Droplet",
+            "spec": {
+                "source": { "path": "hello.wasm" },
+                "runtime": { "resources": { "memory": "64Mi", "cpu": "1" } },
+            },
+        })
+    }
+
+    /// Issues found in `config`; secret references are checked against `secrets` if given.
+    fn issues(config: Value, secrets: Option<&[&str]>) -> Vec<ValidationIssue> {
+        let secrets = secrets.map(|names| names.iter().map(ToString::to_string).collect());
+        let config = versions::migrate(config).unwrap();
+        validate(&config, secrets.as_ref()).map_or_else(|e| e.issues, |()| vec![])
+    }
+
+    fn paths(config: Value, secrets: Option<&[&str]>) -> Vec<String> {
+        issues(config, secrets)
+            .into_iter()
+            .map(|issue| issue.path)
+            .collect()
+    }
+
+    #[test]
+    fn valid_config_has_no_issues() {
+        assert_eq!(paths(config(), Some(&[])), Vec::<String>::new());
+    }
+
+    #[test]
+    fn names_are_dns_labels() {
+        let longest = "a".repeat(MAX_NAME_LEN);
+        let too_long = "a".repeat(MAX_NAME_LEN + 1);
+        for (name, valid) in [
+            ("a", true),
+            ("hello-world", true),
+            ("0droplet9", true),
+            (longest.as_str(), true),
+            ("", false),
+            (too_long.as_str(), false),
+            ("Hello", false),
+            ("hello_world", false),
+            ("hello.world", false),
+            ("-hello", false),
+            ("hello-", false),
+        ] {
+            assert_eq!(check_name(name).is_ok(), valid, "{name:?}");
+
+            let mut config = config();
+            config["metadata"]["name"] = name.into();
+            config["metadata"]["namespace"] = name.into();
+            let expected: &[&str] = if valid {
+                &[]
+            } else {
+                &["metadata.name", "metadata.namespace"]
+            };
+            assert_eq!(paths(config, None), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn labels_follow_key_and_value_rules() {
+        let long_value = "v".repeat(MAX_LABEL_LEN + 1);
+        let long_prefix = format!("{}/app", "a".repeat(MAX_LABEL_PREFIX_LEN + 1));
+        for (key, value, valid) in [
+            ("app", "web", true),
+            ("hotmist.dev/team", "payments", true),
+            ("app.kubernetes_io-name", "v1.2_3-rc", true),
+            ("app", "", true),
+            ("", "web", false),
+            ("hotmist.dev/", "web", false),
+            ("/app", "web", false),
+            ("Hot_Mist/app", "web", false),
+            (long_prefix.as_str(), "web", false),
+            ("-app", "web", false),
+            ("app", "-web", false),
+            ("app", "web.", false),
+            ("app", "web server", false),
+            ("app", long_value.as_str(), false),
+        ] {
+            let mut config = config();
+            config["metadata"]["labels"] = json!({ key: value });
+            let expected = if valid {
+                vec![]
+            } else {
+                vec![format!("metadata.labels.{key}")]
+            };
+            assert_eq!(paths(config, None), expected, "{key:?}={value:?}");
+        }
+    }
+
+    #[test]
+    fn quantities_must_parse() {
+        for (memory, cpu, expected) in [
+            ("64Mi", "1", &[][..]),
+            ("1.5G", "500m", &[]),
+            ("128Ki", ".25", &[]),
+            ("64", "1", &["spec.runtime.resources.memory"]),
+            ("64MB", "1", &["spec.runtime.resources.memory"]),
+            ("lots", "1", &["spec.runtime.resources.memory"]),
+            ("16Ei", "1", &["spec.runtime.resources.memory"]),
+            ("64Mi", "0", &["spec.runtime.resources.cpu"]),
+            ("64Mi", "0m", &["spec.runtime.resources.cpu"]),
+            ("64Mi", "1 core", &["spec.runtime.resources.cpu"]),
+            ("64Mi", "-1", &["spec.runtime.resources.cpu"]),
+            (
+                "",
+                "",
+                &[
+                    "spec.runtime.resources.memory",
+                    "spec.runtime.resources.cpu",
+                ],
+            ),
+        ] {
+            let mut config = config();
+            config["spec"]["runtime"]["resources"] = json!({ "memory": memory, "cpu": cpu });
+            assert_eq!(paths(config, None), expected, "{memory:?} {cpu:?}");
+        }
+    }
+
+    #[test]
+    fn mount_paths_must_be_absolute() {
+        let mut config = config();
+        config["spec"]["runtime"]["filesystem"] = json!([
+            { "name": "data", "guest_path": "/data", "host_path": "data", "read_only": true },
+            { "name": "cache", "guest_path": "cache", "host_path": "/tmp", "read_only": false },
+        ]);
+        config["spec"]["secrets"] = json!([
+            { "name": "token", "mount_path": "/run/token" },
+            { "name": "key", "mount_path": "run/key" },
+        ]);
+
+        let issues = issues(config, None);
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "spec.runtime.filesystem[1].guest_path: Mount path \"cache\" is not absolute.",
+                "spec.secrets[1].mount_path: Mount path \"run/key\" is not absolute.",
+            ]
+        );
+    }
+
+    #[test]
+    fn secret_references_are_checked_when_secrets_are_known() {
+        let mut config = config();
+        config["spec"]["source"] = json!({
+            "url": "https://example.com/hello.wasm",
+            "sha256": "00",
+            "pull_secret": "registry",
+        });
+        config["spec"]["secrets"] = json!([
+            { "name": "token", "mount_path": "/run/token" },
+            { "name": "key", "mount_path": "/run/key" },
+        ]);
+
+        assert_eq!(paths(config.clone(), None), Vec::<String>::new());
+        assert_eq!(
+            paths(config.clone(), Some(&["token"])),
+            ["spec.source.pull_secret", "spec.secrets[1].name"]
+        );
+        assert_eq!(
+            paths(config, Some(&["registry", "token", "key"])),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn every_issue_is_reported_with_its_path() {
+        let mut config = config();
+        config["metadata"]["name"] = "Hello".into();
+        config["spec"]["runtime"]["env"] = json!({ "A=B": "1", "OK": "2" });
+        config["spec"]["runtime"]["resources"]["instances"] = 0.into();
+
+        let error = validate(&versions::migrate(config).unwrap(), None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "metadata.name: Invalid name \"Hello\", only lowercase letters, digits and '-' are allowed.\n\
+             spec.runtime.resources.instances: At least one instance is needed to run the droplet.\n\
+             spec.runtime.env.A=B: Invalid environment variable name \"A=B\"."
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{ApiVersion, RootConfig, v1};
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("api_version: Missing API version.")]
+    MissingVersion,
+    #[error(
+        "api_version: Unknown API version \"{0}\", expected one of: {supported}.",
+        supported = supported_versions()
+    )]
+    UnknownVersion(String),
+    #[error("Invalid {version} config: {source}")]
+    Invalid {
+        version: ApiVersion,
+        source: serde_json::Error,
+    },
+    #[error("spec.runtime.env[{index}].name: Duplicate environment variable \"{name}\".")]
+    DuplicateEnv { index: usize, name: String },
+    #[error(
+        "metadata.namespace: Config declares namespace {declared}, but is placed in {namespace}."
+    )]
+    NamespaceMismatch { declared: String, namespace: String },
+}
+
+/// Reads the `api_version` of a config in any supported schema.
+pub fn api_version(config: &Value) -> Result<ApiVersion, MigrationError> {
+    config
+        .get("api_version")
+        .and_then(Value::as_str)
+        .ok_or(MigrationError::MissingVersion)?
+        .parse()
+}
+
+/// Parses a config in any supported schema and upgrades it to the latest one.
+pub fn migrate(config: Value) -> Result<RootConfig, MigrationError> {
+    let version = api_version(&config)?;
+    let invalid = |source| MigrationError::Invalid { version, source };
+
+    match version {
+        ApiVersion::V1 => serde_json::from_value::<v1::RootConfig>(config)
+            .map_err(invalid)?
+            .try_into(),
+        ApiVersion::V2 => serde_json::from_value(config).map_err(invalid),
+    }
+}
+
+/// Like [`migrate`], placing the config in `namespace`.
+///
+/// Older schemas have no namespace, so it is set after migrating; a config declaring
+/// another namespace is refused.
+pub fn migrate_into(config: Value, namespace: &str) -> Result<RootConfig, MigrationError> {
+    if let Some(declared) = config.pointer("/metadata/namespace")
+        && declared.as_str() != Some(namespace)
+    {
+        return Err(MigrationError::NamespaceMismatch {
+            declared: declared.to_string(),
+            namespace: namespace.to_string(),
+        });
+    }
+
+    let mut config = migrate(config)?;
+    config.metadata.namespace = namespace.to_string();
+
+    Ok(config)
+}
+
+/// Renders a config in the schema of `version`.
+///
+/// Fields that do not exist in older schemas are dropped.
+pub fn convert(config: RootConfig, version: ApiVersion) -> Result<Value, serde_json::Error> {
+    match version {
+        ApiVersion::V1 => serde_json::to_value(v1::RootConfig::from(config)),
+        ApiVersion::V2 => serde_json::to_value(config),
+    }
+}
+
+/// JSON Schema describing config files of `version`.
+pub fn schema(version: ApiVersion) -> Value {
+    let mut schema = match version {
+        ApiVersion::V1 => schemars::schema_for!(v1::RootConfig),
+        ApiVersion::V2 => schemars::schema_for!(RootConfig),
+    };
+    schema.insert(
+        "title".to_string(),
+        format!("Hot Mist {version} config").into(),
+    );
+
+    // Both schemas share the version enum; each only accepts its own version.
+    let mut schema = schema.to_value();
+    if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+        properties.insert(
+            "api_version".to_string(),
+            serde_json::json!({ "const": version.as_str() }),
+        );
+    }
+
+    schema
+}
+
+fn supported_versions() -> String {
+    ApiVersion::ALL
+        .iter()
+        .map(ApiVersion::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{Spec, SpecSource};
+
+    fn v1_config() -> Value {
+        json!({
+            "api_version": "hm/v1",
+            "metadata": { "name": "hello" },
+            "kind": "Droplet",
+            "spec": {
+                "source": { "path": "hello.wasm" },
+                "runtime": {
+                    "env": [{ "name": "GREETING", "value": "hi" }],
+                    "resources": { "memory": "64Mi", "cpu": "1" },
+                },
+                "secrets": [],
+            },
+        })
+    }
+
+    #[test]
+    fn v1_configs_migrate_to_the_latest_schema() {
+        let config = migrate(v1_config()).unwrap();
+        assert_eq!(config.api_version, ApiVersion::LATEST);
+        assert_eq!(config.metadata.id(), "default/hello");
+
+        let Spec::Droplet {
+            source, runtime, ..
+        } = &config.spec;
+        assert!(matches!(source, SpecSource::File { watch: false, .. }));
+        assert_eq!(runtime.env["GREETING"], "hi");
+        assert_eq!(runtime.resources.instances, None);
+    }
+
+    #[test]
+    fn v1_duplicate_env_is_refused() {
+        let mut config = v1_config();
+        config["spec"]["runtime"]["env"] = json!([
+            { "name": "A", "value": "1" },
+            { "name": "A", "value": "2" },
+        ]);
+
+        assert!(matches!(
+            migrate(config),
+            Err(MigrationError::DuplicateEnv { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn converting_to_v1_drops_newer_fields() {
+        let mut config = migrate(v1_config()).unwrap();
+        config.metadata.namespace = "team".to_string();
+        config.metadata.labels.insert("app".into(), "hello".into());
+
+        let converted = convert(config, ApiVersion::V1).unwrap();
+        assert_eq!(converted["metadata"], json!({ "name": "hello" }));
+        assert_eq!(
+            converted["spec"]["runtime"]["resources"],
+            json!({ "memory": "64Mi", "cpu": "1" })
+        );
+        assert_eq!(converted["spec"]["source"], json!({ "path": "hello.wasm" }));
+    }
+
+    #[test]
+    fn migrate_into_places_configs_in_the_namespace() {
+        let config = migrate_into(v1_config(), "team").unwrap();
+        assert_eq!(config.metadata.id(), "team/hello");
+
+        let mut declared = v1_config();
+        declared["api_version"] = "hm/v2".into();
+        declared["spec"]["runtime"]["env"] = json!({});
+        declared["metadata"]["namespace"] = "team".into();
+        assert_eq!(
+            migrate_into(declared.clone(), "team")
+                .unwrap()
+                .metadata
+                .id(),
+            "team/hello"
+        );
+        assert!(matches!(
+            migrate_into(declared, "other"),
+            Err(MigrationError::NamespaceMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+use config::{ApiVersion, versions};
+
+#[test]
+fn hello_world_example_matches_schema() {
+    let schema = versions::schema(ApiVersion::LATEST);
+    let validator = jsonschema::validator_for(&schema).expect("generated schema is valid");
+
+    let example = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../examples/hello_world.yaml"
+    ))
+    .unwrap();
+    let example: serde_json::Value = serde_yaml::from_str(&example).unwrap();
+
+    let errors: Vec<_> = validator
+        .iter_errors(&example)
+        .map(|e| format!("{}: {e}", e.instance_path()))
+        .collect();
+    assert!(errors.is_empty(), "{errors:#?}");
+}
+
+#[test]
+fn cpu_pattern_accepts_what_parse_cpu_accepts() {
+    let validator = jsonschema::validator_for(&serde_json::json!({
+        "type": "string",
+        "pattern": config::quantity::CPU_PATTERN,
+    }))
+    .unwrap();
+
+    for value in [
+        "1", "0.5", ".5", "1.", "100m", "0.1m", "007", "0", "0m", "0.0", ".0m", "00", "", "m", ".",
+        "-1", "1.5.2", "1e3", " 1", "1 ",
+    ] {
+        let parsed = config::quantity::ResourceQuantity::parse_cpu(value).is_ok();
+        assert_eq!(validator.is_valid(&value.into()), parsed, "{value:?}");
+    }
+}
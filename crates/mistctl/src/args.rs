@@ -1,9 +1,13 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use config::Metadata;
 
 #[derive(Debug, Parser)]
 pub struct Args {
+    /// Namespace to operate in.
+    #[arg(short = 'n', long, global = true, default_value = Metadata::DEFAULT_NAMESPACE)]
+    pub namespace: String,
     #[command(subcommand)]
     pub command: Command,
 }
@@ -14,6 +18,103 @@ pub enum Command {
         #[command(subcommand)]
         command: DropletCommand,
     },
+    Trigger {
+        #[command(subcommand)]
+        command: TriggerCommand,
+    },
+    Job {
+        #[command(subcommand)]
+        command: JobCommand,
+    },
+    DeadLetter {
+        #[command(subcommand)]
+        command: DeadLetterCommand,
+    },
+    /// Manage namespaces; requires the daemon's admin token.
+    Namespace {
+        #[command(subcommand)]
+        command: NamespaceCommand,
+    },
+    /// Show the node's resource budget and what running invocations reserve from it.
+    Admission,
+    /// Show what a component or module imports and exports without deploying it.
+    Inspect {
+        #[arg(index = 1)]
+        file: PathBuf,
+    },
+    /// Check droplet configs for mistakes without contacting the daemon.
+    Validate {
+        #[arg(index = 1, required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Print a droplet config converted to another API version.
+    Convert {
+        #[arg(index = 1)]
+        file: PathBuf,
+        #[arg(long, default_value = "hm/v2")]
+        to: String,
+    },
+    /// Create, update and optionally prune droplets to match a set of config files.
+    Apply {
+        /// Config file, or directory of `.yaml`/`.yml` files; each may hold several documents.
+        #[arg(short = 'f', long = "filename", required = true)]
+        paths: Vec<PathBuf>,
+        /// Print the changes as a diff without applying them.
+        #[arg(long)]
+        dry_run: bool,
+        /// Delete droplets matching --selector that are not in the applied files.
+        #[arg(long, requires = "selector")]
+        prune: bool,
+        /// Label selector limiting --prune, e.g. `team=payments,env!=prod`.
+        #[arg(short = 'l', long)]
+        selector: Option<String>,
+    },
+    /// Run a droplet config once in this process, without a daemon.
+    #[cfg(feature = "local")]
+    Run {
+        #[arg(index = 1)]
+        file: PathBuf,
+        /// Arguments for the entrypoint as JSON, either an array or an object keyed by parameter name.
+        #[arg(long)]
+        args: Option<String>,
+        /// Secret the droplet references, read from a file; may be repeated.
+        #[arg(long = "secret", value_name = "NAME=FILE")]
+        secrets: Vec<String>,
+    },
+    /// Create a droplet crate with its WIT world and config from a template.
+    Init {
+        #[arg(index = 1)]
+        name: String,
+        #[arg(long, value_enum, default_value_t = Template::RustHandler)]
+        template: Template,
+    },
+    /// Build the crate in the current directory, deploy it and execute it on every change.
+    Dev {
+        /// Droplet config whose source points at the built binary.
+        #[arg(short = 'f', long = "filename", default_value = "droplet.yaml")]
+        file: PathBuf,
+        /// Arguments for the entrypoint as JSON, either an array or an object keyed by parameter name.
+        #[arg(long)]
+        args: Option<String>,
+        /// Build with the dev profile instead of --release.
+        #[arg(long)]
+        debug: bool,
+    },
+    /// Print the JSON Schema of droplet configs, for editor completion and validation.
+    Schema {
+        #[arg(long, default_value = "hm/v2")]
+        api_version: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Template {
+    /// Function taking and returning a string.
+    RustHandler,
+    /// Handler taking a request record and returning a response record.
+    RustHttp,
+    /// Interface of key-value lookups over the droplet's environment.
+    RustKv,
 }
 
 #[derive(Debug, Subcommand)]
@@ -22,8 +123,121 @@ pub enum DropletCommand {
         #[arg(index = 1)]
         config: PathBuf,
     },
+    /// List droplets and their labels.
+    List {
+        /// Only list droplets matching this label selector, e.g. `team=payments,env!=prod`.
+        #[arg(short = 'l', long)]
+        selector: Option<String>,
+    },
+    /// Delete a droplet by name, or every droplet matching --selector.
+    Delete {
+        #[arg(
+            index = 1,
+            required_unless_present = "selector",
+            conflicts_with = "selector"
+        )]
+        name: Option<String>,
+        #[arg(short = 'l', long)]
+        selector: Option<String>,
+    },
     Execute {
         #[arg(index = 1)]
         name: String,
+        /// Arguments for the entrypoint as JSON, either an array or an object keyed by parameter name.
+        #[arg(long)]
+        args: Option<String>,
+        /// Queue the invocation and print its job id instead of waiting for it.
+        #[arg(long = "async")]
+        detach: bool,
+        /// URL the finished job is POSTed to (requires --async).
+        #[arg(long, requires = "detach")]
+        webhook: Option<String>,
+    },
+    /// Show past invocations of a droplet, newest first.
+    History {
+        #[arg(index = 1)]
+        name: String,
+        /// Only show invocations with this outcome (succeeded, failed).
+        #[arg(long)]
+        outcome: Option<String>,
+        /// Only show invocations from this source (api, job, trigger).
+        #[arg(long)]
+        source: Option<String>,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Summarise the duration and resource usage of a droplet's recent invocations.
+    Stats {
+        #[arg(index = 1)]
+        name: String,
+        /// Number of most recent invocations to summarise.
+        #[arg(long, default_value_t = 100)]
+        limit: usize,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TriggerCommand {
+    /// List scheduled triggers and their upcoming runs.
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum JobCommand {
+    /// List asynchronous invocations.
+    List,
+    /// Show a single job.
+    Get {
+        #[arg(index = 1)]
+        id: String,
+    },
+    /// Poll a job until it finishes and print its output.
+    Wait {
+        #[arg(index = 1)]
+        id: String,
+        /// Seconds between polls.
+        #[arg(long, default_value_t = 1)]
+        interval: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DeadLetterCommand {
+    /// List invocations that failed after exhausting their retry policy.
+    List,
+    /// Show the attempts and errors of a dead-lettered invocation.
+    Get {
+        #[arg(index = 1)]
+        id: String,
+    },
+    /// Queue a dead-lettered invocation again as a new job.
+    Replay {
+        #[arg(index = 1)]
+        id: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NamespaceCommand {
+    /// Create a namespace and print its access token.
+    Create {
+        #[arg(index = 1)]
+        name: String,
+        /// Total memory the namespace's droplets may declare, e.g. `2Gi`.
+        #[arg(long)]
+        memory: Option<String>,
+        /// Maximum number of droplets.
+        #[arg(long)]
+        droplets: Option<usize>,
+        /// Maximum number of invocations running at once.
+        #[arg(long)]
+        concurrency: Option<usize>,
+    },
+    /// List namespaces and their quotas.
+    List,
+    /// Delete a namespace with all of its droplets and secrets.
+    Delete {
+        #[arg(index = 1)]
+        name: String,
     },
 }
@@ -0,0 +1,56 @@
+use config::api::AdmissionReport;
+
+use crate::commands::{DAEMON_URL, client};
+
+pub async fn admission_cmd() -> anyhow::Result<()> {
+    let report = get_admission().await?;
+
+    let limit = |limit: Option<u64>, unit: &str| {
+        limit.map_or_else(|| "unbounded".to_string(), |limit| format!("{limit}{unit}"))
+    };
+    println!(
+        "Memory: {} / {} reserved",
+        report.reserved.memory,
+        limit(report.budget.memory, "")
+    );
+    println!(
+        "CPU:    {}m / {} reserved",
+        report.reserved.cpu,
+        limit(report.budget.cpu, "m")
+    );
+    println!("Queued: {}", report.queued);
+    println!();
+
+    println!(
+        "{:<40} {:>12} {:>8} {:<28}",
+        "DROPLET", "MEMORY", "CPU", "SINCE"
+    );
+    for reservation in report.reservations {
+        println!(
+            "{:<40} {:>12} {:>8} {:<28}",
+            reservation.droplet,
+            reservation.resources.memory,
+            format!("{}m", reservation.resources.cpu),
+            reservation.since.to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn get_admission() -> anyhow::Result<AdmissionReport> {
+    let client = client()?;
+    let request = client.get(format!("{DAEMON_URL}/ctr/admission"));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to get admission report ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
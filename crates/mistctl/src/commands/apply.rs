@@ -0,0 +1,244 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{self, Path, PathBuf},
+};
+
+use anyhow::Context;
+use config::{RootConfig, Spec, SpecSource, api, selector::LabelSelector, validate, versions};
+use serde::Deserialize;
+use similar::TextDiff;
+
+use crate::commands::droplet::{create_droplet, delete_droplet, list_droplets, upload_blob};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Create,
+    Update,
+    Unchanged,
+    Prune,
+}
+
+impl Action {
+    fn past_tense(self) -> &'static str {
+        match self {
+            Self::Create => "created",
+            Self::Update => "configured",
+            Self::Unchanged => "unchanged",
+            Self::Prune => "pruned",
+        }
+    }
+}
+
+/// A droplet config read from disk, with its local binary replaced by the blob it uploads to.
+struct Desired {
+    config: RootConfig,
+    /// Local binary to upload before the config is applied.
+    upload: Option<PathBuf>,
+}
+
+struct Change {
+    name: String,
+    action: Action,
+    current: Option<RootConfig>,
+    desired: Option<Desired>,
+}
+
+/// Brings the namespace's droplets in line with the configs in `paths`.
+pub async fn apply_cmd(
+    namespace: &str,
+    paths: &[PathBuf],
+    dry_run: bool,
+    prune: bool,
+    selector: Option<&str>,
+) -> anyhow::Result<()> {
+    let selector = selector.map(str::parse::<LabelSelector>).transpose()?;
+
+    let mut desired = BTreeMap::new();
+    for file in paths
+        .iter()
+        .map(|path| config_files(path))
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+    {
+        for config in read_documents(&file, namespace)? {
+            let name = config.metadata.name.clone();
+            let config = resolve(config).with_context(|| format!("{}: {name}", file.display()))?;
+            if desired.insert(name.clone(), config).is_some() {
+                anyhow::bail!(
+                    "{}: droplet {name} is defined more than once.",
+                    file.display()
+                );
+            }
+        }
+    }
+
+    let mut current = list_droplets(namespace, None)
+        .await?
+        .into_iter()
+        .map(|config| (config.metadata.name.clone(), config))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut changes = vec![];
+    for (name, desired) in desired {
+        let current = current.remove(&name);
+        let action = match &current {
+            None => Action::Create,
+            Some(current) if same_config(current, &desired.config)? => Action::Unchanged,
+            Some(_) => Action::Update,
+        };
+        changes.push(Change {
+            name,
+            action,
+            current,
+            desired: Some(desired),
+        });
+    }
+    if prune && let Some(selector) = &selector {
+        for config in list_droplets(namespace, Some(selector)).await? {
+            if let Some(config) = current.remove(&config.metadata.name) {
+                changes.push(Change {
+                    name: config.metadata.name.clone(),
+                    action: Action::Prune,
+                    current: Some(config),
+                    desired: None,
+                });
+            }
+        }
+    }
+
+    for change in &changes {
+        if dry_run {
+            println!(
+                "droplet/{} {} (dry run)",
+                change.name,
+                change.action.past_tense()
+            );
+            if change.action != Action::Unchanged {
+                print!("{}", diff(change)?);
+            }
+            continue;
+        }
+
+        match (change.action, &change.desired) {
+            (Action::Create | Action::Update, Some(desired)) => {
+                if let Some(path) = &desired.upload {
+                    upload_blob(path).await?;
+                }
+                create_droplet(&desired.config).await?;
+            }
+            (Action::Prune, _) => delete_droplet(namespace, &change.name).await?,
+            _ => {}
+        }
+        println!("droplet/{} {}", change.name, change.action.past_tense());
+    }
+
+    let count = |action| changes.iter().filter(|c| c.action == action).count();
+    println!(
+        "{} created, {} updated, {} unchanged, {} pruned{}.",
+        count(Action::Create),
+        count(Action::Update),
+        count(Action::Unchanged),
+        count(Action::Prune),
+        if dry_run { " (dry run)" } else { "" }
+    );
+
+    Ok(())
+}
+
+/// The file itself, or the YAML files directly inside a directory, sorted by name.
+fn config_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = fs::read_dir(path)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    files.retain(|file| {
+        file.is_file()
+            && file
+                .extension()
+                .is_some_and(|extension| extension == "yaml" || extension == "yml")
+    });
+    files.sort();
+
+    Ok(files)
+}
+
+/// Reads every `---` separated config in the file, placed in `namespace`, migrated to the
+/// latest schema and validated.
+fn read_documents(file: &Path, namespace: &str) -> anyhow::Result<Vec<RootConfig>> {
+    let text = fs::read_to_string(file).with_context(|| file.display().to_string())?;
+
+    let mut configs = vec![];
+    for (index, document) in serde_yaml::Deserializer::from_str(&text).enumerate() {
+        let location = || format!("{} (document {})", file.display(), index + 1);
+
+        let value = serde_json::Value::deserialize(document).with_context(location)?;
+        if value.is_null() {
+            continue;
+        }
+        let config = versions::migrate_into(value, namespace).with_context(location)?;
+        validate::validate(&config, None).with_context(location)?;
+
+        configs.push(config);
+    }
+
+    Ok(configs)
+}
+
+/// Points a local `File` source at the blob it is uploaded to, so it compares equal to the
+/// config stored by the daemon when the binary has not changed. Watched sources are read by
+/// the daemon itself and only made absolute.
+fn resolve(mut config: RootConfig) -> anyhow::Result<Desired> {
+    let Spec::Droplet { source, .. } = &mut config.spec;
+    let SpecSource::File { path, watch } = source else {
+        return Ok(Desired {
+            config,
+            upload: None,
+        });
+    };
+    if *watch {
+        *path = path::absolute(&*path)?;
+        return Ok(Desired {
+            config,
+            upload: None,
+        });
+    }
+
+    let path = path.clone();
+    let bytes = fs::read(&path).with_context(|| path.display().to_string())?;
+    *source = SpecSource::Blob {
+        digest: api::digest(&bytes),
+    };
+
+    Ok(Desired {
+        config,
+        upload: Some(path),
+    })
+}
+
+fn same_config(a: &RootConfig, b: &RootConfig) -> anyhow::Result<bool> {
+    Ok(serde_json::to_value(a)? == serde_json::to_value(b)?)
+}
+
+fn diff(change: &Change) -> anyhow::Result<String> {
+    let current = match &change.current {
+        Some(config) => serde_yaml::to_string(config)?,
+        None => String::new(),
+    };
+    let desired = match &change.desired {
+        Some(desired) => serde_yaml::to_string(&desired.config)?,
+        None => String::new(),
+    };
+
+    Ok(TextDiff::from_lines(&current, &desired)
+        .unified_diff()
+        .header(
+            &format!("daemon/{}", change.name),
+            &format!("local/{}", change.name),
+        )
+        .to_string())
+}
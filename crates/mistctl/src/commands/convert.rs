@@ -0,0 +1,15 @@
+use std::path::Path;
+
+use config::{ApiVersion, versions};
+
+use crate::commands::read_config;
+
+/// Prints the config in the schema of `to`.
+pub fn convert_cmd(file: &Path, to: &str) -> anyhow::Result<()> {
+    let to: ApiVersion = to.parse()?;
+    let config = versions::convert(read_config(file, None)?, to)?;
+
+    print!("{}", serde_yaml::to_string(&config)?);
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use config::api::{DeadLetter, Job};
+
+use crate::{
+    args::DeadLetterCommand,
+    commands::{DAEMON_URL, client},
+};
+
+pub async fn dead_letter_cmd(namespace: &str, command: DeadLetterCommand) -> anyhow::Result<()> {
+    match command {
+        DeadLetterCommand::List => {
+            let letters = list_dead_letters(namespace).await?;
+
+            println!(
+                "{:<38} {:<24} {:<9} {:<28}",
+                "ID", "DROPLET", "ATTEMPTS", "CREATED"
+            );
+            for letter in letters {
+                println!(
+                    "{:<38} {:<24} {:<9} {:<28}",
+                    letter.id,
+                    letter.droplet,
+                    letter.attempts.len(),
+                    letter.created_at.to_string(),
+                );
+            }
+        }
+        DeadLetterCommand::Get { id } => {
+            let letter = get_dead_letter(namespace, &id).await?;
+            println!("{}", serde_json::to_string_pretty(&letter)?);
+        }
+        DeadLetterCommand::Replay { id } => {
+            let job = replay_dead_letter(namespace, &id).await?;
+            println!("Queued job: {}", job.id);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn list_dead_letters(namespace: &str) -> anyhow::Result<Vec<DeadLetter>> {
+    let client = client()?;
+    let request = client.get(format!(
+        "{DAEMON_URL}/ctr/namespaces/{namespace}/dead-letters"
+    ));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to list dead letters ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn get_dead_letter(namespace: &str, id: &str) -> anyhow::Result<DeadLetter> {
+    let client = client()?;
+    let request = client.get(format!(
+        "{DAEMON_URL}/ctr/namespaces/{namespace}/dead-letters/{id}"
+    ));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to get dead letter ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn replay_dead_letter(namespace: &str, id: &str) -> anyhow::Result<Job> {
+    let client = client()?;
+    let request = client.post(format!(
+        "{DAEMON_URL}/ctr/namespaces/{namespace}/dead-letters/{id}/replay"
+    ));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to replay dead letter ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
@@ -0,0 +1,147 @@
+use std::{
+    path::{self, Path},
+    time::Duration,
+};
+
+use anyhow::Context;
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    sync::mpsc,
+};
+
+use crate::commands::{
+    droplet::{deploy_droplet, print_result, stream_droplet},
+    read_config,
+};
+
+/// Quiet period after a change before rebuilding, so saving several files builds once.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Target droplets are built for.
+const TARGET: &str = "wasm32-wasip2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DevEvent {
+    /// A source file, the manifest or the droplet config changed.
+    Changed,
+    /// Enter was pressed.
+    Execute,
+}
+
+/// Builds the crate in the current directory, deploys it with the droplet config at `file`
+/// and executes it, again whenever the sources change or Enter is pressed.
+pub async fn dev_cmd(
+    namespace: &str,
+    file: &Path,
+    args: Option<String>,
+    debug: bool,
+) -> anyhow::Result<()> {
+    let args = match args {
+        Some(args) => serde_json::from_str(&args)?,
+        None => Value::Null,
+    };
+    let file = path::absolute(file)?;
+    let root = path::absolute(".")?;
+    let sources = [root.join("src"), root.join("wit")];
+
+    let (sender, mut events) = mpsc::unbounded_channel();
+    let changed = sender.clone();
+    let relevant = {
+        let file = file.clone();
+        let sources = sources.clone();
+        move |path: &Path| {
+            path == file
+                || path.file_name().is_some_and(|name| name == "Cargo.toml")
+                || sources.iter().any(|dir| path.starts_with(dir))
+        }
+    };
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            )
+            && event.paths.iter().any(|path| relevant(path))
+        {
+            changed.send(DevEvent::Changed).ok();
+        }
+    })?;
+    watcher.watch(&root, RecursiveMode::NonRecursive)?;
+    if let Some(dir) = file.parent() {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    for dir in sources.iter().filter(|dir| dir.is_dir()) {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+    }
+
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(_)) = lines.next_line().await {
+            if sender.send(DevEvent::Execute).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut event = DevEvent::Changed;
+    let mut deployed = None;
+    loop {
+        if event == DevEvent::Changed {
+            deployed = match deploy(namespace, &file, debug).await {
+                Ok(name) => Some(name),
+                Err(e) => {
+                    eprintln!("{e:#}");
+                    None
+                }
+            };
+        }
+
+        if let Some(name) = &deployed {
+            println!("Executing Droplet: {name}");
+            println!("=== stdout ===");
+            match stream_droplet(namespace, name, args.clone(), |line| println!("{line}")).await {
+                Ok(result) => print_result(&result)?,
+                Err(e) => eprintln!("{e:#}"),
+            }
+        }
+
+        println!();
+        println!("Watching for changes; press Enter to execute again, Ctrl-C to stop.");
+        event = events
+            .recv()
+            .await
+            .context("Stopped watching for changes")?;
+        if event == DevEvent::Changed {
+            tokio::time::sleep(SETTLE).await;
+        }
+        while let Ok(next) = events.try_recv() {
+            if next == DevEvent::Changed {
+                event = next;
+            }
+        }
+    }
+}
+
+/// Builds the crate and creates the droplet from the config, returning its name.
+async fn deploy(namespace: &str, file: &Path, debug: bool) -> anyhow::Result<String> {
+    let mut cargo = Command::new("cargo");
+    cargo.args(["build", "--target", TARGET]);
+    if !debug {
+        cargo.arg("--release");
+    }
+    let status = cargo.status().await.context("Failed to run cargo")?;
+    if !status.success() {
+        anyhow::bail!("Build failed ({status}).");
+    }
+
+    let config = read_config(file, Some(namespace))
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let name = config.metadata.name.clone();
+    deploy_droplet(config).await?;
+    println!("Deployed {name}.");
+
+    Ok(name)
+}
@@ -1,64 +1,504 @@
-use std::{env, fs};
+use std::path::Path;
 
-use config::{RootConfig, Spec, SpecSource};
-use reqwest::{Client, StatusCode};
-use serde_json::json;
+use config::{
+    RootConfig, Spec, SpecSource,
+    api::{Blob, DropletExecutionResult, InvocationOutcome, InvocationRecord, Job, ResourceUsage},
+    quantity::ResourceQuantity,
+    selector::LabelSelector,
+    validate,
+};
+use serde_json::{Value, json};
 
-use crate::args::DropletCommand;
+use crate::{
+    args::DropletCommand,
+    commands::{DAEMON_URL, client, read_config},
+};
 
-pub async fn droplet_cmd(command: DropletCommand) -> anyhow::Result<()> {
+pub async fn droplet_cmd(namespace: &str, command: DropletCommand) -> anyhow::Result<()> {
     match command {
         DropletCommand::Create { config } => {
-            let config = fs::read_to_string(config)?;
-            let mut config: RootConfig = serde_yaml::from_str(&config)?;
-
-            match &mut config.spec {
-                Spec::Droplet { source, .. } => match source {
-                    SpecSource::File { path } => {
-                        if path.is_relative() {
-                            *path = env::current_dir()?.join(path.clone());
-                        }
+            let config = read_config(&config, Some(namespace))?;
+            deploy_droplet(config).await?;
+            println!("Created.");
+        }
+        DropletCommand::List { selector } => {
+            let selector = selector.as_deref().map(str::parse).transpose()?;
+
+            println!("{:<32} {:<40}", "NAME", "LABELS");
+            for config in list_droplets(namespace, selector.as_ref()).await? {
+                let labels = config
+                    .metadata
+                    .labels
+                    .iter()
+                    .map(|(key, value)| format!("{key}={value}"))
+                    .collect::<Vec<_>>();
+                println!(
+                    "{:<32} {:<40}",
+                    config.metadata.name,
+                    if labels.is_empty() {
+                        "-".to_string()
+                    } else {
+                        labels.join(",")
                     }
-                },
+                );
+            }
+        }
+        DropletCommand::Delete { name, selector } => match (name, selector) {
+            (Some(name), _) => {
+                delete_droplet(namespace, &name).await?;
+                println!("Deleted.");
             }
+            (None, Some(selector)) => {
+                for name in delete_droplets(namespace, &selector.parse()?).await? {
+                    println!("Deleted {name}.");
+                }
+            }
+            (None, None) => unreachable!("clap requires a name or a selector"),
+        },
+        DropletCommand::Execute {
+            name,
+            args,
+            detach,
+            webhook,
+        } => {
+            let args = match args {
+                Some(args) => serde_json::from_str(&args)?,
+                None => Value::Null,
+            };
 
-            match create_droplet(&config).await? {
-                code if code.is_success() => println!("Created."),
-                other => eprintln!("Failed to create droplet ({other})."),
+            if detach {
+                let job = enqueue_droplet(namespace, &name, args, webhook).await?;
+                println!("Queued job: {}", job.id);
+                return Ok(());
             }
-        }
-        DropletCommand::Execute { name } => {
+
             println!("Executing Droplet: {name}");
-            let stdout = execute_droplet(&name).await?;
-            println!("=== stdout ===");
-            println!("{stdout}");
+            let result = execute_droplet(namespace, &name, args).await?;
+            print_execution(&result)?;
         }
+        DropletCommand::History {
+            name,
+            outcome,
+            source,
+            limit,
+        } => {
+            let records = droplet_history(namespace, &name, outcome, source, limit).await?;
+
+            println!(
+                "{:<28} {:<5} {:<10} {:<24} {:<22} {:>10} {:>12} {:>14} {:<10}",
+                "STARTED",
+                "REV",
+                "SOURCE",
+                "CALLER",
+                "PEER",
+                "DURATION",
+                "PEAK MEMORY",
+                "FUEL",
+                "OUTCOME"
+            );
+            for record in records {
+                println!(
+                    "{:<28} {:<5} {:<10} {:<24} {:<22} {:>10} {:>12} {:>14} {:<10}",
+                    record.started_at.to_string(),
+                    record.revision,
+                    record.origin.source.kind(),
+                    record.origin.caller.principal.as_deref().unwrap_or("-"),
+                    record.origin.caller.peer.as_deref().unwrap_or("-"),
+                    format!("{}ms", record.duration_ms),
+                    record.usage.peak_memory,
+                    record.usage.fuel_consumed,
+                    format!("{:?}", record.outcome),
+                );
+            }
+        }
+        DropletCommand::Stats { name, limit } => {
+            let records = droplet_history(namespace, &name, None, None, limit).await?;
+            if records.is_empty() {
+                println!("No invocations of {name} recorded.");
+                return Ok(());
+            }
+
+            let failed = records
+                .iter()
+                .filter(|record| record.outcome == InvocationOutcome::Failed)
+                .count();
+            println!(
+                "{} invocation(s), {} succeeded, {failed} failed",
+                records.len(),
+                records.len() - failed
+            );
+            println!();
+
+            println!(
+                "{:<20} {:>14} {:>14} {:>14} {:>14}",
+                "", "P50", "P90", "P99", "MAX"
+            );
+            let column =
+                |value: fn(&InvocationRecord) -> u64| records.iter().map(value).collect::<Vec<_>>();
+            let rows = [
+                ("duration (ms)", column(|record| record.duration_ms)),
+                (
+                    "peak memory",
+                    column(|record| record.usage.peak_memory as u64),
+                ),
+                (
+                    "peak table elems",
+                    column(|record| record.usage.peak_table_elements as u64),
+                ),
+                (
+                    "denied growths",
+                    column(|record| record.usage.denied_growths),
+                ),
+                ("fuel", column(|record| record.usage.fuel_consumed)),
+            ];
+            for (label, mut values) in rows {
+                values.sort_unstable();
+                println!(
+                    "{label:<20} {:>14} {:>14} {:>14} {:>14}",
+                    percentile(&values, 50.0),
+                    percentile(&values, 90.0),
+                    percentile(&values, 99.0),
+                    values[values.len() - 1],
+                );
+            }
+
+            let config = list_droplets(namespace, None)
+                .await?
+                .into_iter()
+                .find(|config| config.metadata.name == name);
+            if let Some(config) = config {
+                let Spec::Droplet { runtime, .. } = &config.spec;
+                let declared = ResourceQuantity::try_from(runtime.resources.memory.as_str())?
+                    .as_memory()
+                    .unwrap_or(0);
+                let peak = records
+                    .iter()
+                    .map(|record| record.usage.peak_memory as u64)
+                    .max()
+                    .unwrap_or(0);
+                println!();
+                println!(
+                    "resources.memory is {} ({declared} bytes); the largest peak used {:.0}% of it.",
+                    runtime.resources.memory,
+                    peak as f64 * 100.0 / declared.max(1) as f64
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the stdout, result and resource usage of an invocation.
+pub fn print_execution(result: &DropletExecutionResult) -> anyhow::Result<()> {
+    println!("=== stdout ===");
+    println!("{}", result.stdout);
+    print_result(result)
+}
+
+/// Prints what the execution returned, if anything, and the resources it used.
+pub fn print_result(result: &DropletExecutionResult) -> anyhow::Result<()> {
+    if !result.result.is_null() {
+        println!("=== result ===");
+        println!("{}", serde_json::to_string_pretty(&result.result)?);
     }
+    print_usage(&result.usage);
 
     Ok(())
 }
 
-pub async fn create_droplet(config: &RootConfig) -> anyhow::Result<StatusCode> {
-    let client = Client::new();
+pub fn print_usage(usage: &ResourceUsage) {
+    println!("=== usage ===");
+    println!(
+        "peak memory: {} bytes, peak table elements: {}, denied growths: {}, fuel: {}",
+        usage.peak_memory, usage.peak_table_elements, usage.denied_growths, usage.fuel_consumed
+    );
+}
+
+/// Nearest-rank percentile of ascending `values`.
+fn percentile(values: &[u64], percent: f64) -> u64 {
+    let rank = (percent / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+/// Uploads a local binary to the daemon's blob store.
+pub async fn upload_blob(path: &Path) -> anyhow::Result<Blob> {
+    let file = tokio::fs::File::open(path).await?;
+
+    let client = client()?;
+    let request = client.post(format!("{DAEMON_URL}/ctr/blobs")).body(file);
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to upload {} ({}):\n{}",
+            path.display(),
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+/// Validates a config and creates the droplet, uploading a local `File` source first unless
+/// the daemon watches it.
+pub async fn deploy_droplet(mut config: RootConfig) -> anyhow::Result<()> {
+    validate::validate(&config, None)?;
+
+    let Spec::Droplet { source, .. } = &mut config.spec;
+    if let SpecSource::File { path, watch: true } = source {
+        // The daemon reads watched sources itself, so it needs a path it can resolve.
+        *path = std::path::absolute(&*path)?;
+    } else if let SpecSource::File { path, .. } = source {
+        let blob = upload_blob(path).await?;
+        println!("Uploaded {} ({} bytes).", blob.digest, blob.size);
+        *source = SpecSource::Blob {
+            digest: blob.digest,
+        };
+    }
+
+    create_droplet(&config).await
+}
+
+pub async fn create_droplet(config: &RootConfig) -> anyhow::Result<()> {
+    let namespace = &config.metadata.namespace;
+
+    let client = client()?;
     let request = client
-        .post("http://0.0.0.0:8080/ctr/droplet")
+        .post(format!("{DAEMON_URL}/ctr/namespaces/{namespace}/droplet"))
         .json(&json!({ "config": config }));
     let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to create droplet ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn list_droplets(
+    namespace: &str,
+    selector: Option<&LabelSelector>,
+) -> anyhow::Result<Vec<RootConfig>> {
+    let query = selector.map(|selector| ("selector", selector.to_string()));
+
+    let client = client()?;
+    let request = client
+        .get(format!("{DAEMON_URL}/ctr/namespaces/{namespace}/droplet"))
+        .query(&query.as_slice());
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to list droplets ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn delete_droplet(namespace: &str, name: &str) -> anyhow::Result<()> {
+    let client = client()?;
+    let request = client.delete(format!(
+        "{DAEMON_URL}/ctr/namespaces/{namespace}/droplet/{name}"
+    ));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to delete droplet {name} ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(())
+}
+
+/// Deletes every droplet matching `selector`, returning their names.
+pub async fn delete_droplets(
+    namespace: &str,
+    selector: &LabelSelector,
+) -> anyhow::Result<Vec<String>> {
+    let client = client()?;
+    let request = client
+        .delete(format!("{DAEMON_URL}/ctr/namespaces/{namespace}/droplet"))
+        .query(&[("selector", selector.to_string())]);
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to delete droplets ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn execute_droplet(
+    namespace: &str,
+    name: &str,
+    args: Value,
+) -> anyhow::Result<DropletExecutionResult> {
+    let client = client()?;
+    let request = client
+        .post(format!(
+            "{DAEMON_URL}/ctr/namespaces/{namespace}/droplet/{name}/invoke"
+        ))
+        .json(&json!({ "args": args }));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to execute droplet ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
 
-    Ok(response.status())
+/// Executes the droplet, calling `on_line` with every line it prints while it runs.
+pub async fn stream_droplet(
+    namespace: &str,
+    name: &str,
+    args: Value,
+    mut on_line: impl FnMut(&str),
+) -> anyhow::Result<DropletExecutionResult> {
+    let client = client()?;
+    let request = client
+        .post(format!(
+            "{DAEMON_URL}/ctr/namespaces/{namespace}/droplet/{name}/invoke?stream=true"
+        ))
+        .json(&json!({ "args": args }));
+    let mut response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to execute droplet ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    let mut buffered = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        buffered.extend_from_slice(&chunk);
+        // Events end with an empty line.
+        while let Some(end) = buffered.windows(2).position(|bytes| bytes == b"\n\n") {
+            let event = buffered.drain(..end + 2).collect::<Vec<_>>();
+            let (kind, data) = parse_event(std::str::from_utf8(&event)?);
+            match kind {
+                "output" => on_line(&serde_json::from_str::<String>(data)?),
+                "result" => return Ok(serde_json::from_str(data)?),
+                "error" => anyhow::bail!("{}", serde_json::from_str::<String>(data)?),
+                _ => {}
+            }
+        }
+    }
+
+    anyhow::bail!("The daemon stopped responding before the droplet finished.")
+}
+
+/// Splits a server-sent event into its type and data, which the daemon sends as a single
+/// line of JSON.
+fn parse_event(event: &str) -> (&str, &str) {
+    let (mut kind, mut data) = ("message", "");
+    for line in event.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            kind = value.trim_start();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data = value.trim_start();
+        }
+    }
+
+    (kind, data)
 }
 
-pub async fn execute_droplet(name: &str) -> anyhow::Result<String> {
-    let client = Client::new();
-    let request = client.get(format!("http://0.0.0.0:8080/ctr/droplet/{name}/execute"));
+pub async fn enqueue_droplet(
+    namespace: &str,
+    name: &str,
+    args: Value,
+    webhook: Option<String>,
+) -> anyhow::Result<Job> {
+    let client = client()?;
+    let request = client
+        .post(format!(
+            "{DAEMON_URL}/ctr/namespaces/{namespace}/droplet/{name}/invoke?async=true"
+        ))
+        .json(&json!({ "args": args, "webhook": webhook }));
     let response = request.send().await?;
     let status = response.status();
 
-    let contents = response.text().await?;
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to queue droplet ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn droplet_history(
+    namespace: &str,
+    name: &str,
+    outcome: Option<String>,
+    source: Option<String>,
+    limit: usize,
+) -> anyhow::Result<Vec<InvocationRecord>> {
+    let mut query = vec![("limit", limit.to_string())];
+    query.extend(outcome.map(|outcome| ("outcome", outcome)));
+    query.extend(source.map(|source| ("source", source)));
+
+    let client = client()?;
+    let request = client
+        .get(format!(
+            "{DAEMON_URL}/ctr/namespaces/{namespace}/droplet/{name}/invocations"
+        ))
+        .query(&query);
+    let response = request.send().await?;
+    let status = response.status();
 
     if !status.is_success() {
-        anyhow::bail!("Failed to execute droplet ({}):\n{}", status, contents);
+        anyhow::bail!(
+            "Failed to get droplet history ({}):\n{}",
+            status,
+            response.text().await?
+        );
     }
 
-    Ok(contents)
+    Ok(response.json().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_split_into_type_and_data() {
+        assert_eq!(
+            parse_event("event: output\ndata: \"tick\"\n\n"),
+            ("output", "\"tick\"")
+        );
+        assert_eq!(parse_event("data:{}\n\n"), ("message", "{}"));
+    }
 }
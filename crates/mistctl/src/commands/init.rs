@@ -0,0 +1,114 @@
+use std::{fs, path::Path};
+
+use config::validate;
+
+use crate::args::Template;
+
+const MANIFEST: &str = include_str!("../../templates/Cargo.toml.tmpl");
+const GITIGNORE: &str = include_str!("../../templates/gitignore");
+
+impl Template {
+    /// World, WIT, library source and droplet config of the template.
+    fn files(self) -> (&'static str, &'static str, &'static str, &'static str) {
+        match self {
+            Self::RustHandler => (
+                "handler-world",
+                include_str!("../../templates/rust-handler/world.wit"),
+                include_str!("../../templates/rust-handler/lib.rs"),
+                include_str!("../../templates/rust-handler/droplet.yaml"),
+            ),
+            Self::RustHttp => (
+                "http-world",
+                include_str!("../../templates/rust-http/world.wit"),
+                include_str!("../../templates/rust-http/lib.rs"),
+                include_str!("../../templates/rust-http/droplet.yaml"),
+            ),
+            Self::RustKv => (
+                "kv-world",
+                include_str!("../../templates/rust-kv/world.wit"),
+                include_str!("../../templates/rust-kv/lib.rs"),
+                include_str!("../../templates/rust-kv/droplet.yaml"),
+            ),
+        }
+    }
+}
+
+/// Creates a crate named `name` in a new directory, ready for `mistctl dev`.
+pub fn init_cmd(name: &str, template: Template) -> anyhow::Result<()> {
+    validate::check_name(name).map_err(anyhow::Error::msg)?;
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        anyhow::bail!("Invalid name \"{name}\", crate names cannot start with a digit.");
+    }
+
+    let dir = Path::new(name);
+    if fs::exists(dir)? {
+        anyhow::bail!("{} already exists.", dir.display());
+    }
+    create(dir, name, template)?;
+
+    println!("Created {name}. Build and deploy it with:");
+    println!();
+    println!("    rustup target add wasm32-wasip2");
+    println!("    cd {name} && mistctl dev");
+
+    Ok(())
+}
+
+/// Writes the crate rendered from `template` into `dir`.
+fn create(dir: &Path, name: &str, template: Template) -> anyhow::Result<()> {
+    let (world, wit, lib, droplet) = template.files();
+    let render = |text: &str| {
+        text.replace("{{name}}", name)
+            .replace("{{crate}}", &name.replace('-', "_"))
+            .replace("{{world}}", world)
+    };
+
+    fs::create_dir_all(dir.join("src"))?;
+    fs::create_dir_all(dir.join("wit"))?;
+    fs::write(dir.join("Cargo.toml"), render(MANIFEST))?;
+    fs::write(dir.join(".gitignore"), GITIGNORE)?;
+    fs::write(dir.join("wit").join(format!("{name}.wit")), render(wit))?;
+    fs::write(dir.join("src").join("lib.rs"), render(lib))?;
+    fs::write(dir.join("droplet.yaml"), render(droplet))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+    use config::RootConfig;
+
+    use super::*;
+    use crate::commands::read_config;
+
+    #[test]
+    fn templates_render_valid_crates() {
+        let root = tempfile::tempdir().unwrap();
+        for &template in Template::value_variants() {
+            let name = "my-droplet";
+            let dir = root.path().join(format!("{template:?}"));
+            create(&dir, name, template).unwrap();
+
+            let config: RootConfig = read_config(&dir.join("droplet.yaml"), Some("default"))
+                .unwrap_or_else(|e| panic!("{template:?}: {e:#}"));
+            validate::validate(&config, None).unwrap_or_else(|e| panic!("{template:?}: {e}"));
+            assert_eq!(config.metadata.name, name);
+
+            let (world, ..) = template.files();
+            let mut resolve = wit_parser::Resolve::new();
+            let package = resolve
+                .push_file(dir.join("wit").join(format!("{name}.wit")))
+                .unwrap_or_else(|e| panic!("{template:?}: {e:#}"));
+            resolve
+                .select_world(package, Some(world))
+                .unwrap_or_else(|e| panic!("{template:?}: {e:#}"));
+
+            let lib = fs::read_to_string(dir.join("src").join("lib.rs")).unwrap();
+            assert!(
+                !lib.contains("{{"),
+                "{template:?} left a placeholder in lib.rs"
+            );
+        }
+    }
+}
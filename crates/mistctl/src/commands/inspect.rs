@@ -0,0 +1,63 @@
+use std::{fs, path::Path};
+
+use config::api::WasmReport;
+
+use crate::commands::{DAEMON_URL, client};
+
+pub async fn inspect_cmd(file: &Path) -> anyhow::Result<()> {
+    let report = inspect_wasm(fs::read(file)?).await?;
+
+    println!("Kind: {:?}", report.kind);
+    println!(
+        "Size: {} bytes (artifact: {} bytes, compiled in {}ms)",
+        report.size, report.artifact_size, report.compile_ms
+    );
+
+    println!();
+    println!("{:<48} {:<10} {:<10}", "IMPORT", "KIND", "PROVIDED");
+    for import in &report.imports {
+        println!(
+            "{:<48} {:<10} {:<10}",
+            import.name,
+            import.kind,
+            if import.satisfied { "yes" } else { "NO" },
+        );
+    }
+
+    println!();
+    println!("{:<48} {:<10}", "EXPORT", "SIGNATURE");
+    for export in &report.exports {
+        println!("{:<48} {:<10}", export.name, export.signature);
+    }
+
+    let missing = report
+        .imports
+        .iter()
+        .filter(|import| !import.satisfied)
+        .count();
+    if missing > 0 {
+        println!();
+        println!(
+            "{missing} import(s) cannot be provided by the host; the droplet will fail to create."
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn inspect_wasm(bytes: Vec<u8>) -> anyhow::Result<WasmReport> {
+    let client = client()?;
+    let request = client.post(format!("{DAEMON_URL}/ctr/inspect")).body(bytes);
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to inspect binary ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
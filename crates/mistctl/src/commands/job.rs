@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use config::api::Job;
+
+use crate::{
+    args::JobCommand,
+    commands::{DAEMON_URL, client},
+};
+
+pub async fn job_cmd(namespace: &str, command: JobCommand) -> anyhow::Result<()> {
+    match command {
+        JobCommand::List => {
+            let jobs = list_jobs(namespace).await?;
+
+            println!(
+                "{:<38} {:<24} {:<10} {:<28}",
+                "ID", "DROPLET", "STATUS", "CREATED"
+            );
+            for job in jobs {
+                println!(
+                    "{:<38} {:<24} {:<10} {:<28}",
+                    job.id,
+                    job.droplet,
+                    format!("{:?}", job.status),
+                    job.created_at.to_string(),
+                );
+            }
+        }
+        JobCommand::Get { id } => {
+            let job = get_job(namespace, &id).await?;
+            println!("{}", serde_json::to_string_pretty(&job)?);
+        }
+        JobCommand::Wait { id, interval } => {
+            let job = loop {
+                let job = get_job(namespace, &id).await?;
+                if job.status.is_finished() {
+                    break job;
+                }
+
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            };
+
+            print_job_outcome(&job)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_job_outcome(job: &Job) -> anyhow::Result<()> {
+    if let Some(ref error) = job.error {
+        anyhow::bail!("Job {} failed:\n{}", job.id, error);
+    }
+
+    println!("Job {} finished: {:?}", job.id, job.status);
+    if let Some(ref result) = job.result {
+        println!("=== stdout ===");
+        println!("{}", result.stdout);
+    }
+
+    Ok(())
+}
+
+pub async fn list_jobs(namespace: &str) -> anyhow::Result<Vec<Job>> {
+    let client = client()?;
+    let request = client.get(format!("{DAEMON_URL}/ctr/namespaces/{namespace}/jobs"));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to list jobs ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn get_job(namespace: &str, id: &str) -> anyhow::Result<Job> {
+    let client = client()?;
+    let request = client.get(format!("{DAEMON_URL}/ctr/namespaces/{namespace}/jobs/{id}"));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to get job ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
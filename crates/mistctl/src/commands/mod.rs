@@ -1 +1,49 @@
+pub mod admission;
+pub mod apply;
+pub mod convert;
+pub mod dead_letter;
+pub mod dev;
 pub mod droplet;
+pub mod init;
+pub mod inspect;
+pub mod job;
+pub mod namespace;
+#[cfg(feature = "local")]
+pub mod run;
+pub mod schema;
+pub mod trigger;
+pub mod validate;
+
+use std::{env, fs, path::Path};
+
+use config::{RootConfig, versions};
+use reqwest::{
+    Client,
+    header::{AUTHORIZATION, HeaderMap, HeaderValue},
+};
+use serde_json::Value;
+
+pub const DAEMON_URL: &str = "http://0.0.0.0:8080";
+
+/// Client authenticating with the token in `MIST_TOKEN`, if set.
+pub fn client() -> anyhow::Result<Client> {
+    let mut headers = HeaderMap::new();
+    if let Ok(token) = env::var("MIST_TOKEN") {
+        let mut value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+
+    Ok(Client::builder().default_headers(headers).build()?)
+}
+
+/// Reads a droplet config file in any supported schema, migrated to the latest one.
+///
+/// With a `namespace`, a config that does not declare one is placed in it.
+pub fn read_config(path: &Path, namespace: Option<&str>) -> anyhow::Result<RootConfig> {
+    let config: Value = serde_yaml::from_str(&fs::read_to_string(path)?)?;
+    match namespace {
+        Some(namespace) => Ok(versions::migrate_into(config, namespace)?),
+        None => Ok(versions::migrate(config)?),
+    }
+}
@@ -0,0 +1,121 @@
+use config::api::{Namespace, NamespaceQuota};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    args::NamespaceCommand,
+    commands::{DAEMON_URL, client},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct NamespaceCreated {
+    pub namespace: Namespace,
+    pub token: String,
+}
+
+pub async fn namespace_cmd(command: NamespaceCommand) -> anyhow::Result<()> {
+    match command {
+        NamespaceCommand::Create {
+            name,
+            memory,
+            droplets,
+            concurrency,
+        } => {
+            let quota = NamespaceQuota {
+                memory,
+                droplets,
+                concurrent_invocations: concurrency,
+            };
+            let created = create_namespace(&name, &quota).await?;
+
+            println!("Created namespace {}.", created.namespace.name);
+            println!("Token (shown only once): {}", created.token);
+        }
+        NamespaceCommand::List => {
+            let namespaces = list_namespaces().await?;
+
+            println!(
+                "{:<24} {:<10} {:<10} {:<12} {:<28}",
+                "NAME", "MEMORY", "DROPLETS", "CONCURRENCY", "CREATED"
+            );
+            let limit = |limit: Option<String>| limit.unwrap_or_else(|| "-".to_string());
+            for namespace in namespaces {
+                println!(
+                    "{:<24} {:<10} {:<10} {:<12} {:<28}",
+                    namespace.name,
+                    limit(namespace.quota.memory),
+                    limit(namespace.quota.droplets.map(|n| n.to_string())),
+                    limit(
+                        namespace
+                            .quota
+                            .concurrent_invocations
+                            .map(|n| n.to_string())
+                    ),
+                    namespace.created_at.to_string(),
+                );
+            }
+        }
+        NamespaceCommand::Delete { name } => {
+            delete_namespace(&name).await?;
+            println!("Deleted.");
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn create_namespace(
+    name: &str,
+    quota: &NamespaceQuota,
+) -> anyhow::Result<NamespaceCreated> {
+    let client = client()?;
+    let request = client
+        .post(format!("{DAEMON_URL}/ctr/namespaces"))
+        .json(&json!({ "name": name, "quota": quota }));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to create namespace ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn list_namespaces() -> anyhow::Result<Vec<Namespace>> {
+    let client = client()?;
+    let request = client.get(format!("{DAEMON_URL}/ctr/namespaces"));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to list namespaces ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
+
+pub async fn delete_namespace(name: &str) -> anyhow::Result<()> {
+    let client = client()?;
+    let request = client.delete(format!("{DAEMON_URL}/ctr/namespaces/{name}"));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to delete namespace {name} ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(())
+}
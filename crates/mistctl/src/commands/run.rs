@@ -0,0 +1,62 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use anyhow::Context;
+use config::{Spec, validate};
+use mistctr::{context::ControlContext, droplet::DropletHandle, limits::ResourceMaximums};
+use serde_json::Value;
+
+use crate::commands::{
+    droplet::{print_execution, print_usage},
+    read_config,
+};
+
+/// Runs a droplet config once in this process, with the limits a daemon started with the
+/// same `MIST_MAX_*` variables would apply.
+///
+/// `secrets` are `NAME=FILE` pairs, stored in the droplet's namespace before it is created.
+pub async fn run_cmd(
+    namespace: &str,
+    file: &Path,
+    args: Option<String>,
+    secrets: &[String],
+) -> anyhow::Result<()> {
+    let args = match args {
+        Some(args) => serde_json::from_str(&args)?,
+        None => Value::Null,
+    };
+    let config = read_config(file, Some(namespace))?;
+
+    // Removed when the run finishes.
+    let root = tempfile::Builder::new().prefix("mistctl-run-").tempdir()?;
+    let cx = ControlContext::new(
+        root.path().to_path_buf(),
+        &mistctr::engine_config(),
+        ResourceMaximums::from_env()?,
+    )?;
+
+    for secret in secrets {
+        let (name, path) = secret
+            .split_once('=')
+            .with_context(|| format!("Secret \"{secret}\" is not NAME=FILE"))?;
+        let value = fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+        cx.secrets().set(&config.metadata.namespace, name, &value)?;
+    }
+    let names = cx
+        .secrets()
+        .list(&config.metadata.namespace)?
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    validate::validate(&config, Some(&names))?;
+    let Spec::Droplet { runtime, .. } = &config.spec;
+    cx.maximums().check(&runtime.resources)?;
+
+    let droplet = DropletHandle::new(&cx, config).await?;
+    println!("Executing Droplet: {}", droplet.config.metadata.name);
+    match droplet.run(&args).await {
+        Ok(result) => print_execution(&result),
+        Err(e) => {
+            print_usage(&e.usage);
+            Err(e.error)
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use config::{ApiVersion, versions};
+
+/// Prints the JSON Schema for config files of `api_version`, for use in editors.
+pub fn schema_cmd(api_version: &str) -> anyhow::Result<()> {
+    let version: ApiVersion = api_version.parse()?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&versions::schema(version))?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use config::api::TriggerInfo;
+
+use crate::{
+    args::TriggerCommand,
+    commands::{DAEMON_URL, client},
+};
+
+pub async fn trigger_cmd(namespace: &str, command: TriggerCommand) -> anyhow::Result<()> {
+    match command {
+        TriggerCommand::List => {
+            let triggers = list_triggers(namespace).await?;
+
+            println!(
+                "{:<24} {:<16} {:<28} {:<28}",
+                "DROPLET", "TRIGGER", "LAST RUN", "NEXT RUN"
+            );
+            for trigger in triggers {
+                println!(
+                    "{:<24} {:<16} {:<28} {:<28}",
+                    trigger.droplet,
+                    trigger.trigger,
+                    trigger
+                        .last_run
+                        .map(|last_run| last_run.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    trigger.next_run.to_string(),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn list_triggers(namespace: &str) -> anyhow::Result<Vec<TriggerInfo>> {
+    let client = client()?;
+    let request = client.get(format!("{DAEMON_URL}/ctr/namespaces/{namespace}/trigger"));
+    let response = request.send().await?;
+    let status = response.status();
+
+    if !status.is_success() {
+        anyhow::bail!(
+            "Failed to list triggers ({}):\n{}",
+            status,
+            response.text().await?
+        );
+    }
+
+    Ok(response.json().await?)
+}
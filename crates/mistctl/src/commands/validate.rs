@@ -0,0 +1,37 @@
+use std::path::{Path, PathBuf};
+
+use config::validate;
+
+use crate::commands::read_config;
+
+/// Checks every file offline, printing all problems before failing.
+pub fn validate_cmd(files: &[PathBuf]) -> anyhow::Result<()> {
+    let mut failed = 0;
+    for file in files {
+        let problems = match check(file) {
+            Ok(()) => {
+                println!("{}: OK", file.display());
+                continue;
+            }
+            Err(problems) => problems,
+        };
+
+        failed += 1;
+        for problem in problems {
+            println!("{}: {problem}", file.display());
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} file(s) failed validation.", files.len());
+    }
+
+    Ok(())
+}
+
+fn check(file: &Path) -> Result<(), Vec<String>> {
+    let config = read_config(file, None).map_err(|e| vec![e.to_string()])?;
+
+    validate::validate(&config, None)
+        .map_err(|e| e.issues.iter().map(ToString::to_string).collect::<Vec<_>>())
+}
@@ -1,20 +1,42 @@
 use clap::Parser;
+#[cfg(feature = "local")]
+use mistctl::commands::run;
 use mistctl::{
     args::{Args, Command},
-    commands::droplet,
+    commands::{
+        admission, apply, convert, dead_letter, dev, droplet, init, inspect, job, namespace,
+        schema, trigger, validate,
+    },
 };
-use serde::{Deserialize, Serialize};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    let namespace = &args.namespace;
     match args.command {
-        Command::Droplet { command } => droplet::droplet_cmd(command).await,
+        Command::Droplet { command } => droplet::droplet_cmd(namespace, command).await,
+        Command::Trigger { command } => trigger::trigger_cmd(namespace, command).await,
+        Command::Job { command } => job::job_cmd(namespace, command).await,
+        Command::DeadLetter { command } => dead_letter::dead_letter_cmd(namespace, command).await,
+        Command::Namespace { command } => namespace::namespace_cmd(command).await,
+        Command::Admission => admission::admission_cmd().await,
+        Command::Inspect { file } => inspect::inspect_cmd(&file).await,
+        Command::Validate { files } => validate::validate_cmd(&files),
+        Command::Convert { file, to } => convert::convert_cmd(&file, &to),
+        Command::Apply {
+            paths,
+            dry_run,
+            prune,
+            selector,
+        } => apply::apply_cmd(namespace, &paths, dry_run, prune, selector.as_deref()).await,
+        #[cfg(feature = "local")]
+        Command::Run {
+            file,
+            args,
+            secrets,
+        } => run::run_cmd(namespace, &file, args, &secrets).await,
+        Command::Init { name, template } => init::init_cmd(&name, template),
+        Command::Dev { file, args, debug } => dev::dev_cmd(namespace, &file, args, debug).await,
+        Command::Schema { api_version } => schema::schema_cmd(&api_version),
     }
 }
-
-#[derive(Debug, Deserialize, Serialize)]
-pub struct DropletDefinePayload {
-    name: String,
-    wasm: Vec<u8>,
-}
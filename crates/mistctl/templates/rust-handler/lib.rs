@@ -0,0 +1,17 @@
+#![allow(unsafe_op_in_unsafe_fn)]
+
+wit_bindgen::generate!({
+    world: "handler-world"
+});
+
+struct Handler;
+
+impl Guest for Handler {
+    fn handler(name: String) -> String {
+        println!("Greeting {name}.");
+
+        format!("Hello, {name}!")
+    }
+}
+
+export!(Handler);
@@ -0,0 +1,29 @@
+#![allow(unsafe_op_in_unsafe_fn)]
+
+wit_bindgen::generate!({
+    world: "http-world"
+});
+
+struct Handler;
+
+impl Guest for Handler {
+    fn handler(request: Request) -> Response {
+        println!("{} {}", request.method, request.path);
+
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/") => text(200, "Hello from {{name}}!"),
+            ("POST", "/echo") => text(200, &request.body),
+            _ => text(404, "Not found"),
+        }
+    }
+}
+
+fn text(status: u16, body: &str) -> Response {
+    Response {
+        status,
+        headers: vec![("content-type".to_string(), "text/plain".to_string())],
+        body: body.to_string(),
+    }
+}
+
+export!(Handler);
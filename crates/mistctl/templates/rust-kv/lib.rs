@@ -0,0 +1,28 @@
+#![allow(unsafe_op_in_unsafe_fn)]
+
+wit_bindgen::generate!({
+    world: "kv-world"
+});
+
+use exports::local::{{crate}}::kv::Guest;
+
+/// Only variables with this prefix are exposed, without it.
+const PREFIX: &str = "KV_";
+
+struct Store;
+
+impl Guest for Store {
+    fn get(key: String) -> Option<String> {
+        std::env::var(format!("{PREFIX}{key}")).ok()
+    }
+
+    fn keys() -> Vec<String> {
+        let mut keys = std::env::vars()
+            .filter_map(|(name, _)| name.strip_prefix(PREFIX).map(str::to_string))
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+}
+
+export!(Store);
@@ -0,0 +1,322 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use thiserror::Error;
+use tokio::{sync::Notify, time::Instant};
+
+use crate::metrics;
+
+pub use config::api::{AdmissionReport, Reservation, ResourceBudget, Resources};
+
+#[derive(Debug, Error)]
+#[error("Not enough node capacity to run {droplet}: {reason}")]
+pub struct CapacityExceeded {
+    pub droplet: String,
+    pub reason: String,
+}
+
+#[derive(Default)]
+struct AdmissionState {
+    reserved: Resources,
+    next_id: u64,
+    reservations: BTreeMap<u64, Reservation>,
+    /// Invocations waiting for capacity, admitted strictly in the order they arrived.
+    waiters: VecDeque<Waiter>,
+}
+
+impl AdmissionState {
+    /// Lets the first waiter check whether it fits now.
+    fn wake_first(&self) {
+        if let Some(waiter) = self.waiters.front() {
+            waiter.wake.notify_one();
+        }
+    }
+}
+
+struct Waiter {
+    id: u64,
+    wake: Arc<Notify>,
+}
+
+/// Reserves the declared resources of each invocation from the node's [`ResourceBudget`]
+/// before it is instantiated, so concurrent invocations cannot exhaust the host together.
+///
+/// Invocations that do not fit wait in line until enough is released or the queue
+/// timeout passes; one that would fit does not overtake those already waiting.
+pub struct Admission {
+    budget: ResourceBudget,
+    state: Mutex<AdmissionState>,
+}
+
+impl Admission {
+    pub fn new(budget: ResourceBudget) -> Self {
+        Self {
+            budget,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn budget(&self) -> &ResourceBudget {
+        &self.budget
+    }
+
+    /// Refuses a droplet that could not run even on an idle node.
+    pub fn check_fits(&self, droplet: &str, resources: Resources) -> Result<(), CapacityExceeded> {
+        if resources.fits(&self.budget) {
+            return Ok(());
+        }
+
+        Err(CapacityExceeded {
+            droplet: droplet.to_string(),
+            reason: format!(
+                "it declares {} bytes of memory and {}m CPU, more than the node's budget",
+                resources.memory, resources.cpu
+            ),
+        })
+    }
+
+    /// Waits until `resources` fit into what is left of the budget and reserves them,
+    /// until the returned guard is dropped.
+    pub async fn reserve(
+        &self,
+        droplet: &str,
+        resources: Resources,
+    ) -> Result<ReservationGuard<'_>, CapacityExceeded> {
+        self.check_fits(droplet, resources)?;
+
+        let deadline = Instant::now() + self.budget.queue_timeout;
+        let queued = {
+            let mut state = self.state.lock().unwrap();
+            if state.waiters.is_empty()
+                && let Some(id) = self.try_reserve(&mut state, droplet, resources)
+            {
+                return Ok(ReservationGuard {
+                    admission: self,
+                    id,
+                });
+            }
+            QueuedGuard::new(self, &mut state)
+        };
+
+        loop {
+            if tokio::time::timeout_at(deadline, queued.wake.notified())
+                .await
+                .is_err()
+            {
+                metrics::ADMISSION_REJECTIONS.inc();
+                return Err(CapacityExceeded {
+                    droplet: droplet.to_string(),
+                    reason: format!(
+                        "no capacity freed up within {}",
+                        humantime::format_duration(self.budget.queue_timeout)
+                    ),
+                });
+            }
+
+            let mut state = self.state.lock().unwrap();
+            if state
+                .waiters
+                .front()
+                .is_some_and(|waiter| waiter.id == queued.id)
+                && let Some(id) = self.try_reserve(&mut state, droplet, resources)
+            {
+                state.waiters.pop_front();
+                // What is left may be enough for the next in line as well.
+                state.wake_first();
+                return Ok(ReservationGuard {
+                    admission: self,
+                    id,
+                });
+            }
+        }
+    }
+
+    /// Reserves `resources` if they fit into what is left of the budget. Totals that would
+    /// overflow are never reserved, even without a budget, so releases stay exact.
+    fn try_reserve(
+        &self,
+        state: &mut AdmissionState,
+        droplet: &str,
+        resources: Resources,
+    ) -> Option<u64> {
+        let total = Resources {
+            memory: state.reserved.memory.checked_add(resources.memory)?,
+            cpu: state.reserved.cpu.checked_add(resources.cpu)?,
+        };
+        if !total.fits(&self.budget) {
+            return None;
+        }
+
+        state.reserved = total;
+        state.next_id += 1;
+        let id = state.next_id;
+        state.reservations.insert(
+            id,
+            Reservation {
+                droplet: droplet.to_string(),
+                resources,
+                since: Utc::now(),
+            },
+        );
+        metrics::RESERVED_MEMORY_BYTES.set(total.memory as i64);
+        metrics::RESERVED_CPU_MILLICORES.set(total.cpu as i64);
+
+        Some(id)
+    }
+
+    fn release(&self, id: u64) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(reservation) = state.reservations.remove(&id) {
+            state.reserved.memory -= reservation.resources.memory;
+            state.reserved.cpu -= reservation.resources.cpu;
+            metrics::RESERVED_MEMORY_BYTES.set(state.reserved.memory as i64);
+            metrics::RESERVED_CPU_MILLICORES.set(state.reserved.cpu as i64);
+        }
+        state.wake_first();
+    }
+
+    pub fn report(&self) -> AdmissionReport {
+        let state = self.state.lock().unwrap();
+
+        AdmissionReport {
+            budget: self.budget,
+            reserved: state.reserved,
+            queued: state.waiters.len(),
+            reservations: state.reservations.values().cloned().collect(),
+        }
+    }
+}
+
+/// Keeps an invocation in line until it is admitted, rejected or abandoned.
+struct QueuedGuard<'a> {
+    admission: &'a Admission,
+    id: u64,
+    wake: Arc<Notify>,
+}
+
+impl<'a> QueuedGuard<'a> {
+    fn new(admission: &'a Admission, state: &mut AdmissionState) -> Self {
+        state.next_id += 1;
+        let waiter = Waiter {
+            id: state.next_id,
+            wake: Arc::new(Notify::new()),
+        };
+        let guard = Self {
+            admission,
+            id: waiter.id,
+            wake: waiter.wake.clone(),
+        };
+        state.waiters.push_back(waiter);
+
+        guard
+    }
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.admission.state.lock().unwrap();
+        // Admitted waiters have already left the line.
+        if let Some(position) = state.waiters.iter().position(|waiter| waiter.id == self.id) {
+            state.waiters.remove(position);
+            if position == 0 {
+                state.wake_first();
+            }
+        }
+    }
+}
+
+/// Returns its reservation to the budget when dropped.
+pub struct ReservationGuard<'a> {
+    admission: &'a Admission,
+    id: u64,
+}
+
+impl Drop for ReservationGuard<'_> {
+    fn drop(&mut self) {
+        self.admission.release(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::poll;
+
+    use super::*;
+
+    fn budget(memory: Option<u64>, queue_timeout: Duration) -> ResourceBudget {
+        ResourceBudget {
+            memory,
+            cpu: None,
+            queue_timeout,
+        }
+    }
+
+    fn resources(memory: u64) -> Resources {
+        Resources { memory, cpu: 100 }
+    }
+
+    #[tokio::test]
+    async fn reservations_past_the_budget_are_rejected() {
+        let admission = Admission::new(budget(Some(u64::MAX - 1), Duration::ZERO));
+        let _full = admission
+            .reserve("a", resources(u64::MAX - 1))
+            .await
+            .unwrap();
+        // Would wrap around to fit without checking for overflow.
+        assert!(admission.reserve("b", resources(5)).await.is_err());
+        assert_eq!(admission.report().reserved.memory, u64::MAX - 1);
+    }
+
+    #[tokio::test]
+    async fn unbounded_totals_that_would_overflow_are_rejected() {
+        let admission = Admission::new(budget(None, Duration::ZERO));
+        let first = admission.reserve("a", resources(u64::MAX)).await.unwrap();
+        assert!(admission.reserve("b", resources(1)).await.is_err());
+        assert_eq!(admission.report().reserved.memory, u64::MAX);
+
+        drop(first);
+        assert_eq!(admission.report().reserved, Resources::default());
+        let _second = admission.reserve("b", resources(1)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn waiters_are_admitted_in_arrival_order() {
+        let admission = Admission::new(budget(Some(10), Duration::from_secs(60)));
+        let first = admission.reserve("a", resources(6)).await.unwrap();
+
+        let large = admission.reserve("b", resources(5));
+        let small = admission.reserve("c", resources(4));
+        tokio::pin!(large, small);
+        assert!(poll!(large.as_mut()).is_pending());
+        // Fits next to the first reservation, but must not overtake the larger waiter.
+        assert!(poll!(small.as_mut()).is_pending());
+        assert_eq!(admission.report().queued, 2);
+
+        drop(first);
+        let _large = large.await.unwrap();
+        let _small = small.await.unwrap();
+        assert_eq!(admission.report().reserved.memory, 9);
+        assert_eq!(admission.report().queued, 0);
+    }
+
+    #[tokio::test]
+    async fn abandoned_waiters_let_the_next_one_in() {
+        let admission = Admission::new(budget(Some(10), Duration::from_secs(60)));
+        let _first = admission.reserve("a", resources(6)).await.unwrap();
+
+        let mut large = Box::pin(admission.reserve("b", resources(5)));
+        let small = admission.reserve("c", resources(4));
+        tokio::pin!(small);
+        assert!(poll!(large.as_mut()).is_pending());
+        assert!(poll!(small.as_mut()).is_pending());
+
+        drop(large);
+        let _small = small.await.unwrap();
+        assert_eq!(admission.report().queued, 0);
+    }
+}
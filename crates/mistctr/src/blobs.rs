@@ -0,0 +1,128 @@
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+use wasmtime::Engine;
+
+pub use config::api::{Blob, digest};
+
+/// Largest binary accepted by the blob store.
+pub const MAX_BLOB_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+#[error("Blob exceeds the maximum size of {MAX_BLOB_SIZE} bytes.")]
+pub struct BlobTooLarge;
+
+/// Content-addressed store for uploaded binaries, one file per digest.
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn open(dir: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir })
+    }
+
+    pub fn contains(&self, digest: &str) -> anyhow::Result<bool> {
+        Ok(fs::exists(self.path(digest)?)?)
+    }
+
+    pub fn read(&self, digest: &str) -> anyhow::Result<Vec<u8>> {
+        if !self.contains(digest)? {
+            anyhow::bail!("Blob {digest} does not exist.");
+        }
+
+        Ok(fs::read(self.path(digest)?)?)
+    }
+
+    /// Starts an upload, which becomes visible under its digest once finished.
+    pub async fn writer(&self) -> anyhow::Result<BlobWriter> {
+        let temp = self.dir.join(format!(".upload-{}", Uuid::now_v7()));
+        let file = tokio::fs::File::create(&temp).await?;
+
+        Ok(BlobWriter {
+            file,
+            temp,
+            dir: self.dir.clone(),
+            hasher: Sha256::new(),
+            size: 0,
+        })
+    }
+
+    fn path(&self, digest: &str) -> anyhow::Result<PathBuf> {
+        let hex = digest
+            .strip_prefix("sha256:")
+            .filter(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Malformed digest \"{digest}\", expected sha256:<hex>.")
+            })?;
+
+        Ok(self.dir.join(hex.to_ascii_lowercase()))
+    }
+}
+
+/// An upload in progress; dropping it without finishing discards the data.
+pub struct BlobWriter {
+    file: tokio::fs::File,
+    temp: PathBuf,
+    dir: PathBuf,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl BlobWriter {
+    pub async fn write(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        self.size += chunk.len() as u64;
+        if self.size > MAX_BLOB_SIZE {
+            return Err(BlobTooLarge.into());
+        }
+
+        self.hasher.update(chunk);
+        self.file.write_all(chunk).await?;
+
+        Ok(())
+    }
+
+    pub async fn finish(mut self) -> anyhow::Result<Blob> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+
+        let hex = format!("{:x}", std::mem::take(&mut self.hasher).finalize());
+        tokio::fs::rename(&self.temp, self.dir.join(&hex)).await?;
+
+        Ok(Blob {
+            digest: format!("sha256:{hex}"),
+            size: self.size,
+        })
+    }
+}
+
+impl Drop for BlobWriter {
+    fn drop(&mut self) {
+        // Already renamed into place if the upload finished.
+        let _ = fs::remove_file(&self.temp);
+    }
+}
+
+/// File name an artifact compiled from `bytes` by `engine` is cached under in `dir`.
+///
+/// The name includes the engine's compatibility hash, so artifacts built with other
+/// compiler settings are compiled again instead of failing to load.
+pub fn artifact_path(dir: &Path, engine: &Engine, bytes: &[u8]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+
+    dir.join(format!(
+        "{:x}-{:016x}",
+        Sha256::digest(bytes),
+        hasher.finish()
+    ))
+}
@@ -1,19 +1,45 @@
-use std::{fs, path::PathBuf};
+use std::{fs, path::PathBuf, thread, time::Duration};
 
 use wasmtime::{Config, Engine};
 
+use crate::{
+    blobs::BlobStore, fetch::SourceFetcher, limits::ResourceMaximums, secrets::SecretStore,
+};
+
+/// Interval between engine epochs, which is how precisely execution deadlines are enforced.
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+
 pub struct ControlContext {
     storage: StorageContext,
     engine: Engine,
+    blobs: BlobStore,
+    secrets: SecretStore,
+    fetcher: SourceFetcher,
+    maximums: ResourceMaximums,
 }
 
 impl ControlContext {
-    pub fn new(root_dir: PathBuf, config: &Config) -> anyhow::Result<Self> {
+    pub fn new(
+        root_dir: PathBuf,
+        config: &Config,
+        maximums: ResourceMaximums,
+    ) -> anyhow::Result<Self> {
         let storage = StorageContext::create(root_dir)?;
 
         let engine = Engine::new(config)?;
+        start_epoch_ticker(&engine)?;
+        let blobs = BlobStore::open(storage.blob_dir.clone())?;
+        let secrets = SecretStore::open(storage.secret_dir.clone(), storage.mount_dir.clone())?;
+        let fetcher = SourceFetcher::open(storage.download_dir.clone())?;
 
-        Ok(Self { storage, engine })
+        Ok(Self {
+            storage,
+            engine,
+            blobs,
+            secrets,
+            fetcher,
+            maximums,
+        })
     }
 
     pub fn engine(&self) -> &Engine {
@@ -23,11 +49,49 @@ impl ControlContext {
     pub fn storage(&self) -> &StorageContext {
         &self.storage
     }
+
+    pub fn blobs(&self) -> &BlobStore {
+        &self.blobs
+    }
+
+    pub fn secrets(&self) -> &SecretStore {
+        &self.secrets
+    }
+
+    pub fn fetcher(&self) -> &SourceFetcher {
+        &self.fetcher
+    }
+
+    pub fn maximums(&self) -> &ResourceMaximums {
+        &self.maximums
+    }
+}
+
+/// Advances the engine's epoch every [`EPOCH_TICK`] for as long as the engine is alive.
+fn start_epoch_ticker(engine: &Engine) -> anyhow::Result<()> {
+    let weak = engine.weak();
+    thread::Builder::new()
+        .name("mist-epoch".to_string())
+        .spawn(move || {
+            while let Some(engine) = weak.upgrade() {
+                engine.increment_epoch();
+                drop(engine);
+                thread::sleep(EPOCH_TICK);
+            }
+        })?;
+
+    Ok(())
 }
 
 pub struct StorageContext {
     pub root_dir: PathBuf,
     pub artifact_dir: PathBuf,
+    pub blob_dir: PathBuf,
+    pub secret_dir: PathBuf,
+    /// Secrets of running invocations, copied out to be mounted into guests.
+    pub mount_dir: PathBuf,
+    /// Cache of sources downloaded from URLs and OCI registries.
+    pub download_dir: PathBuf,
     pub config_dir: PathBuf,
 }
 
@@ -35,6 +99,10 @@ impl StorageContext {
     pub fn create(root_dir: PathBuf) -> anyhow::Result<Self> {
         let storage = Self {
             artifact_dir: root_dir.join("artifacts"),
+            blob_dir: root_dir.join("blobs"),
+            secret_dir: root_dir.join("secrets"),
+            mount_dir: root_dir.join("mounts"),
+            download_dir: root_dir.join("downloads"),
             config_dir: root_dir.join("config"),
             root_dir,
         };
@@ -1,104 +1,754 @@
-use std::{fs, sync::Arc};
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use anyhow::Context;
+use base64::{Engine as _, prelude::BASE64_STANDARD};
 use config::{RootConfig, Spec, SpecSource};
-use tokio::io::AsyncReadExt;
+use thiserror::Error;
+use tokio::{io::AsyncReadExt, sync::mpsc};
+use tracing::{Instrument, Span};
 use wasmtime::{
-    Engine, Store,
-    component::{Component, Linker},
+    Engine, FuncType, Module, Precompiled, Store,
+    component::{
+        Component, ComponentExportIndex, Linker,
+        types::{ComponentFunc, ComponentItem},
+    },
 };
 use wasmtime_wasi::{
-    ResourceTable,
+    DirPerms, FilePerms, I32Exit, ResourceTable,
     p2::{AsyncStdoutStream, WasiCtxBuilder, pipe::AsyncWriteStream},
 };
 
 use crate::{
-    context::ControlContext, limits::StoreLimitsAsyncBuilder, quantity::ResourceQuantity,
-    state::HostState,
+    blobs,
+    context::{ControlContext, EPOCH_TICK},
+    guest_log::GuestLogWriter,
+    inspect,
+    limits::{ResourceCounts, ResourceMaximums, ResourceUsage},
+    metrics::{self, ActiveInstanceGuard, LimiterMetrics},
+    secrets::{Credentials, SecretStore},
+    state::{HostState, ModuleState},
+    values::{self, InvalidArguments},
 };
 
+pub use config::api::DropletExecutionResult;
+
+/// Largest inline or WAT source accepted, since the config is stored with it.
+pub const MAX_INLINE_SOURCE_SIZE: usize = 1024 * 1024;
+
+/// Fuel every store starts with; only used to measure consumption, not to limit it.
+const INITIAL_FUEL: u64 = u64::MAX;
+/// Fuel burned between yields to the async runtime, so long-running guests share threads.
+const FUEL_YIELD_INTERVAL: u64 = 1_000_000;
+
 pub struct DropletHandle {
     pub config: RootConfig,
+    /// Incremented every time the droplet is re-created.
+    pub revision: u64,
+    /// Cached artifact the code was loaded from, shared with droplets of the same source.
+    pub(crate) artifact: PathBuf,
+    code: DropletCode,
+    secrets: SecretStore,
+    counts: ResourceCounts,
+    engine: Engine,
+    maximums: ResourceMaximums,
+}
+
+/// Compiled droplet code, either a component or a core module run with WASI preview 1.
+enum DropletCode {
+    Component(ComponentCode),
+    Module(ModuleCode),
+}
+
+struct ComponentCode {
     component: Component,
+    entrypoint: ComponentExportIndex,
+    signature: ComponentFunc,
     linker: Arc<Linker<HostState>>,
-    engine: Engine,
+}
+
+struct ModuleCode {
+    module: Module,
+    entrypoint: String,
+    signature: FuncType,
+    linker: Arc<wasmtime::Linker<ModuleState>>,
+}
+
+/// A single call of the entrypoint: the state its store starts with, the arguments, and
+/// the span the guest's execution is recorded in.
+struct Call<'a, T> {
+    state: T,
+    args: &'a serde_json::Value,
+    span: Span,
 }
 
 impl DropletHandle {
-    pub fn new(cx: &ControlContext, config: RootConfig) -> anyhow::Result<Self> {
-        let Spec::Droplet { source, .. } = &config.spec;
-
-        let artifact_path = cx.storage().artifact_dir.join(&config.metadata.name);
-        let artifact = if fs::exists(&artifact_path)? {
-            fs::read(artifact_path)?
-        } else {
-            let bytes = match source {
-                SpecSource::File { path } => fs::read(path)?,
-            };
+    pub async fn new(cx: &ControlContext, config: RootConfig) -> anyhow::Result<Self> {
+        let Spec::Droplet {
+            source, entrypoint, ..
+        } = &config.spec;
 
-            let artifact = cx.engine().precompile_component(&bytes)?;
-            fs::write(
-                cx.storage().artifact_dir.join(&config.metadata.name),
-                &artifact,
-            )?;
-            artifact
+        let bytes = match source {
+            SpecSource::File { path, .. } => fs::read(path)?,
+            SpecSource::Blob { digest } => cx.blobs().read(digest)?,
+            SpecSource::Url {
+                url,
+                sha256,
+                pull_secret,
+            } => {
+                let credentials =
+                    pull_credentials(cx, &config.metadata.namespace, pull_secret.as_deref())?;
+                cx.fetcher()
+                    .fetch_url(url, sha256, credentials.as_ref())
+                    .await?
+            }
+            SpecSource::Oci {
+                reference,
+                pull_secret,
+            } => {
+                let credentials =
+                    pull_credentials(cx, &config.metadata.namespace, pull_secret.as_deref())?;
+                cx.fetcher()
+                    .fetch_oci(reference, credentials.as_ref())
+                    .await?
+            }
+            SpecSource::Inline { base64 } => {
+                check_inline_size(base64.len())?;
+                BASE64_STANDARD
+                    .decode(base64.trim())
+                    .context("Inline source is not valid base64")?
+            }
+            SpecSource::Wat { text } => {
+                check_inline_size(text.len())?;
+                wat::parse_str(text).context("Invalid WAT source")?
+            }
+        };
+
+        let instances = inspect::core_instance_count(&bytes)?;
+
+        // Artifacts are keyed by content, so changing the source recompiles the droplet.
+        let artifact_dir = cx.storage().artifact_dir.join(&config.metadata.namespace);
+        fs::create_dir_all(&artifact_dir)?;
+        let artifact_path = blobs::artifact_path(&artifact_dir, cx.engine(), &bytes);
+        let artifact = match fs::read(&artifact_path) {
+            Ok(artifact) => artifact,
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            Err(_) => {
+                let started = Instant::now();
+                // Compiling takes long enough to stall other tasks on this worker.
+                let engine = cx.engine().clone();
+                let (_, artifact) =
+                    tokio::task::spawn_blocking(move || inspect::precompile(&engine, &bytes))
+                        .await??;
+                metrics::COMPILE_SECONDS
+                    .with_label_values(&[&config.metadata.id()])
+                    .observe(started.elapsed().as_secs_f64());
+                fs::write(&artifact_path, &artifact)?;
+                artifact
+            }
         };
 
-        let component = unsafe { Component::deserialize(cx.engine(), artifact) }?;
+        let (code, resources) = match Engine::detect_precompiled(&artifact) {
+            Some(Precompiled::Component) => {
+                let component = unsafe { Component::deserialize(cx.engine(), artifact) }?;
+                let (entrypoint, signature) = resolve_entrypoint(
+                    &component,
+                    entrypoint.as_deref().unwrap_or(Spec::DEFAULT_ENTRYPOINT),
+                )?;
 
-        let mut linker = Linker::new(cx.engine());
-        wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+                let linker = HostState::linker(cx.engine())?;
+                let missing = inspect::unsatisfied_imports(&linker, &component)?;
+                if !missing.is_empty() {
+                    anyhow::bail!(
+                        "Component imports {} which the host cannot provide.",
+                        missing.join(", ")
+                    );
+                }
+
+                let resources = component.resources_required();
+                let code = DropletCode::Component(ComponentCode {
+                    component,
+                    entrypoint,
+                    signature,
+                    linker: Arc::new(linker),
+                });
+                (code, resources)
+            }
+            Some(Precompiled::Module) => {
+                let module = unsafe { Module::deserialize(cx.engine(), artifact) }?;
+                let entrypoint = entrypoint
+                    .as_deref()
+                    .unwrap_or(Spec::DEFAULT_MODULE_ENTRYPOINT);
+                let signature = module
+                    .get_export(entrypoint)
+                    .with_context(|| format!("Module does not export `{entrypoint}`."))?
+                    .func()
+                    .with_context(|| format!("Export `{entrypoint}` is not a function."))?
+                    .clone();
+                values::check_core_signature(&signature)
+                    .with_context(|| format!("Entrypoint `{entrypoint}` cannot be invoked"))?;
+
+                let linker = ModuleState::linker(cx.engine())?;
+                let missing = inspect::unsatisfied_module_imports(&linker, &module);
+                if !missing.is_empty() {
+                    anyhow::bail!(
+                        "Module imports {} which the host cannot provide.",
+                        missing.join(", ")
+                    );
+                }
+                linker.instantiate_pre(&module)?;
+
+                let resources = Some(module.resources_required());
+                let code = DropletCode::Module(ModuleCode {
+                    module,
+                    entrypoint: entrypoint.to_string(),
+                    signature,
+                    linker: Arc::new(linker),
+                });
+                (code, resources)
+            }
+            None => anyhow::bail!("Malformed droplet artifact."),
+        };
+        // Components that import core modules do not know what those will create.
+        let counts = ResourceCounts {
+            instances,
+            tables: resources
+                .as_ref()
+                .map_or(0, |resources| resources.num_tables as usize),
+            memories: resources.map_or(0, |resources| resources.num_memories as usize),
+        };
 
         Ok(Self {
             config,
+            revision: 1,
+            artifact: artifact_path,
             engine: cx.engine().clone(),
-            component,
-            linker: Arc::new(linker),
+            maximums: *cx.maximums(),
+            secrets: cx.secrets().clone(),
+            code,
+            counts,
         })
     }
 
-    pub async fn run(&self) -> anyhow::Result<DropletExecutionResult> {
+    pub fn with_revision(mut self, revision: u64) -> Self {
+        self.revision = revision;
+        self
+    }
+
+    /// Signature of the droplet's entrypoint, in WIT for components and in the
+    /// text format for core modules.
+    pub fn signature(&self) -> String {
+        match &self.code {
+            DropletCode::Component(code) => values::render_signature(&code.signature),
+            DropletCode::Module(code) => values::render_core_signature(&code.signature),
+        }
+    }
+
+    /// Checks that `args` can be passed to the entrypoint, without running it.
+    pub fn check_args(&self, args: &serde_json::Value) -> Result<(), InvalidArguments> {
+        match &self.code {
+            DropletCode::Component(code) => values::json_to_params(&code.signature, args).map(drop),
+            DropletCode::Module(code) => {
+                values::json_to_core_params(&code.signature, args).map(drop)
+            }
+        }
+        .map_err(InvalidArguments)
+    }
+
+    pub async fn run(
+        &self,
+        args: &serde_json::Value,
+    ) -> Result<DropletExecutionResult, DropletExecutionError> {
+        self.run_with_output(args, None).await
+    }
+
+    /// Runs the droplet, also sending each line it prints to `output` while it runs.
+    ///
+    /// `output` is dropped by the time the run returns, so every line has been sent by then.
+    pub async fn run_with_output(
+        &self,
+        args: &serde_json::Value,
+        output: Option<mpsc::UnboundedSender<String>>,
+    ) -> Result<DropletExecutionResult, DropletExecutionError> {
+        let name = &self.config.metadata.id();
+        let labels = self
+            .config
+            .metadata
+            .labels
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(",");
+        let call_span =
+            tracing::info_span!("guest.call", droplet = %name, droplet.labels = %labels);
+
         let (mut reader, writer) = tokio::io::duplex(65536);
+        let writer = GuestLogWriter::new(writer, call_span.clone()).with_lines(output);
         let stdout = AsyncStdoutStream::new(AsyncWriteStream::new(16384, writer));
 
-        let table = ResourceTable::new();
+        let (_, runtime, secrets) = self.config.spec.as_droplet().unwrap();
         let mut ctx = WasiCtxBuilder::new();
-        if let Some((_, runtime, _)) = self.config.spec.as_droplet() {
-            if let Some(ref env) = runtime.env {
-                for var in env {
-                    ctx.env(var.name.clone(), var.value.clone());
-                }
-            }
+        for (name, value) in &runtime.env {
+            ctx.env(name, value);
+        }
+        for mount in runtime.filesystem.iter().flatten() {
+            let (dir_perms, file_perms) = if mount.read_only {
+                (DirPerms::READ, FilePerms::READ)
+            } else {
+                (DirPerms::all(), FilePerms::all())
+            };
+            ctx.preopened_dir(&mount.host_path, &mount.guest_path, dir_perms, file_perms)
+                .with_context(|| format!("Failed to mount {}", mount.host_path))?;
+        }
+        // Held until the invocation ends, when the copies are removed.
+        let secrets = self
+            .secrets
+            .mount(&self.config.metadata.namespace, secrets)?;
+        for (guest_dir, host_dir) in secrets.dirs() {
+            ctx.preopened_dir(
+                host_dir,
+                guest_dir.to_string_lossy(),
+                DirPerms::READ,
+                FilePerms::READ,
+            )?;
         }
 
-        let ctx = ctx.stdout(stdout).build();
+        ctx.stdout(stdout);
 
-        let (_, runtime, _) = self.config.spec.as_droplet().unwrap();
-        let memory: ResourceQuantity = runtime.resources.memory.as_str().try_into()?;
-        let limits = StoreLimitsAsyncBuilder::new()
-            .memory_size(memory.as_memory().unwrap() as usize)
+        let limits = self
+            .maximums
+            .limits(&runtime.resources)?
+            .metrics(LimiterMetrics::for_droplet(name))
             .build();
 
-        let state = HostState { ctx, table, limits };
-        let mut store = Store::new(&self.engine, state);
+        let _active = ActiveInstanceGuard::new(name);
+        // Both calls drop their store before returning, closing the guest's end of the stdout pipe.
+        let (outcome, usage) = match &self.code {
+            DropletCode::Component(code) => {
+                let state = HostState {
+                    ctx: ctx.build(),
+                    table: ResourceTable::new(),
+                    limits,
+                };
+                self.call_component(
+                    code,
+                    Call {
+                        state,
+                        args,
+                        span: call_span,
+                    },
+                )
+                .await
+            }
+            DropletCode::Module(code) => {
+                let state = ModuleState {
+                    ctx: ctx.build_p1(),
+                    limits,
+                };
+                self.call_module(
+                    code,
+                    Call {
+                        state,
+                        args,
+                        span: call_span,
+                    },
+                )
+                .await
+            }
+        };
+
+        let (stdout, result) = async {
+            let result = outcome?;
+
+            let mut stdout = vec![];
+            reader.read_to_end(&mut stdout).await?;
+            Ok((String::from_utf8(stdout)?, result))
+        }
+        .await
+        .map_err(|error| DropletExecutionError { error, usage })?;
+
+        Ok(DropletExecutionResult {
+            stdout,
+            result,
+            usage,
+        })
+    }
+
+    async fn call_component(
+        &self,
+        code: &ComponentCode,
+        call: Call<'_, HostState>,
+    ) -> (anyhow::Result<serde_json::Value>, ResourceUsage) {
+        let name = &self.config.metadata.id();
+        let mut store = Store::new(&self.engine, call.state);
+        store.limiter_async(|state| &mut state.limits);
+        if let Err(e) = start_store(&mut store, self.maximums.execution_time) {
+            return (Err(e), ResourceUsage::default());
+        }
+
+        let outcome = async {
+            let params =
+                values::json_to_params(&code.signature, call.args).map_err(InvalidArguments)?;
+            store.data().limits.check_counts(&self.counts)?;
+
+            let started = Instant::now();
+            let instance = code
+                .linker
+                .instantiate_async(&mut store, &code.component)
+                .instrument(tracing::info_span!("instantiate"))
+                .await
+                .map_err(|e| store.data().limits.instantiation_error(e))?;
+            metrics::INSTANTIATE_SECONDS
+                .with_label_values(&[name])
+                .observe(started.elapsed().as_secs_f64());
+
+            let started = Instant::now();
+            let handler = instance
+                .get_func(&mut store, code.entrypoint)
+                .context("Entrypoint is not exported by the instance.")?;
+            let mut results = values::result_slots(&code.signature);
+            let called = async {
+                handler
+                    .call_async(&mut store, &params, &mut results)
+                    .await?;
+                handler.post_return_async(&mut store).await
+            }
+            .instrument(call.span)
+            .await;
+            metrics::EXECUTION_SECONDS
+                .with_label_values(&[name])
+                .observe(started.elapsed().as_secs_f64());
+            called?;
+
+            Ok(values::results_to_json(&results))
+        }
+        .await;
+
+        let usage = ResourceUsage {
+            fuel_consumed: INITIAL_FUEL - store.get_fuel().unwrap_or(INITIAL_FUEL),
+            ..store.data().limits.usage()
+        };
+
+        (outcome, usage)
+    }
 
+    async fn call_module(
+        &self,
+        code: &ModuleCode,
+        call: Call<'_, ModuleState>,
+    ) -> (anyhow::Result<serde_json::Value>, ResourceUsage) {
+        let name = &self.config.metadata.id();
+        let mut store = Store::new(&self.engine, call.state);
         store.limiter_async(|state| &mut state.limits);
+        if let Err(e) = start_store(&mut store, self.maximums.execution_time) {
+            return (Err(e), ResourceUsage::default());
+        }
+
+        let outcome = async {
+            let params = values::json_to_core_params(&code.signature, call.args)
+                .map_err(InvalidArguments)?;
+            store.data().limits.check_counts(&self.counts)?;
+
+            let started = Instant::now();
+            let instance = code
+                .linker
+                .instantiate_async(&mut store, &code.module)
+                .instrument(tracing::info_span!("instantiate"))
+                .await
+                .map_err(|e| store.data().limits.instantiation_error(e))?;
+            metrics::INSTANTIATE_SECONDS
+                .with_label_values(&[name])
+                .observe(started.elapsed().as_secs_f64());
+
+            let started = Instant::now();
+            let handler = instance
+                .get_func(&mut store, &code.entrypoint)
+                .context("Entrypoint is not exported by the instance.")?;
+            let mut results = values::core_result_slots(&code.signature);
+            let called = handler
+                .call_async(&mut store, &params, &mut results)
+                .instrument(call.span)
+                .await;
+            metrics::EXECUTION_SECONDS
+                .with_label_values(&[name])
+                .observe(started.elapsed().as_secs_f64());
+
+            match called {
+                Ok(()) => Ok(values::core_results_to_json(&results)),
+                // `proc_exit(0)` is how WASI commands return successfully from `_start`.
+                Err(e) if e.downcast_ref::<I32Exit>().is_some_and(|exit| exit.0 == 0) => {
+                    Ok(serde_json::Value::Null)
+                }
+                Err(e) => Err(e),
+            }
+        }
+        .await;
+
+        let usage = ResourceUsage {
+            fuel_consumed: INITIAL_FUEL - store.get_fuel().unwrap_or(INITIAL_FUEL),
+            ..store.data().limits.usage()
+        };
 
-        let instance = self
-            .linker
-            .instantiate_async(&mut store, &self.component)
-            .await?;
-        let handler = instance.get_func(&mut store, "handler").unwrap();
-        handler.call_async(store, &[], &mut []).await?;
+        (outcome, usage)
+    }
+}
 
-        let mut stdout = vec![];
-        reader.read_to_end(&mut stdout).await?;
-        let stdout = String::from_utf8(stdout)?;
+/// Fills the store's fuel and interrupts it once `execution_time` has passed.
+fn start_store<T>(store: &mut Store<T>, execution_time: Duration) -> anyhow::Result<()> {
+    let ticks = execution_time.as_nanos().div_ceil(EPOCH_TICK.as_nanos());
+    store.set_epoch_deadline(u64::try_from(ticks).unwrap_or(u64::MAX));
+    store.epoch_deadline_trap();
+    store.set_fuel(INITIAL_FUEL)?;
+    store.fuel_async_yield_interval(Some(FUEL_YIELD_INTERVAL))
+}
 
-        Ok(DropletExecutionResult { stdout })
+fn check_inline_size(size: usize) -> anyhow::Result<()> {
+    if size > MAX_INLINE_SOURCE_SIZE {
+        anyhow::bail!(
+            "Inline source is {size} bytes, larger than the maximum of {MAX_INLINE_SOURCE_SIZE}; upload it as a blob instead."
+        );
     }
+
+    Ok(())
 }
 
-#[derive(Debug)]
-pub struct DropletExecutionResult {
-    pub stdout: String,
+fn pull_credentials(
+    cx: &ControlContext,
+    namespace: &str,
+    pull_secret: Option<&str>,
+) -> anyhow::Result<Option<Credentials>> {
+    pull_secret
+        .map(|name| cx.secrets().credentials(namespace, name))
+        .transpose()
+}
+
+/// Looks up the exported function `entrypoint`, either top-level or as
+/// `<interface>#<function>`, and checks it can be invoked with JSON arguments.
+fn resolve_entrypoint(
+    component: &Component,
+    entrypoint: &str,
+) -> anyhow::Result<(ComponentExportIndex, ComponentFunc)> {
+    let (interface, function) = match entrypoint.split_once('#') {
+        Some((interface, function)) => (Some(interface), function),
+        None => (None, entrypoint),
+    };
+
+    let interface = interface
+        .map(|interface| {
+            component
+                .get_export_index(None, interface)
+                .with_context(|| format!("Component does not export interface `{interface}`."))
+        })
+        .transpose()?;
+    let (item, index) = component
+        .get_export(interface.as_ref(), function)
+        .with_context(|| format!("Component does not export `{entrypoint}`."))?;
+    let ComponentItem::ComponentFunc(signature) = item else {
+        anyhow::bail!("Export `{entrypoint}` is not a function.");
+    };
+    values::check_signature(&signature)
+        .with_context(|| format!("Entrypoint `{entrypoint}` cannot be invoked"))?;
+
+    Ok((index, signature))
+}
+
+#[derive(Debug, Error)]
+#[error("Droplet {0} does not exist.")]
+pub struct DropletNotFound(pub String);
+
+/// A failed execution, together with the resources used up to the failure.
+#[derive(Debug, Error)]
+#[error("{error:#}")]
+pub struct DropletExecutionError {
+    pub error: anyhow::Error,
+    pub usage: ResourceUsage,
+}
+
+impl From<anyhow::Error> for DropletExecutionError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            usage: ResourceUsage::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Metadata, versions};
+    use serde_json::{Value, json};
+    use wasmtime::Trap;
+
+    use super::*;
+    use crate::retry::classify_error;
+
+    /// Opens `file` in the first preopened directory with `oflags` and `rights`, returning
+    /// the errno, or on success reads up to 64 bytes of it to stdout and returns 0.
+    const OPEN_FILE: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "path_open"
+            (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_read"
+            (func $fd_read (param i32 i32 i32 i32) (result i32)))
+          (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 0) "token")
+          (func $open (param $oflags i32) (param $rights i64) (result i32)
+            (local $errno i32)
+            (local.set $errno (call $path_open (i32.const 3) (i32.const 0) (i32.const 0)
+              (i32.const 5) (local.get $oflags) (local.get $rights) (i64.const 0) (i32.const 0)
+              (i32.const 16)))
+            (if (local.get $errno) (then (return (local.get $errno))))
+            (i32.store (i32.const 32) (i32.const 64))
+            (i32.store (i32.const 36) (i32.const 64))
+            (drop (call $fd_read (i32.load (i32.const 16)) (i32.const 32) (i32.const 1)
+              (i32.const 40)))
+            (i32.store (i32.const 36) (i32.load (i32.const 40)))
+            (drop (call $fd_write (i32.const 1) (i32.const 32) (i32.const 1) (i32.const 44)))
+            (i32.const 0))
+          (func (export "read") (result i32) (call $open (i32.const 0) (i64.const 2)))
+          (func (export "create") (result i32) (call $open (i32.const 1) (i64.const 66))))
+    "#;
+
+    async fn droplet(maximums: ResourceMaximums, wat: &str) -> (tempfile::TempDir, DropletHandle) {
+        droplet_with(maximums, wat, json!({}), &[]).await
+    }
+
+    /// Like [`droplet`], with `spec` merged into the droplet's spec and `secrets` stored in
+    /// its namespace beforehand.
+    async fn droplet_with(
+        maximums: ResourceMaximums,
+        wat: &str,
+        spec: Value,
+        secrets: &[(&str, &str)],
+    ) -> (tempfile::TempDir, DropletHandle) {
+        let dir = tempfile::tempdir().unwrap();
+        let cx = ControlContext::new(dir.path().to_path_buf(), &crate::engine_config(), maximums)
+            .unwrap();
+        for (name, value) in secrets {
+            cx.secrets()
+                .set(Metadata::DEFAULT_NAMESPACE, name, value.as_bytes())
+                .unwrap();
+        }
+        let mut config = json!({
+            "api_version": "hm/v2",
+            "metadata": { "name": "test" },
+            "kind": "Droplet",
+            "spec": {
+                "source": { "text": wat },
+                "runtime": { "resources": { "memory": "1Mi", "cpu": "100m" } },
+            },
+        });
+        for (key, value) in spec.as_object().unwrap() {
+            config["spec"][key] = value.clone();
+        }
+        let config = versions::migrate(config).unwrap();
+        let droplet = DropletHandle::new(&cx, config).await.unwrap();
+
+        (dir, droplet)
+    }
+
+    #[tokio::test]
+    async fn runs_past_the_execution_time_are_interrupted() {
+        let maximums = ResourceMaximums {
+            execution_time: Duration::from_millis(50),
+            ..ResourceMaximums::default()
+        };
+        let (_dir, droplet) = droplet(
+            maximums,
+            r#"(module (func (export "_start") (loop (br 0))))"#,
+        )
+        .await;
+
+        let error = droplet.run(&Value::Null).await.unwrap_err().error;
+        assert_eq!(error.downcast_ref::<Trap>(), Some(&Trap::Interrupt));
+        assert_eq!(classify_error(&error), config::RetryErrorClass::Timeout);
+    }
+
+    #[tokio::test]
+    async fn guests_read_mounted_secrets() {
+        let (dir, droplet) = droplet_with(
+            ResourceMaximums::default(),
+            OPEN_FILE,
+            json!({
+                "entrypoint": "read",
+                "secrets": [{ "name": "api-key", "mount_path": "/run/secrets/token" }],
+            }),
+            &[("api-key", "hunter2")],
+        )
+        .await;
+
+        let result = droplet.run(&Value::Null).await.unwrap();
+        assert_eq!(result.result, json!(0));
+        assert_eq!(result.stdout, "hunter2");
+        // The copy made for the invocation is gone once it ends.
+        let mounts = dir.path().join("mounts");
+        assert_eq!(fs::read_dir(mounts).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn read_only_mounts_cannot_be_written() {
+        let host = tempfile::tempdir().unwrap();
+        for read_only in [true, false] {
+            let (_dir, droplet) = droplet_with(
+                ResourceMaximums::default(),
+                OPEN_FILE,
+                json!({
+                    "entrypoint": "create",
+                    "runtime": {
+                        "resources": { "memory": "1Mi", "cpu": "100m" },
+                        "filesystem": [{
+                            "name": "data",
+                            "guest_path": "/data",
+                            "host_path": host.path(),
+                            "read_only": read_only,
+                        }],
+                    },
+                }),
+                &[],
+            )
+            .await;
+
+            let errno = droplet.run(&Value::Null).await.unwrap().result;
+            assert_eq!(errno == json!(0), !read_only, "read_only: {read_only}");
+            assert_eq!(host.path().join("token").exists(), !read_only);
+        }
+    }
+
+    #[tokio::test]
+    async fn printed_lines_are_sent_while_running() {
+        let (_dir, droplet) = droplet(
+            ResourceMaximums::default(),
+            r#"
+            (module
+              (import "wasi_snapshot_preview1" "fd_write"
+                (func $fd_write (param i32 i32 i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (data (i32.const 16) "one\ntwo\nthree")
+              (func (export "_start")
+                (i32.store (i32.const 0) (i32.const 16))
+                (i32.store (i32.const 4) (i32.const 13))
+                (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+            "#,
+        )
+        .await;
+
+        let (lines, mut output) = mpsc::unbounded_channel();
+        let result = droplet
+            .run_with_output(&Value::Null, Some(lines))
+            .await
+            .unwrap();
+
+        assert_eq!(result.stdout, "one\ntwo\nthree");
+        let mut sent = vec![];
+        while let Some(line) = output.recv().await {
+            sent.push(line);
+        }
+        assert_eq!(sent, ["one", "two", "three"]);
+    }
 }
@@ -0,0 +1,883 @@
+use std::{fmt, fs, path::PathBuf, time::Duration};
+
+use anyhow::Context;
+use reqwest::{
+    RequestBuilder, Response, StatusCode,
+    header::{ACCEPT, WWW_AUTHENTICATE},
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{blobs::MAX_BLOB_SIZE, outbound, secrets::Credentials};
+
+/// Attempts made for every request before giving up.
+const ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+/// Largest manifest accepted from a registry, as recommended by the OCI distribution spec.
+const MAX_MANIFEST_SIZE: u64 = 4 * 1024 * 1024;
+
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, application/vnd.docker.distribution.manifest.v2+json";
+
+/// Downloads remote droplet sources into a local cache keyed by SHA-256 digest.
+pub struct SourceFetcher {
+    client: reqwest::Client,
+    dir: PathBuf,
+    /// Largest binary downloaded, the same as for uploads.
+    max_size: u64,
+}
+
+impl SourceFetcher {
+    pub fn open(dir: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir.join("oci"))?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            dir,
+            max_size: MAX_BLOB_SIZE,
+        })
+    }
+
+    /// Downloads `url`, or reuses an earlier download, verifying it hashes to `sha256`.
+    pub async fn fetch_url(
+        &self,
+        url: &str,
+        sha256: &str,
+        credentials: Option<&Credentials>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let expected = parse_hex_digest(sha256)?;
+        if let Some(bytes) = self.cached(&expected)? {
+            return Ok(bytes);
+        }
+
+        let response = self
+            .send(|| authorize(self.client.get(url), credentials))
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to download {url}"))?;
+        let bytes = read_body(response, self.max_size).await?;
+        tracing::info!("Downloaded {url} ({} bytes)", bytes.len());
+
+        self.store(&expected, bytes)
+    }
+
+    /// Pulls the wasm layer of an OCI artifact.
+    ///
+    /// The reference is resolved on every call, since tags move; if the registry
+    /// cannot be reached the layer it resolved to last time is used instead.
+    pub async fn fetch_oci(
+        &self,
+        reference: &str,
+        credentials: Option<&Credentials>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let reference = OciReference::parse(reference)?;
+        let pinned = self
+            .dir
+            .join("oci")
+            .join(format!("{:x}", Sha256::digest(reference.to_string())));
+
+        let layer = match self.resolve_layer(&reference, credentials).await {
+            Ok(layer) => {
+                fs::write(&pinned, &layer)?;
+                layer
+            }
+            Err(e) => match fs::read_to_string(&pinned) {
+                Ok(layer) => {
+                    tracing::warn!(
+                        "Could not resolve {reference}, using previously pulled {layer}: {e:#}"
+                    );
+                    layer
+                }
+                Err(_) => return Err(e),
+            },
+        };
+
+        let expected = parse_hex_digest(&layer)?;
+        if let Some(bytes) = self.cached(&expected)? {
+            return Ok(bytes);
+        }
+
+        let response = self
+            .registry_get(&reference, &format!("blobs/{layer}"), None, credentials)
+            .await?;
+        let bytes = read_body(response, self.max_size).await?;
+        tracing::info!("Pulled {reference} ({} bytes)", bytes.len());
+
+        self.store(&expected, bytes)
+    }
+
+    /// Fetches the manifest and picks the layer holding the wasm binary.
+    async fn resolve_layer(
+        &self,
+        reference: &OciReference,
+        credentials: Option<&Credentials>,
+    ) -> anyhow::Result<String> {
+        let response = self
+            .registry_get(
+                reference,
+                &format!("manifests/{}", reference.reference),
+                Some(MANIFEST_MEDIA_TYPES),
+                credentials,
+            )
+            .await?;
+        let body = read_body(response, MAX_MANIFEST_SIZE).await?;
+        if let Some(expected) = reference.reference.strip_prefix("sha256:") {
+            verify(expected, &body).context("Manifest does not match its digest")?;
+        }
+
+        let manifest: Manifest = serde_json::from_slice(&body).context("Malformed manifest")?;
+        if !manifest.manifests.is_empty() {
+            anyhow::bail!("{reference} is an image index, expected a single artifact.");
+        }
+
+        let layer = match manifest
+            .layers
+            .iter()
+            .find(|layer| layer.media_type.contains("wasm"))
+        {
+            Some(layer) => layer,
+            None => match manifest.layers.as_slice() {
+                [layer] => layer,
+                _ => anyhow::bail!("{reference} has no wasm layer."),
+            },
+        };
+
+        Ok(layer.digest.clone())
+    }
+
+    /// GETs `/v2/<repository>/<path>`, answering a bearer token challenge if the registry sends one.
+    async fn registry_get(
+        &self,
+        reference: &OciReference,
+        path: &str,
+        accept: Option<&str>,
+        credentials: Option<&Credentials>,
+    ) -> anyhow::Result<Response> {
+        let url = format!(
+            "{}/v2/{}/{path}",
+            reference.base_url(),
+            reference.repository
+        );
+        let request = |credentials: Option<&Credentials>| {
+            let request = self.client.get(&url);
+            let request = match accept {
+                Some(accept) => request.header(ACCEPT, accept),
+                None => request,
+            };
+            authorize(request, credentials)
+        };
+
+        let mut response = self.send(|| request(credentials)).await?;
+        if response.status() == StatusCode::UNAUTHORIZED
+            && let Some(challenge) = response
+                .headers()
+                .get(WWW_AUTHENTICATE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+        {
+            let token = self.token(challenge, credentials).await?;
+            response = self.send(|| request(Some(&token))).await?;
+        }
+
+        response
+            .error_for_status()
+            .with_context(|| format!("Failed to pull {reference}"))
+    }
+
+    /// Exchanges credentials for a registry token as described by a `WWW-Authenticate` challenge.
+    async fn token(
+        &self,
+        challenge: &str,
+        credentials: Option<&Credentials>,
+    ) -> anyhow::Result<Credentials> {
+        let params = challenge
+            .split(',')
+            .filter_map(|param| param.trim().split_once('='))
+            .map(|(key, value)| (key, value.trim_matches('"')))
+            .collect::<Vec<_>>();
+        let realm = params
+            .iter()
+            .find(|(key, _)| *key == "realm")
+            .map(|(_, value)| *value)
+            .context("Registry challenge has no realm.")?;
+        let query = params
+            .iter()
+            .filter(|(key, _)| matches!(*key, "service" | "scope"))
+            .collect::<Vec<_>>();
+
+        let response = self
+            .send(|| authorize(self.client.get(realm).query(&query), credentials))
+            .await?
+            .error_for_status()
+            .context("Failed to authenticate with registry")?;
+
+        #[derive(Deserialize)]
+        struct Token {
+            token: Option<String>,
+            access_token: Option<String>,
+        }
+        let token: Token = response.json().await?;
+
+        token
+            .token
+            .or(token.access_token)
+            .map(Credentials::Bearer)
+            .context("Registry returned no token.")
+    }
+
+    /// Sends the request built by `request`, retrying connection errors and
+    /// server-side failures with exponential backoff.
+    async fn send(&self, request: impl Fn() -> RequestBuilder) -> anyhow::Result<Response> {
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=ATTEMPTS {
+            let retryable = match outbound::send(&self.client, request().build()?).await {
+                Ok(response)
+                    if response.status().is_server_error()
+                        || response.status() == StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    if attempt == ATTEMPTS {
+                        return Ok(response);
+                    }
+                    format!("{} responded with {}", response.url(), response.status())
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if attempt == ATTEMPTS => return Err(e.into()),
+                Err(e) => e.to_string(),
+            };
+
+            tracing::warn!("{retryable}, retrying in {backoff:?}");
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+
+    fn cached(&self, hex: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.dir.join(hex);
+        if !fs::exists(&path)? {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&path)?;
+        if verify(hex, &bytes).is_err() {
+            tracing::warn!("Discarding corrupted download {hex}");
+            fs::remove_file(path)?;
+            return Ok(None);
+        }
+
+        Ok(Some(bytes))
+    }
+
+    fn store(&self, hex: &str, bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        verify(hex, &bytes)?;
+
+        let temp = self.dir.join(format!(".{hex}"));
+        fs::write(&temp, &bytes)?;
+        fs::rename(temp, self.dir.join(hex))?;
+
+        Ok(bytes)
+    }
+}
+
+fn authorize(request: RequestBuilder, credentials: Option<&Credentials>) -> RequestBuilder {
+    match credentials {
+        Some(Credentials::Basic { username, password }) => {
+            request.basic_auth(username, Some(password))
+        }
+        Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+/// Reads a response body, giving up as soon as it is known to exceed `limit` bytes.
+async fn read_body(mut response: Response, limit: u64) -> anyhow::Result<Vec<u8>> {
+    let too_large =
+        |url: &reqwest::Url| anyhow::anyhow!("{url} is larger than the maximum of {limit} bytes.");
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit)
+    {
+        return Err(too_large(response.url()));
+    }
+
+    let mut body = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if (body.len() + chunk.len()) as u64 > limit {
+            return Err(too_large(response.url()));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+fn verify(hex: &str, bytes: &[u8]) -> anyhow::Result<()> {
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if actual != hex {
+        anyhow::bail!("Digest mismatch: expected sha256:{hex}, got sha256:{actual}.");
+    }
+
+    Ok(())
+}
+
+/// Accepts `<hex>` or `sha256:<hex>`, returning the lowercase hex digest.
+fn parse_hex_digest(digest: &str) -> anyhow::Result<String> {
+    let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Malformed SHA-256 digest \"{digest}\".");
+    }
+
+    Ok(hex.to_ascii_lowercase())
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+    /// Only present on image indexes.
+    #[serde(default)]
+    manifests: Vec<Descriptor>,
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    #[serde(rename = "mediaType")]
+    media_type: String,
+    digest: String,
+}
+
+/// `[registry/]repository[:tag|@digest]`, defaulting to Docker Hub and `latest`.
+struct OciReference {
+    registry: String,
+    repository: String,
+    /// Tag or digest.
+    reference: String,
+}
+
+impl OciReference {
+    fn parse(value: &str) -> anyhow::Result<Self> {
+        let (name, reference) = match value.split_once('@') {
+            Some((name, digest)) => (name, digest.to_string()),
+            None => match value.rsplit_once(':') {
+                Some((name, tag)) if !tag.contains('/') => (name, tag.to_string()),
+                _ => (value, "latest".to_string()),
+            },
+        };
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((registry, repository))
+                if registry.contains(['.', ':']) || registry == "localhost" =>
+            {
+                (registry.to_string(), repository.to_string())
+            }
+            Some(_) => ("registry-1.docker.io".to_string(), name.to_string()),
+            None => (
+                "registry-1.docker.io".to_string(),
+                format!("library/{name}"),
+            ),
+        };
+        if repository.is_empty() || reference.is_empty() {
+            anyhow::bail!("Malformed OCI reference \"{value}\".");
+        }
+
+        Ok(Self {
+            registry,
+            repository,
+            reference,
+        })
+    }
+
+    /// Registries on the local machine are spoken to over plain HTTP.
+    fn base_url(&self) -> String {
+        let host = self.registry.split(':').next().unwrap_or_default();
+        if matches!(host, "localhost" | "127.0.0.1") {
+            format!("http://{}", self.registry)
+        } else {
+            format!("https://{}", self.registry)
+        }
+    }
+}
+
+impl fmt::Display for OciReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.reference.contains(':') {
+            '@'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{}/{}{separator}{}",
+            self.registry, self.repository, self.reference
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicUsize, Ordering},
+        },
+    };
+
+    use axum::{
+        Json, Router,
+        body::Body,
+        extract::{Path, Query, State},
+        http::{HeaderMap, StatusCode, header},
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+    use serde_json::json;
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const WASM: &[u8] = b"\0asm\x01\0\0\0";
+    const OTHER_WASM: &[u8] = b"\0asm\x0d\0\x01\0";
+    const README: &[u8] = b"# hello";
+
+    fn digest(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    fn fetcher() -> (TempDir, SourceFetcher) {
+        let dir = TempDir::new().unwrap();
+        let fetcher = SourceFetcher::open(dir.path().to_path_buf()).unwrap();
+        (dir, fetcher)
+    }
+
+    /// Binds a port on the loopback interface, returning it with its `host:port`.
+    async fn listen() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        (listener, address)
+    }
+
+    fn serve(listener: TcpListener, router: Router) {
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    }
+
+    /// Serves `body` at `/droplet.wasm`, answering the first `failures` requests with 503.
+    async fn artifact_server(body: &'static [u8], failures: usize) -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let handler = {
+            let hits = hits.clone();
+            move || async move {
+                if hits.fetch_add(1, Ordering::SeqCst) < failures {
+                    StatusCode::SERVICE_UNAVAILABLE.into_response()
+                } else {
+                    body.into_response()
+                }
+            }
+        };
+
+        let (listener, address) = listen().await;
+        serve(listener, Router::new().route("/droplet.wasm", get(handler)));
+        (format!("http://{address}/droplet.wasm"), hits)
+    }
+
+    #[tokio::test]
+    async fn url_downloads_are_verified_and_cached() {
+        let (dir, fetcher) = fetcher();
+        let (url, hits) = artifact_server(WASM, 0).await;
+        let sha256 = digest(WASM);
+
+        assert_eq!(fetcher.fetch_url(&url, &sha256, None).await.unwrap(), WASM);
+        assert_eq!(
+            fetcher
+                .fetch_url(&url, &format!("sha256:{sha256}"), None)
+                .await
+                .unwrap(),
+            WASM
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // A corrupted cache entry is discarded and downloaded again.
+        fs::write(dir.path().join(&sha256), b"corrupted").unwrap();
+        assert_eq!(fetcher.fetch_url(&url, &sha256, None).await.unwrap(), WASM);
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn url_digest_mismatch_is_rejected() {
+        let (dir, fetcher) = fetcher();
+        let (url, _) = artifact_server(WASM, 0).await;
+        let sha256 = digest(OTHER_WASM);
+
+        let error = fetcher.fetch_url(&url, &sha256, None).await.unwrap_err();
+        assert!(error.to_string().contains("Digest mismatch"), "{error}");
+        assert!(!dir.path().join(sha256).exists());
+
+        let error = fetcher.fetch_url(&url, "sha256:1234", None).await;
+        assert!(error.is_err());
+    }
+
+    #[tokio::test]
+    async fn url_server_errors_are_retried() {
+        let (_dir, fetcher) = fetcher();
+        let (url, hits) = artifact_server(WASM, 2).await;
+
+        let bytes = fetcher.fetch_url(&url, &digest(WASM), None).await.unwrap();
+        assert_eq!(bytes, WASM);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn url_retries_give_up_after_the_last_attempt() {
+        let (_dir, fetcher) = fetcher();
+        let (url, hits) = artifact_server(WASM, usize::MAX).await;
+
+        let error = fetcher
+            .fetch_url(&url, &digest(WASM), None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Failed to download"), "{error}");
+        assert_eq!(hits.load(Ordering::SeqCst), ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn url_downloads_over_the_maximum_size_are_refused() {
+        let (dir, mut fetcher) = fetcher();
+        fetcher.max_size = WASM.len() as u64 - 1;
+
+        // Announced through Content-Length.
+        let (url, _) = artifact_server(WASM, 0).await;
+        let error = fetcher
+            .fetch_url(&url, &digest(WASM), None)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("larger than the maximum"),
+            "{error}"
+        );
+
+        // Only found out while streaming a chunked body.
+        let chunked = || async {
+            let chunks = WASM
+                .chunks(3)
+                .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()));
+            Body::from_stream(futures_util::stream::iter(chunks))
+        };
+        let (listener, address) = listen().await;
+        serve(listener, Router::new().route("/droplet.wasm", get(chunked)));
+        let error = fetcher
+            .fetch_url(
+                &format!("http://{address}/droplet.wasm"),
+                &digest(WASM),
+                None,
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("larger than the maximum"),
+            "{error}"
+        );
+        assert!(!dir.path().join(digest(WASM)).exists());
+
+        fetcher.max_size = WASM.len() as u64;
+        assert_eq!(
+            fetcher.fetch_url(&url, &digest(WASM), None).await.unwrap(),
+            WASM
+        );
+    }
+
+    /// Registry stand-in serving the repository `droplets/hello`.
+    struct Registry {
+        address: String,
+        /// Manifests by tag or digest.
+        manifests: Mutex<HashMap<String, Vec<u8>>>,
+        /// Blobs by `sha256:<hex>` digest.
+        blobs: HashMap<String, Vec<u8>>,
+        /// Token required once a client answered the bearer challenge, if any.
+        token: Option<&'static str>,
+    }
+
+    impl Registry {
+        async fn start(blobs: &[&[u8]], token: Option<&'static str>) -> Arc<Self> {
+            let (listener, address) = listen().await;
+            let registry = Arc::new(Self {
+                address,
+                manifests: Mutex::default(),
+                blobs: blobs
+                    .iter()
+                    .map(|blob| (format!("sha256:{}", digest(blob)), blob.to_vec()))
+                    .collect(),
+                token,
+            });
+
+            let router = Router::new()
+                .route(
+                    "/v2/droplets/hello/manifests/{reference}",
+                    get(Self::manifest),
+                )
+                .route("/v2/droplets/hello/blobs/{digest}", get(Self::blob))
+                .route("/token", get(Self::issue_token))
+                .with_state(registry.clone());
+            serve(listener, router);
+
+            registry
+        }
+
+        fn reference(&self, tag: &str) -> String {
+            format!("{}/droplets/hello{tag}", self.address)
+        }
+
+        /// Points `reference` at a manifest listing `layers` as media type and content.
+        fn tag(&self, reference: &str, layers: &[(&str, &[u8])]) -> Vec<u8> {
+            let layers = layers
+                .iter()
+                .map(|(media_type, bytes)| {
+                    json!({
+                        "mediaType": media_type,
+                        "digest": format!("sha256:{}", digest(bytes)),
+                        "size": bytes.len(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let manifest = serde_json::to_vec(&json!({
+                "schemaVersion": 2,
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "layers": layers,
+            }))
+            .unwrap();
+
+            self.manifests
+                .lock()
+                .unwrap()
+                .insert(reference.to_string(), manifest.clone());
+            manifest
+        }
+
+        fn untag(&self, reference: &str) {
+            self.manifests.lock().unwrap().remove(reference);
+        }
+
+        fn challenge(&self, headers: &HeaderMap) -> Option<Response> {
+            let token = self.token?;
+            let authorization = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            if authorization == Some(&format!("Bearer {token}")) {
+                return None;
+            }
+
+            let challenge = format!(
+                "Bearer realm=\"http://{}/token\",service=\"stand-in\",scope=\"repository:droplets/hello:pull\"",
+                self.address
+            );
+            Some(
+                (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, challenge)],
+                )
+                    .into_response(),
+            )
+        }
+
+        async fn manifest(
+            State(registry): State<Arc<Self>>,
+            Path(reference): Path<String>,
+            headers: HeaderMap,
+        ) -> Response {
+            if let Some(challenge) = registry.challenge(&headers) {
+                return challenge;
+            }
+
+            match registry.manifests.lock().unwrap().get(&reference) {
+                Some(manifest) => manifest.clone().into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+
+        async fn blob(
+            State(registry): State<Arc<Self>>,
+            Path(digest): Path<String>,
+            headers: HeaderMap,
+        ) -> Response {
+            if let Some(challenge) = registry.challenge(&headers) {
+                return challenge;
+            }
+
+            match registry.blobs.get(&digest) {
+                Some(blob) => blob.clone().into_response(),
+                None => StatusCode::NOT_FOUND.into_response(),
+            }
+        }
+
+        /// Hands out the token to `user:password`.
+        async fn issue_token(
+            State(registry): State<Arc<Self>>,
+            Query(query): Query<HashMap<String, String>>,
+            headers: HeaderMap,
+        ) -> Response {
+            let authorization = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            // base64 of `user:password`.
+            if authorization != Some("Basic dXNlcjpwYXNzd29yZA==")
+                || query.get("service").map(String::as_str) != Some("stand-in")
+            {
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+
+            Json(json!({ "token": registry.token })).into_response()
+        }
+    }
+
+    #[tokio::test]
+    async fn oci_tag_resolves_to_the_wasm_layer() {
+        let (_dir, fetcher) = fetcher();
+        let registry = Registry::start(&[README, WASM], None).await;
+        registry.tag(
+            "v1",
+            &[("text/markdown", README), ("application/wasm", WASM)],
+        );
+
+        let bytes = fetcher
+            .fetch_oci(&registry.reference(":v1"), None)
+            .await
+            .unwrap();
+        assert_eq!(bytes, WASM);
+    }
+
+    #[tokio::test]
+    async fn oci_single_layer_is_used_whatever_its_media_type() {
+        let (_dir, fetcher) = fetcher();
+        let registry = Registry::start(&[WASM, README], None).await;
+        registry.tag("latest", &[("application/octet-stream", WASM)]);
+        registry.tag("docs", &[("text/markdown", README), ("text/plain", README)]);
+
+        let bytes = fetcher
+            .fetch_oci(&registry.reference(""), None)
+            .await
+            .unwrap();
+        assert_eq!(bytes, WASM);
+
+        let error = fetcher
+            .fetch_oci(&registry.reference(":docs"), None)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("has no wasm layer"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn oci_tags_are_resolved_again_on_every_pull() {
+        let (_dir, fetcher) = fetcher();
+        let registry = Registry::start(&[WASM, OTHER_WASM], None).await;
+        let reference = registry.reference(":v1");
+
+        registry.tag("v1", &[("application/wasm", WASM)]);
+        assert_eq!(fetcher.fetch_oci(&reference, None).await.unwrap(), WASM);
+
+        registry.tag("v1", &[("application/wasm", OTHER_WASM)]);
+        assert_eq!(
+            fetcher.fetch_oci(&reference, None).await.unwrap(),
+            OTHER_WASM
+        );
+    }
+
+    #[tokio::test]
+    async fn oci_falls_back_to_the_last_resolved_layer() {
+        let (_dir, fetcher) = fetcher();
+        let registry = Registry::start(&[WASM], None).await;
+
+        registry.tag("v1", &[("application/wasm", WASM)]);
+        fetcher
+            .fetch_oci(&registry.reference(":v1"), None)
+            .await
+            .unwrap();
+
+        registry.untag("v1");
+        assert_eq!(
+            fetcher
+                .fetch_oci(&registry.reference(":v1"), None)
+                .await
+                .unwrap(),
+            WASM
+        );
+        assert!(
+            fetcher
+                .fetch_oci(&registry.reference(":v2"), None)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn oci_manifest_digest_is_verified() {
+        let (_dir, fetcher) = fetcher();
+        let registry = Registry::start(&[WASM], None).await;
+        let manifest = registry.tag("v1", &[("application/wasm", WASM)]);
+        let pinned = format!("sha256:{}", digest(&manifest));
+        registry.tag(&pinned, &[("application/wasm", WASM)]);
+
+        let bytes = fetcher
+            .fetch_oci(&registry.reference(&format!("@{pinned}")), None)
+            .await
+            .unwrap();
+        assert_eq!(bytes, WASM);
+
+        // A registry answering a digest with some other manifest is not trusted.
+        let forged = format!("sha256:{}", digest(b"forged"));
+        registry.tag(&forged, &[("application/wasm", WASM)]);
+        let error = fetcher
+            .fetch_oci(&registry.reference(&format!("@{forged}")), None)
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{error:#}").contains("does not match its digest"),
+            "{error:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn oci_bearer_challenge_is_answered_with_credentials() {
+        let (_dir, fetcher) = fetcher();
+        let registry = Registry::start(&[WASM], Some("registry-token")).await;
+        registry.tag("v1", &[("application/wasm", WASM)]);
+        let reference = registry.reference(":v1");
+
+        let credentials = Credentials::Basic {
+            username: "user".to_string(),
+            password: "password".to_string(),
+        };
+        let bytes = fetcher
+            .fetch_oci(&reference, Some(&credentials))
+            .await
+            .unwrap();
+        assert_eq!(bytes, WASM);
+
+        let (_dir, fetcher) = self::fetcher();
+        let error = fetcher.fetch_oci(&reference, None).await.unwrap_err();
+        assert!(
+            format!("{error:#}").contains("Failed to authenticate with registry"),
+            "{error:#}"
+        );
+    }
+
+    #[test]
+    fn oci_references_default_to_docker_hub_and_latest() {
+        let reference = OciReference::parse("hello").unwrap();
+        assert_eq!(reference.registry, "registry-1.docker.io");
+        assert_eq!(reference.repository, "library/hello");
+        assert_eq!(reference.reference, "latest");
+
+        let reference = OciReference::parse("localhost:5000/droplets/hello:v1").unwrap();
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.repository, "droplets/hello");
+        assert_eq!(reference.reference, "v1");
+        assert_eq!(reference.base_url(), "http://localhost:5000");
+
+        let reference = OciReference::parse("ghcr.io/org/app@sha256:abc").unwrap();
+        assert_eq!(reference.base_url(), "https://ghcr.io");
+        assert_eq!(reference.reference, "sha256:abc");
+        assert_eq!(reference.to_string(), "ghcr.io/org/app@sha256:abc");
+
+        assert!(OciReference::parse("ghcr.io/").is_err());
+    }
+}
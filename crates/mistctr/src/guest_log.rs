@@ -0,0 +1,96 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use tokio::{io::AsyncWrite, sync::mpsc};
+use tracing::Span;
+
+/// Lines longer than this are logged in pieces.
+const MAX_LINE: usize = 8192;
+
+/// Passes guest output through to `inner`, logging every line as a child span of `span`
+/// and, if asked to, sending it on for streaming.
+///
+/// Guest output is written from wasmtime's background tasks rather than the
+/// task running the guest, so the parent span is captured up front.
+pub struct GuestLogWriter<W> {
+    inner: W,
+    span: Span,
+    line: Vec<u8>,
+    lines: Option<mpsc::UnboundedSender<String>>,
+}
+
+impl<W> GuestLogWriter<W> {
+    pub fn new(inner: W, span: Span) -> Self {
+        Self {
+            inner,
+            span,
+            line: vec![],
+            lines: None,
+        }
+    }
+
+    /// Also sends every line to `lines` as soon as it is complete.
+    pub fn with_lines(mut self, lines: Option<mpsc::UnboundedSender<String>>) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    fn record(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if byte == b'\n' {
+                self.emit();
+            } else {
+                self.line.push(byte);
+                if self.line.len() >= MAX_LINE {
+                    self.emit();
+                }
+            }
+        }
+    }
+
+    fn emit(&mut self) {
+        let line = String::from_utf8_lossy(&self.line);
+        let line = line.trim_end_matches('\r');
+
+        tracing::info_span!(parent: &self.span, "guest.log", message = line)
+            .in_scope(|| tracing::info!(target: "mist::guest", "{line}"));
+        if let Some(lines) = &self.lines {
+            // Nobody may be listening any more; the guest keeps running regardless.
+            lines.send(line.to_string()).ok();
+        }
+        self.line.clear();
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for GuestLogWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+        this.record(&buf[..written]);
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<W> Drop for GuestLogWriter<W> {
+    fn drop(&mut self) {
+        if !self.line.is_empty() {
+            self.emit();
+        }
+    }
+}
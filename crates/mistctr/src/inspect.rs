@@ -0,0 +1,437 @@
+use std::time::Instant;
+
+use wasmtime::{
+    Engine, ExternType, Module, Store,
+    component::{Component, Linker, LinkerInstance, ResourceType, types::ComponentItem},
+};
+use wasmtime_wasi::p2::WasiCtxBuilder;
+
+use crate::{
+    limits::StoreLimitsAsync,
+    state::{HostState, ModuleState},
+    values,
+};
+
+pub use config::api::{ExportReport, ImportReport, WasmKind, WasmReport};
+
+/// Tells components and core modules apart, in either the binary or the text format.
+pub fn detect(bytes: &[u8]) -> anyhow::Result<WasmKind> {
+    let binary = wat::parse_bytes(bytes)?;
+    if wasmparser::Parser::is_component(&binary) {
+        Ok(WasmKind::Component)
+    } else if wasmparser::Parser::is_core_wasm(&binary) {
+        Ok(WasmKind::Module)
+    } else {
+        anyhow::bail!("Not a WebAssembly component or module.")
+    }
+}
+
+/// Core instances instantiating `bytes` creates; modules and components nested in a
+/// component are counted as if instantiated once.
+pub fn core_instance_count(bytes: &[u8]) -> anyhow::Result<usize> {
+    let binary = wat::parse_bytes(bytes)?;
+    if wasmparser::Parser::is_core_wasm(&binary) {
+        return Ok(1);
+    }
+
+    let mut count = 0;
+    for payload in wasmparser::Parser::new(0).parse_all(&binary) {
+        if let wasmparser::Payload::InstanceSection(instances) = payload? {
+            for instance in instances {
+                if let wasmparser::Instance::Instantiate { .. } = instance? {
+                    count += 1;
+                }
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+/// Compiles `bytes` into an artifact of whichever kind they turn out to be.
+pub fn precompile(engine: &Engine, bytes: &[u8]) -> anyhow::Result<(WasmKind, Vec<u8>)> {
+    let kind = detect(bytes)?;
+    let artifact = match kind {
+        WasmKind::Component => engine.precompile_component(bytes)?,
+        WasmKind::Module => engine.precompile_module(bytes)?,
+    };
+
+    Ok((kind, artifact))
+}
+
+/// Compiles `bytes` and reports the component's world or the module's imports and exports.
+pub fn inspect(engine: &Engine, bytes: &[u8]) -> anyhow::Result<WasmReport> {
+    let started = Instant::now();
+    let (kind, artifact) = precompile(engine, bytes)?;
+    let compile_ms = started.elapsed().as_millis() as u64;
+
+    let (imports, exports) = match kind {
+        WasmKind::Component => describe_component(engine, unsafe {
+            Component::deserialize(engine, &artifact)
+        }?)?,
+        WasmKind::Module => {
+            describe_module(engine, unsafe { Module::deserialize(engine, &artifact) }?)?
+        }
+    };
+
+    Ok(WasmReport {
+        kind,
+        size: bytes.len(),
+        artifact_size: artifact.len(),
+        compile_ms,
+        imports,
+        exports,
+    })
+}
+
+fn describe_component(
+    engine: &Engine,
+    component: Component,
+) -> anyhow::Result<(Vec<ImportReport>, Vec<ExportReport>)> {
+    let ty = component.component_type();
+
+    let missing = unsatisfied_imports(&HostState::linker(engine)?, &component)?;
+    let imports = ty
+        .imports(engine)
+        .map(|(name, item)| ImportReport {
+            name: name.to_string(),
+            kind: item_kind(&item).to_string(),
+            satisfied: !missing.iter().any(|missing| missing == name),
+        })
+        .collect();
+
+    let mut exports = vec![];
+    for (name, item) in ty.exports(engine) {
+        match item {
+            ComponentItem::ComponentFunc(func) => exports.push(ExportReport {
+                name: name.to_string(),
+                signature: values::render_signature(&func),
+            }),
+            ComponentItem::ComponentInstance(instance) => {
+                for (function, item) in instance.exports(engine) {
+                    if let ComponentItem::ComponentFunc(func) = item {
+                        exports.push(ExportReport {
+                            name: format!("{name}#{function}"),
+                            signature: values::render_signature(&func),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((imports, exports))
+}
+
+fn describe_module(
+    engine: &Engine,
+    module: Module,
+) -> anyhow::Result<(Vec<ImportReport>, Vec<ExportReport>)> {
+    let missing = unsatisfied_module_imports(&ModuleState::linker(engine)?, &module);
+    let imports = module
+        .imports()
+        .map(|import| {
+            let name = format!("{}::{}", import.module(), import.name());
+            ImportReport {
+                kind: extern_kind(&import.ty()).to_string(),
+                satisfied: !missing.contains(&name),
+                name,
+            }
+        })
+        .collect();
+
+    let exports = module
+        .exports()
+        .filter_map(|export| {
+            let ExternType::Func(func) = export.ty() else {
+                return None;
+            };
+            Some(ExportReport {
+                name: export.name().to_string(),
+                signature: values::render_core_signature(&func),
+            })
+        })
+        .collect();
+
+    Ok((imports, exports))
+}
+
+/// Names of the component's imports that `linker` cannot satisfy.
+///
+/// Imports are type-checked in order and may only use resources of the ones before them,
+/// so an import is satisfied exactly when the component links with every import after it,
+/// and every unsatisfied one before it, stubbed out.
+pub fn unsatisfied_imports<T>(
+    linker: &Linker<T>,
+    component: &Component,
+) -> anyhow::Result<Vec<String>> {
+    let engine = linker.engine().clone();
+    let ty = component.component_type();
+    let imports = ty.imports(&engine).collect::<Vec<_>>();
+
+    let mut linker = linker.clone();
+    linker.allow_shadowing(true);
+
+    let mut missing = vec![];
+    let mut resources = vec![];
+    for (i, (name, item)) in imports.iter().enumerate() {
+        let mut probe = linker.clone();
+        let mut probe_resources = resources.clone();
+        collect_resources(item, &engine, &mut probe_resources);
+        for (name, item) in &imports[i + 1..] {
+            stub(&mut probe.root(), name, item, &engine, &mut probe_resources)?;
+        }
+
+        if probe.instantiate_pre(component).is_err() {
+            stub(&mut linker.root(), name, item, &engine, &mut resources)?;
+            missing.push(name.to_string());
+        } else {
+            collect_resources(item, &engine, &mut resources);
+        }
+    }
+
+    // Surfaces anything the stubs could not make up for, such as imported core modules.
+    linker.instantiate_pre(component)?;
+
+    Ok(missing)
+}
+
+/// Names of the module's imports that `linker` does not define, as `<module>::<name>`.
+pub fn unsatisfied_module_imports(
+    linker: &wasmtime::Linker<ModuleState>,
+    module: &Module,
+) -> Vec<String> {
+    // Definitions can only be looked up through a store, though nothing is instantiated.
+    let mut store = Store::new(
+        module.engine(),
+        ModuleState {
+            ctx: WasiCtxBuilder::new().build_p1(),
+            limits: StoreLimitsAsync::default(),
+        },
+    );
+
+    module
+        .imports()
+        .filter(|import| linker.get_by_import(&mut store, import).is_none())
+        .map(|import| format!("{}::{}", import.module(), import.name()))
+        .collect()
+}
+
+/// Defines `item` in `linker` with functions that fail when called.
+///
+/// Resources in `defined` were introduced by earlier imports and are left alone, since
+/// later imports only refer back to them; the ones `item` introduces are added.
+fn stub<T>(
+    linker: &mut LinkerInstance<'_, T>,
+    name: &str,
+    item: &ComponentItem,
+    engine: &Engine,
+    defined: &mut Vec<ResourceType>,
+) -> anyhow::Result<()> {
+    match item {
+        ComponentItem::ComponentFunc(_) => {
+            let name = name.to_string();
+            linker.func_new(&name.clone(), move |_, _, _| {
+                anyhow::bail!("Import `{name}` is not provided by the host.")
+            })
+        }
+        ComponentItem::ComponentInstance(instance) => {
+            let mut linker = linker.instance(name)?;
+            for (name, item) in instance.exports(engine) {
+                stub(&mut linker, name, &item, engine, defined)?;
+            }
+            Ok(())
+        }
+        ComponentItem::Resource(resource) if !defined.contains(resource) => {
+            defined.push(*resource);
+            linker.resource(name, ResourceType::host::<()>(), |_, _| Ok(()))
+        }
+        // Types need no definition, and modules and components cannot be stubbed.
+        _ => Ok(()),
+    }
+}
+
+/// Adds the resources `item` refers to that are not in `resources` yet.
+fn collect_resources(item: &ComponentItem, engine: &Engine, resources: &mut Vec<ResourceType>) {
+    match item {
+        ComponentItem::ComponentInstance(instance) => {
+            for (_, item) in instance.exports(engine) {
+                collect_resources(&item, engine, resources);
+            }
+        }
+        ComponentItem::Resource(resource) if !resources.contains(resource) => {
+            resources.push(*resource);
+        }
+        _ => {}
+    }
+}
+
+fn item_kind(item: &ComponentItem) -> &'static str {
+    match item {
+        ComponentItem::ComponentFunc(_) => "func",
+        ComponentItem::CoreFunc(_) => "core func",
+        ComponentItem::Module(_) => "module",
+        ComponentItem::Component(_) => "component",
+        ComponentItem::ComponentInstance(_) => "instance",
+        ComponentItem::Type(_) => "type",
+        ComponentItem::Resource(_) => "resource",
+    }
+}
+
+fn extern_kind(ty: &ExternType) -> &'static str {
+    match ty {
+        ExternType::Func(_) => "func",
+        ExternType::Global(_) => "global",
+        ExternType::Table(_) => "table",
+        ExternType::Memory(_) => "memory",
+        ExternType::Tag(_) => "tag",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wasmtime::component::Resource;
+
+    use super::*;
+
+    struct Stream;
+
+    fn engine() -> Engine {
+        Engine::new(&crate::engine_config()).unwrap()
+    }
+
+    /// Host defining a resource in one interface and handing it out from another, at a
+    /// newer patch version than the components below import.
+    fn host_linker(engine: &Engine) -> Linker<()> {
+        let mut linker = Linker::new(engine);
+        linker
+            .instance("acme:host/io@0.2.3")
+            .unwrap()
+            .resource("stream", ResourceType::host::<Stream>(), |_, _| Ok(()))
+            .unwrap();
+        linker
+            .instance("acme:host/out@0.2.3")
+            .unwrap()
+            .func_wrap("get-stream", |_, ()| Ok((Resource::<Stream>::new_own(0),)))
+            .unwrap();
+        linker
+    }
+
+    fn component(engine: &Engine, wat: &str) -> Component {
+        Component::new(engine, wat).unwrap()
+    }
+
+    #[test]
+    fn core_instances_are_counted() {
+        assert_eq!(core_instance_count(b"(module)").unwrap(), 1);
+        assert_eq!(
+            core_instance_count(
+                br#"(component
+                    (core module $a)
+                    (core module $b (memory (export "m") 1))
+                    (core instance (instantiate $a))
+                    (core instance (instantiate $a))
+                    (core instance $b (instantiate $b))
+                    (core instance (export "m" (memory $b "m")))
+                )"#
+            )
+            .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn satisfied_imports_are_not_reported() {
+        let engine = engine();
+        let component = component(
+            &engine,
+            r#"(component $c
+                (import "acme:host/io@0.2.0" (instance $io
+                    (export "stream" (type (sub resource)))
+                ))
+                (alias export $io "stream" (type $stream))
+                (import "acme:host/out@0.2.0" (instance
+                    (alias outer $c $stream (type $s))
+                    (export "stream" (type $s2 (eq $s)))
+                    (export "get-stream" (func (result (own $s2))))
+                ))
+            )"#,
+        );
+
+        let missing = unsatisfied_imports(&host_linker(&engine), &component).unwrap();
+        assert!(missing.is_empty(), "{missing:?}");
+    }
+
+    #[test]
+    fn every_unsatisfied_import_is_reported() {
+        let engine = engine();
+        let component = component(
+            &engine,
+            r#"(component $c
+                (import "acme:host/io@0.2.0" (instance $io
+                    (export "stream" (type (sub resource)))
+                ))
+                (alias export $io "stream" (type $stream))
+                (import "acme:other/thing" (func))
+                (import "acme:host/out@0.2.0" (instance
+                    (alias outer $c $stream (type $s))
+                    (export "stream" (type $s2 (eq $s)))
+                    (export "get-stream" (func (result (own $s2))))
+                ))
+                (import "acme:host/sink@0.2.0" (instance
+                    (alias outer $c $stream (type $s))
+                    (export "stream" (type $s2 (eq $s)))
+                    (export "put-stream" (func (param "stream" (own $s2))))
+                ))
+                (import "acme:host/clock@1.0.0" (instance
+                    (export "instant" (type (sub resource)))
+                    (export "now" (func (result u64)))
+                ))
+            )"#,
+        );
+
+        let missing = unsatisfied_imports(&host_linker(&engine), &component).unwrap();
+        assert_eq!(
+            missing,
+            [
+                "acme:other/thing",
+                "acme:host/sink@0.2.0",
+                "acme:host/clock@1.0.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn mistyped_imports_are_reported() {
+        let engine = engine();
+        let component = component(
+            &engine,
+            r#"(component
+                (import "acme:host/out@0.2.0" (instance
+                    (export "get-stream" (func (result u32)))
+                ))
+            )"#,
+        );
+
+        let missing = unsatisfied_imports(&host_linker(&engine), &component).unwrap();
+        assert_eq!(missing, ["acme:host/out@0.2.0"]);
+    }
+
+    #[test]
+    fn module_imports_missing_from_wasi_are_reported() {
+        let engine = engine();
+        let module = Module::new(
+            &engine,
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func (param i32 i32 i32 i32) (result i32)))
+                (import "env" "missing" (func))
+            )"#,
+        )
+        .unwrap();
+
+        let missing = unsatisfied_module_imports(&ModuleState::linker(&engine).unwrap(), &module);
+        assert_eq!(missing, ["env::missing"]);
+    }
+}
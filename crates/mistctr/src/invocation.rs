@@ -0,0 +1,279 @@
+use std::{env, sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::Deserialize;
+use uuid::Builder;
+
+use crate::ControlPanel;
+
+pub use config::api::{
+    Caller, InvocationOrigin, InvocationOutcome, InvocationRecord, InvocationSource,
+};
+
+/// How often records past the retention are removed.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InvocationFilter {
+    pub outcome: Option<InvocationOutcome>,
+    /// Source kind: `api`, `job` or `trigger`.
+    pub source: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl InvocationFilter {
+    fn matches(&self, record: &InvocationRecord) -> bool {
+        self.outcome.is_none_or(|outcome| record.outcome == outcome)
+            && self
+                .source
+                .as_deref()
+                .is_none_or(|source| record.origin.source.kind() == source)
+            && self.since.is_none_or(|since| record.started_at >= since)
+            && self.until.is_none_or(|until| record.started_at <= until)
+    }
+}
+
+/// How long invocation records are kept for, per droplet.
+#[derive(Debug, Clone)]
+pub struct HistoryRetention {
+    pub max_age: Duration,
+    pub max_count: usize,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+            max_count: 1000,
+        }
+    }
+}
+
+impl HistoryRetention {
+    /// Reads the retention from `MIST_HISTORY_MAX_AGE` (e.g. `7d`) and
+    /// `MIST_HISTORY_MAX_COUNT`.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let mut retention = Self::default();
+
+        if let Some(max_age) = var("MIST_HISTORY_MAX_AGE") {
+            retention.max_age = humantime::parse_duration(&max_age)
+                .map_err(|e| anyhow::anyhow!("Invalid MIST_HISTORY_MAX_AGE \"{max_age}\": {e}"))?;
+        }
+        if let Some(max_count) = var("MIST_HISTORY_MAX_COUNT") {
+            retention.max_count = max_count.parse().map_err(|e| {
+                anyhow::anyhow!("Invalid MIST_HISTORY_MAX_COUNT \"{max_count}\": {e}")
+            })?;
+        }
+
+        Ok(retention)
+    }
+}
+
+/// Invocation records, stored in the `invocations` tree under `<droplet>/<record id>`.
+///
+/// Record ids are UUIDv7, so a droplet's records are ordered oldest to newest and records
+/// past the retention can be found by their keys alone.
+pub struct InvocationHistory {
+    tree: sled::Tree,
+    retention: HistoryRetention,
+}
+
+impl InvocationHistory {
+    pub fn open(db: &sled::Db, retention: HistoryRetention) -> anyhow::Result<Self> {
+        Ok(Self {
+            tree: db.open_tree("invocations")?,
+            retention,
+        })
+    }
+
+    pub fn set_retention(&mut self, retention: HistoryRetention) {
+        self.retention = retention;
+    }
+
+    pub fn insert(&self, record: &InvocationRecord) -> anyhow::Result<()> {
+        self.tree.insert(
+            format!("{}/{}", record.droplet, record.id),
+            serde_json::to_vec(record)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the droplet's records matching `filter`, newest first.
+    pub fn query(
+        &self,
+        droplet: &str,
+        filter: &InvocationFilter,
+    ) -> anyhow::Result<Vec<InvocationRecord>> {
+        let mut records = vec![];
+        for item in self.tree.scan_prefix(format!("{droplet}/")).rev() {
+            let (_, value) = item?;
+            let record: InvocationRecord = serde_json::from_slice(&value)?;
+
+            if filter.matches(&record) {
+                records.push(record);
+            }
+            if filter.limit.is_some_and(|limit| records.len() >= limit) {
+                break;
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Removes the records of every droplet that are older than the maximum age or not
+    /// among its newest `max_count`, returning how many were removed.
+    pub fn prune(&self) -> anyhow::Result<usize> {
+        // Ids created before the cutoff sort before the smallest id created at it.
+        let cutoff = TimeDelta::from_std(self.retention.max_age)
+            .ok()
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age))
+            .map(|cutoff| {
+                let millis = cutoff.timestamp_millis().max(0) as u64;
+                Builder::from_unix_timestamp_millis(millis, &[0; 10])
+                    .into_uuid()
+                    .to_string()
+            });
+
+        let mut removed = 0;
+        let mut droplet = None;
+        let mut kept = 0;
+        for key in self.tree.iter().keys().rev() {
+            let key = key?;
+            let Some((name, id)) = std::str::from_utf8(&key)?.rsplit_once('/') else {
+                continue;
+            };
+            // Keys sharing a droplet's prefix are contiguous, newest first in reverse.
+            if droplet.as_deref() != Some(name) {
+                droplet = Some(name.to_string());
+                kept = 0;
+            }
+
+            if kept >= self.retention.max_count || cutoff.as_deref().is_some_and(|c| id < c) {
+                self.tree.remove(&key)?;
+                removed += 1;
+            } else {
+                kept += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Periodically removes invocation records past the [`HistoryRetention`].
+pub struct HistoryPruner {
+    panel: Arc<ControlPanel>,
+}
+
+impl HistoryPruner {
+    pub fn new(panel: Arc<ControlPanel>) -> Self {
+        Self { panel }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+            match self.panel.history.prune() {
+                Ok(0) => {}
+                Ok(removed) => tracing::debug!("Pruned {removed} invocation records"),
+                Err(e) => tracing::error!("Failed to prune invocation history: {e:#}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::ResourceUsage;
+
+    fn history(max_age: Duration, max_count: usize) -> InvocationHistory {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        InvocationHistory::open(&db, HistoryRetention { max_age, max_count }).unwrap()
+    }
+
+    /// Records an invocation of `droplet` that started `age` ago.
+    fn insert(history: &InvocationHistory, droplet: &str, age: Duration) -> String {
+        let started_at = Utc::now() - TimeDelta::from_std(age).unwrap();
+        let id = Builder::from_unix_timestamp_millis(
+            started_at.timestamp_millis() as u64,
+            &rand::random(),
+        )
+        .into_uuid()
+        .to_string();
+
+        history
+            .insert(&InvocationRecord {
+                id: id.clone(),
+                droplet: droplet.to_string(),
+                revision: 1,
+                origin: InvocationOrigin::new(InvocationSource::Api, Caller::default()),
+                started_at,
+                finished_at: started_at,
+                duration_ms: 0,
+                usage: ResourceUsage::default(),
+                outcome: InvocationOutcome::Succeeded,
+                error_class: None,
+                error: None,
+                output: String::new(),
+                output_truncated: false,
+            })
+            .unwrap();
+        id
+    }
+
+    fn ids(history: &InvocationHistory, droplet: &str) -> Vec<String> {
+        history
+            .query(droplet, &InvocationFilter::default())
+            .unwrap()
+            .into_iter()
+            .map(|record| record.id)
+            .collect()
+    }
+
+    #[test]
+    fn records_older_than_the_maximum_age_are_pruned() {
+        let history = history(Duration::from_secs(3600), 100);
+        insert(&history, "default/a", Duration::from_secs(7200));
+        let recent = insert(&history, "default/a", Duration::from_secs(60));
+        insert(&history, "default/b", Duration::from_secs(3700));
+
+        assert_eq!(history.prune().unwrap(), 2);
+        assert_eq!(ids(&history, "default/a"), [recent]);
+        assert!(ids(&history, "default/b").is_empty());
+    }
+
+    #[test]
+    fn only_the_newest_records_of_each_droplet_are_kept() {
+        let history = history(Duration::from_secs(3600), 2);
+        let mut a = (1..=4)
+            .map(|age| insert(&history, "default/a", Duration::from_secs(age)))
+            .collect::<Vec<_>>();
+        // A droplet whose id extends another's is counted separately.
+        let b = (1..=2)
+            .map(|age| insert(&history, "default/a-b", Duration::from_secs(age)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(history.prune().unwrap(), 2);
+        a.truncate(2);
+        assert_eq!(ids(&history, "default/a"), a);
+        assert_eq!(ids(&history, "default/a-b"), b);
+        assert_eq!(history.prune().unwrap(), 0);
+    }
+
+    #[test]
+    fn unbounded_ages_keep_every_record() {
+        let history = history(Duration::MAX, 10);
+        insert(&history, "default/a", Duration::from_secs(365 * 24 * 3600));
+
+        assert_eq!(history.prune().unwrap(), 0);
+        assert_eq!(ids(&history, "default/a").len(), 1);
+    }
+}
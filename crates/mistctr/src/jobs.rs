@@ -0,0 +1,634 @@
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{TimeDelta, Utc};
+use reqwest::redirect;
+use thiserror::Error;
+use tokio::sync::{Mutex, Semaphore, mpsc};
+use tracing::Instrument;
+use url::{Host, Url};
+use uuid::Uuid;
+
+use crate::{
+    ControlPanel,
+    droplet::DropletExecutionResult,
+    invocation::{Caller, InvocationOrigin, InvocationSource},
+    metrics, outbound,
+};
+
+pub use config::api::{Job, JobStatus};
+
+/// How often finished jobs past the retention are removed.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A webhook jobs may not be delivered to.
+#[derive(Debug, Error)]
+#[error("Webhook {url} is not allowed: {reason}.")]
+pub struct WebhookRefused {
+    pub url: String,
+    pub reason: String,
+}
+
+/// How many jobs run at once, how long finished ones are kept and where they may be
+/// delivered to.
+#[derive(Debug, Clone)]
+pub struct JobSettings {
+    pub concurrency: usize,
+    pub max_age: Duration,
+    /// Finished jobs kept across every droplet.
+    pub max_count: usize,
+    /// Webhook hosts allowed even though they are, or resolve to, non-public addresses.
+    pub webhook_allowed_hosts: Vec<String>,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self {
+            concurrency: 16,
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+            max_count: 10_000,
+            webhook_allowed_hosts: vec![],
+        }
+    }
+}
+
+impl JobSettings {
+    /// Reads the settings from `MIST_JOB_CONCURRENCY`, `MIST_JOB_MAX_AGE` (e.g. `7d`),
+    /// `MIST_JOB_MAX_COUNT` and `MIST_WEBHOOK_ALLOWED_HOSTS` (comma separated).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let mut settings = Self::default();
+
+        if let Some(concurrency) = var("MIST_JOB_CONCURRENCY") {
+            settings.concurrency = concurrency
+                .parse()
+                .ok()
+                .filter(|&concurrency| concurrency > 0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Invalid MIST_JOB_CONCURRENCY \"{concurrency}\": expected a positive integer"
+                    )
+                })?;
+        }
+        if let Some(max_age) = var("MIST_JOB_MAX_AGE") {
+            settings.max_age = humantime::parse_duration(&max_age)
+                .map_err(|e| anyhow::anyhow!("Invalid MIST_JOB_MAX_AGE \"{max_age}\": {e}"))?;
+        }
+        if let Some(max_count) = var("MIST_JOB_MAX_COUNT") {
+            settings.max_count = max_count
+                .parse()
+                .map_err(|e| anyhow::anyhow!("Invalid MIST_JOB_MAX_COUNT \"{max_count}\": {e}"))?;
+        }
+        if let Some(hosts) = var("MIST_WEBHOOK_ALLOWED_HOSTS") {
+            settings.webhook_allowed_hosts = hosts
+                .split(',')
+                .map(|host| host.trim().to_ascii_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect();
+        }
+
+        Ok(settings)
+    }
+
+    /// Checks that `webhook` is an http(s) URL whose host, if it is an address, is public
+    /// or allow-listed. Returns the URL and whether its host is allow-listed.
+    fn check_webhook(&self, webhook: &str) -> Result<(Url, bool), WebhookRefused> {
+        let refused = |reason: String| WebhookRefused {
+            url: webhook.to_string(),
+            reason,
+        };
+
+        let url = Url::parse(webhook).map_err(|e| refused(e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(refused(format!(
+                "the {} scheme is not supported",
+                url.scheme()
+            )));
+        }
+        let Some(host) = url.host() else {
+            return Err(refused("it has no host".to_string()));
+        };
+
+        let allowed = url
+            .host_str()
+            .is_some_and(|host| self.webhook_allowed_hosts.iter().any(|h| h == host));
+        let address = match host {
+            Host::Ipv4(ip) => Some(IpAddr::V4(ip)),
+            Host::Ipv6(ip) => Some(IpAddr::V6(ip)),
+            Host::Domain(_) => None,
+        };
+        if let Some(address) = address.filter(|&address| !allowed && !is_public(address)) {
+            return Err(refused(format!("{address} is not a public address")));
+        }
+
+        Ok((url, allowed))
+    }
+}
+
+/// Whether `address` is reachable on the public internet, rather than being the daemon's
+/// own, a private network's or otherwise reserved.
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(a == 0
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+                    || ip.is_multicast())
+            }
+        },
+    }
+}
+
+/// Persisted table of asynchronous invocations, stored in the `jobs` tree.
+///
+/// Job ids are UUIDv7, so iterating the tree yields jobs in creation order.
+pub struct JobQueue {
+    tree: sled::Tree,
+    settings: JobSettings,
+    sender: mpsc::UnboundedSender<String>,
+    receiver: Mutex<mpsc::UnboundedReceiver<String>>,
+}
+
+impl JobQueue {
+    /// Opens the job table, re-queueing jobs that were still queued and failing
+    /// the ones interrupted mid-run by a daemon restart.
+    pub fn open(db: &sled::Db) -> anyhow::Result<Self> {
+        let tree = db.open_tree("jobs")?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let queue = Self {
+            tree,
+            settings: JobSettings::default(),
+            sender,
+            receiver: Mutex::new(receiver),
+        };
+
+        for job in queue.list()? {
+            match job.status {
+                JobStatus::Queued => {
+                    metrics::JOB_QUEUE_DEPTH.inc();
+                    queue.sender.send(job.id)?;
+                }
+                JobStatus::Running => {
+                    queue.update(&job.id, |job| {
+                        job.status = JobStatus::Failed;
+                        job.error = Some("Interrupted by daemon restart.".to_string());
+                        job.finished_at = Some(Utc::now());
+                    })?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(queue)
+    }
+
+    pub fn set_settings(&mut self, settings: JobSettings) {
+        self.settings = settings;
+    }
+
+    pub fn enqueue(
+        &self,
+        droplet: String,
+        args: serde_json::Value,
+        webhook: Option<String>,
+        caller: Caller,
+    ) -> anyhow::Result<Job> {
+        if let Some(ref webhook) = webhook {
+            self.settings.check_webhook(webhook)?;
+        }
+
+        let job = Job {
+            id: Uuid::now_v7().to_string(),
+            droplet,
+            status: JobStatus::Queued,
+            args,
+            webhook,
+            caller,
+            result: None,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+        };
+
+        self.store(&job)?;
+        metrics::JOB_QUEUE_DEPTH.inc();
+        self.sender.send(job.id.clone())?;
+
+        Ok(job)
+    }
+
+    pub fn get(&self, id: &str) -> anyhow::Result<Option<Job>> {
+        self.tree
+            .get(id)?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<Job>> {
+        self.tree
+            .iter()
+            .map(|item| {
+                let (_, value) = item?;
+                Ok(serde_json::from_slice(&value)?)
+            })
+            .collect()
+    }
+
+    fn store(&self, job: &Job) -> anyhow::Result<()> {
+        self.tree.insert(&job.id, serde_json::to_vec(job)?)?;
+        Ok(())
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job)) -> anyhow::Result<Job> {
+        let mut job = self
+            .get(id)?
+            .ok_or_else(|| anyhow::anyhow!("Job {id} does not exist."))?;
+        f(&mut job);
+        self.store(&job)?;
+
+        Ok(job)
+    }
+
+    fn start(&self, id: &str) -> anyhow::Result<Job> {
+        self.update(id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(Utc::now());
+        })
+    }
+
+    fn finish(
+        &self,
+        id: &str,
+        outcome: anyhow::Result<DropletExecutionResult>,
+    ) -> anyhow::Result<Job> {
+        self.update(id, |job| {
+            match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Succeeded;
+                    job.result = Some(result);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(format!("{e:#}"));
+                }
+            }
+            job.finished_at = Some(Utc::now());
+        })
+    }
+
+    /// Removes finished jobs that finished before the maximum age or are not among the
+    /// newest `max_count` finished ones, returning how many were removed. Queued and
+    /// running jobs are always kept.
+    pub fn prune(&self) -> anyhow::Result<usize> {
+        let cutoff = TimeDelta::from_std(self.settings.max_age)
+            .ok()
+            .and_then(|max_age| Utc::now().checked_sub_signed(max_age));
+
+        let mut removed = 0;
+        let mut kept = 0;
+        for item in self.tree.iter().rev() {
+            let (key, value) = item?;
+            let job: Job = serde_json::from_slice(&value)?;
+            let Some(finished_at) = job.finished_at.filter(|_| job.status.is_finished()) else {
+                continue;
+            };
+
+            if kept >= self.settings.max_count || cutoff.is_some_and(|c| finished_at < c) {
+                self.tree.remove(&key)?;
+                removed += 1;
+            } else {
+                kept += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Pulls queued jobs and runs each of them on its own task, at most
+/// [`JobSettings::concurrency`] at a time.
+pub struct JobWorker {
+    panel: Arc<ControlPanel>,
+}
+
+impl JobWorker {
+    pub fn new(panel: Arc<ControlPanel>) -> Self {
+        Self { panel }
+    }
+
+    pub async fn run(self) {
+        let permits = Arc::new(Semaphore::new(self.panel.jobs.settings.concurrency));
+
+        loop {
+            // Taken before pulling a job, so jobs past the concurrency stay queued.
+            let Ok(permit) = permits.clone().acquire_owned().await else {
+                break;
+            };
+            let Some(id) = self.panel.jobs.receiver.lock().await.recv().await else {
+                break;
+            };
+
+            metrics::JOB_QUEUE_DEPTH.dec();
+
+            let panel = self.panel.clone();
+            let span = tracing::info_span!("job", job.id = %id);
+            tokio::spawn(
+                async move {
+                    let _permit = permit;
+                    if let Err(e) = execute(&panel, &id).await {
+                        tracing::error!("Job {id}: {e}");
+                    }
+                }
+                .instrument(span),
+            );
+        }
+    }
+}
+
+async fn execute(panel: &ControlPanel, id: &str) -> anyhow::Result<()> {
+    let job = panel.jobs.start(id)?;
+
+    let outcome = panel
+        .run_droplet_with_retry(
+            &job.droplet,
+            InvocationOrigin::new(InvocationSource::Job { id: id.to_string() }, job.caller),
+            job.args,
+        )
+        .await;
+
+    let job = panel.jobs.finish(id, outcome)?;
+
+    tracing::info!("Job {id} finished: {:?}", job.status);
+
+    if let Some(ref webhook) = job.webhook {
+        notify_webhook(&panel.jobs.settings, webhook, &job)
+            .instrument(tracing::info_span!("webhook", url.full = %webhook))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// POSTs the finished job to its webhook.
+///
+/// The host is resolved up front and the request pinned to the addresses checked, so a
+/// name that resolves differently on delivery cannot reach the daemon's own network.
+async fn notify_webhook(settings: &JobSettings, webhook: &str, job: &Job) -> anyhow::Result<()> {
+    let (url, allowed) = settings.check_webhook(webhook)?;
+    let mut client = reqwest::Client::builder().redirect(redirect::Policy::none());
+    if let Some(Host::Domain(domain)) = url.host().filter(|_| !allowed) {
+        let port = url.port_or_known_default().unwrap_or_default();
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((domain, port)).await?.collect();
+        if let Some(address) = addresses.iter().find(|address| !is_public(address.ip())) {
+            return Err(WebhookRefused {
+                url: webhook.to_string(),
+                reason: format!(
+                    "{domain} resolves to {}, not a public address",
+                    address.ip()
+                ),
+            }
+            .into());
+        }
+        client = client.resolve_to_addrs(domain, &addresses);
+    }
+
+    let client = client.build()?;
+    let request = client.post(url).json(job).build()?;
+    let response = outbound::send(&client, request).await?;
+    if !response.status().is_success() {
+        anyhow::bail!("Webhook {webhook} responded with {}", response.status());
+    }
+
+    Ok(())
+}
+
+/// Periodically removes finished jobs past the [`JobSettings`] retention.
+pub struct JobPruner {
+    panel: Arc<ControlPanel>,
+}
+
+impl JobPruner {
+    pub fn new(panel: Arc<ControlPanel>) -> Self {
+        Self { panel }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+            match self.panel.jobs.prune() {
+                Ok(0) => {}
+                Ok(removed) => tracing::debug!("Pruned {removed} jobs"),
+                Err(e) => tracing::error!("Failed to prune jobs: {e:#}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::ResourceUsage;
+
+    fn db() -> sled::Db {
+        sled::Config::new().temporary(true).open().unwrap()
+    }
+
+    fn enqueue(queue: &JobQueue) -> Job {
+        queue
+            .enqueue(
+                "default/echo".to_string(),
+                serde_json::Value::Null,
+                None,
+                Caller::default(),
+            )
+            .unwrap()
+    }
+
+    fn result() -> DropletExecutionResult {
+        DropletExecutionResult {
+            stdout: "hello\n".to_string(),
+            result: serde_json::Value::Null,
+            usage: ResourceUsage::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn enqueued_jobs_are_stored_and_handed_to_the_worker() {
+        let queue = JobQueue::open(&db()).unwrap();
+        let job = enqueue(&queue);
+
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(
+            queue.get(&job.id).unwrap().unwrap().status,
+            JobStatus::Queued
+        );
+        assert_eq!(queue.receiver.lock().await.recv().await, Some(job.id));
+    }
+
+    #[test]
+    fn jobs_run_then_succeed_or_fail() {
+        let queue = JobQueue::open(&db()).unwrap();
+        let (succeeding, failing) = (enqueue(&queue), enqueue(&queue));
+
+        let job = queue.start(&succeeding.id).unwrap();
+        assert_eq!(job.status, JobStatus::Running);
+        assert!(job.started_at.is_some() && job.finished_at.is_none());
+
+        let job = queue.finish(&succeeding.id, Ok(result())).unwrap();
+        assert_eq!(job.status, JobStatus::Succeeded);
+        assert_eq!(job.result.unwrap().stdout, "hello\n");
+        assert!(job.finished_at.is_some());
+
+        queue.start(&failing.id).unwrap();
+        let job = queue
+            .finish(&failing.id, Err(anyhow::anyhow!("trapped")))
+            .unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("trapped"));
+        assert!(job.result.is_none());
+    }
+
+    #[tokio::test]
+    async fn restarts_requeue_queued_jobs_and_fail_running_ones() {
+        let db = db();
+        let queue = JobQueue::open(&db).unwrap();
+        let (queued, running, finished) = (enqueue(&queue), enqueue(&queue), enqueue(&queue));
+        queue.start(&running.id).unwrap();
+        queue.start(&finished.id).unwrap();
+        queue.finish(&finished.id, Ok(result())).unwrap();
+        drop(queue);
+
+        let queue = JobQueue::open(&db).unwrap();
+        let mut receiver = queue.receiver.lock().await;
+        assert_eq!(receiver.try_recv().ok(), Some(queued.id.clone()));
+        assert!(receiver.try_recv().is_err());
+
+        let job = queue.get(&running.id).unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.error.as_deref(), Some("Interrupted by daemon restart."));
+        assert!(job.finished_at.is_some());
+
+        assert_eq!(
+            queue.get(&queued.id).unwrap().unwrap().status,
+            JobStatus::Queued
+        );
+        assert_eq!(
+            queue.get(&finished.id).unwrap().unwrap().status,
+            JobStatus::Succeeded
+        );
+    }
+
+    #[test]
+    fn finished_jobs_past_the_retention_are_pruned() {
+        let mut queue = JobQueue::open(&db()).unwrap();
+        queue.set_settings(JobSettings {
+            max_count: 1,
+            ..Default::default()
+        });
+        let jobs: Vec<_> = (0..4).map(|_| enqueue(&queue)).collect();
+        for job in &jobs[..3] {
+            queue.start(&job.id).unwrap();
+            queue.finish(&job.id, Ok(result())).unwrap();
+        }
+
+        // The newest finished job and the queued one are kept.
+        assert_eq!(queue.prune().unwrap(), 2);
+        let kept: Vec<_> = queue
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|job| job.id)
+            .collect();
+        assert_eq!(kept, [jobs[2].id.clone(), jobs[3].id.clone()]);
+
+        queue.set_settings(JobSettings {
+            max_age: Duration::ZERO,
+            ..Default::default()
+        });
+        assert_eq!(queue.prune().unwrap(), 1);
+        assert_eq!(queue.list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn webhooks_must_be_http_to_public_or_allowed_hosts() {
+        let settings = JobSettings {
+            webhook_allowed_hosts: vec!["10.0.0.5".to_string()],
+            ..Default::default()
+        };
+
+        for webhook in [
+            "https://hooks.example.com/done",
+            "http://93.184.216.34:8080/",
+            "http://10.0.0.5/internal",
+        ] {
+            assert!(settings.check_webhook(webhook).is_ok(), "{webhook}");
+        }
+        for webhook in [
+            "ftp://hooks.example.com/",
+            "file:///etc/passwd",
+            "not a url",
+            "http://127.0.0.1:8080/ctr",
+            "http://0.0.0.0/",
+            "http://10.0.0.6/",
+            "http://192.168.1.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+        ] {
+            assert!(settings.check_webhook(webhook).is_err(), "{webhook}");
+        }
+    }
+
+    #[test]
+    fn refused_webhooks_are_not_queued() {
+        let queue = JobQueue::open(&db()).unwrap();
+        let e = queue
+            .enqueue(
+                "default/echo".to_string(),
+                serde_json::Value::Null,
+                Some("http://127.0.0.1:8080/ctr".to_string()),
+                Caller::default(),
+            )
+            .unwrap_err();
+
+        assert!(e.is::<WebhookRefused>());
+        assert!(queue.list().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn webhook_names_resolving_to_private_addresses_are_refused() {
+        let job = enqueue(&JobQueue::open(&db()).unwrap());
+        let e = notify_webhook(&JobSettings::default(), "http://localhost:1/", &job)
+            .await
+            .unwrap_err();
+
+        assert!(e.is::<WebhookRefused>(), "{e:#}");
+    }
+}
@@ -1,51 +1,185 @@
+pub mod admission;
+pub mod blobs;
 pub mod context;
 pub mod droplet;
+pub mod fetch;
+pub mod guest_log;
+pub mod inspect;
+pub mod invocation;
+pub mod jobs;
 pub mod limits;
-pub mod quantity;
+pub mod metrics;
+pub mod namespace;
+pub mod outbound;
+pub mod retry;
+pub mod scheduler;
+pub mod secrets;
 pub mod state;
+pub mod values;
+pub mod watch;
 
-use std::env;
+pub use config::selector;
+
+use std::{
+    collections::HashSet,
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::Context;
-use config::RootConfig;
+use chrono::Utc;
+use config::{ApiVersion, Metadata, RootConfig, Spec, versions};
 use dashmap::DashMap;
+use tokio::sync::mpsc;
+use uuid::Uuid;
 use wasmtime::Config;
 
 use crate::{
+    admission::{Admission, AdmissionReport, ResourceBudget, Resources},
+    blobs::BlobStore,
     context::ControlContext,
-    droplet::{DropletExecutionResult, DropletHandle},
+    droplet::{DropletExecutionResult, DropletHandle, DropletNotFound},
+    inspect::WasmReport,
+    invocation::{
+        Caller, HistoryRetention, InvocationFilter, InvocationHistory, InvocationOrigin,
+        InvocationOutcome, InvocationRecord,
+    },
+    jobs::{Job, JobQueue, JobSettings},
+    limits::ResourceMaximums,
+    namespace::{Namespace, NamespaceNotFound, NamespaceQuota, NamespaceStore, QuotaExceeded},
+    retry::{DeadLetter, DeadLetterStore, FailedAttempt, RetryPolicy},
+    scheduler::TriggerInfo,
+    secrets::SecretStore,
+    selector::LabelSelector,
 };
 
+/// Engine settings droplets are compiled and run with.
+pub fn engine_config() -> Config {
+    let mut config = Config::new();
+    config.async_support(true);
+    config.consume_fuel(true);
+    config.epoch_interruption(true);
+    config
+}
+
 pub struct ControlPanel {
-    droplets: DashMap<String, DropletHandle>,
+    /// Keyed by droplet id, `<namespace>/<name>`, as are the droplet's stored state and history.
+    /// Invocations hold their own reference, so a handle can be swapped while it runs.
+    droplets: DashMap<String, Arc<DropletHandle>>,
     cx: ControlContext,
     db: sled::Db,
+    triggers: sled::Tree,
+    jobs: JobQueue,
+    dead_letters: DeadLetterStore,
+    history: InvocationHistory,
+    revisions: sled::Tree,
+    namespaces: NamespaceStore,
+    admission: Admission,
 }
 
 impl ControlPanel {
-    pub fn default() -> anyhow::Result<Self> {
-        let mut config = Config::new();
-        config.async_support(true);
-        let cx = ControlContext::new(env::current_dir()?.join("mist"), &config)?;
+    #[allow(clippy::should_implement_trait)]
+    pub async fn default() -> anyhow::Result<Self> {
+        Self::new(ResourceMaximums::default()).await
+    }
+
+    /// Opens the control panel, allowing droplets to request limits up to `maximums`.
+    pub async fn new(maximums: ResourceMaximums) -> anyhow::Result<Self> {
+        let cx = ControlContext::new(env::current_dir()?.join("mist"), &engine_config(), maximums)?;
 
         let db = sled::open(cx.storage().root_dir.join("db"))?;
-        let droplets = Self::load_droplets_state(db.clone())?;
-        let droplets =
-            droplets
-                .into_iter()
-                .try_fold(DashMap::new(), |acc, val| -> anyhow::Result<_> {
-                    acc.insert(val.metadata.name.clone(), DropletHandle::new(&cx, val)?);
-                    anyhow::Result::Ok(acc)
-                })?;
+        namespace::migrate_legacy_state(&db)?;
+        let namespaces = NamespaceStore::open(&db)?;
+        let triggers = db.open_tree("triggers")?;
+        let jobs = JobQueue::open(&db)?;
+        let dead_letters = DeadLetterStore::open(&db)?;
+        let history = InvocationHistory::open(&db, HistoryRetention::default())?;
+        let revisions = db.open_tree("revisions")?;
+        let droplets = DashMap::new();
+        for config in Self::load_droplets_state(db.clone())? {
+            let id = config.metadata.id();
+            let revision = revisions
+                .get(&id)?
+                .map_or(Ok(1), |revision| decode_revision(&revision))?;
+            metrics::set_droplet_labels(&id, &config.metadata.labels);
+            droplets.insert(
+                id,
+                Arc::new(
+                    DropletHandle::new(&cx, config)
+                        .await?
+                        .with_revision(revision),
+                ),
+            );
+        }
+        let referenced = droplets
+            .iter()
+            .map(|droplet| droplet.artifact.clone())
+            .collect();
+        let removed = remove_unused_artifacts(&cx.storage().artifact_dir, &referenced)?;
+        if removed > 0 {
+            tracing::info!("Removed {removed} unused artifacts");
+        }
 
-        Ok(Self { droplets, db, cx })
+        Ok(Self {
+            droplets,
+            db,
+            triggers,
+            jobs,
+            dead_letters,
+            history,
+            revisions,
+            namespaces,
+            admission: Admission::new(ResourceBudget::default()),
+            cx,
+        })
     }
 
+    /// Keeps invocation records for as long as `retention` allows; see [`HistoryPruner`].
+    ///
+    /// [`HistoryPruner`]: invocation::HistoryPruner
+    pub fn with_history_retention(mut self, retention: HistoryRetention) -> Self {
+        self.history.set_retention(retention);
+        self
+    }
+
+    /// Runs, keeps and delivers jobs as `settings` allow; see [`JobWorker`] and [`JobPruner`].
+    ///
+    /// [`JobWorker`]: jobs::JobWorker
+    /// [`JobPruner`]: jobs::JobPruner
+    pub fn with_job_settings(mut self, settings: JobSettings) -> Self {
+        self.jobs.set_settings(settings);
+        self
+    }
+
+    /// Limits what concurrently running invocations may reserve; unbounded by default.
+    pub fn with_budget(mut self, budget: ResourceBudget) -> Self {
+        self.admission = Admission::new(budget);
+        self
+    }
+
+    /// Reads the stored droplet configs, rewriting any stored in an older schema.
     fn load_droplets_state(db: sled::Db) -> anyhow::Result<Vec<RootConfig>> {
         db.iter()
             .map(|item| {
-                let (_, value) = item?;
-                let config = serde_json::from_slice(&value)?;
+                let (key, value) = item?;
+                let value: serde_json::Value = serde_json::from_slice(&value)?;
+                let version = versions::api_version(&value)?;
+                let config = versions::migrate(value).with_context(|| {
+                    format!(
+                        "Failed to migrate stored config of {}",
+                        String::from_utf8_lossy(&key)
+                    )
+                })?;
+
+                if version != ApiVersion::LATEST {
+                    tracing::info!(
+                        "Migrated {} from {version} to {}",
+                        config.metadata.id(),
+                        ApiVersion::LATEST
+                    );
+                    db.insert(key, serde_json::to_vec(&config)?)?;
+                }
 
                 Ok(config)
             })
@@ -54,22 +188,491 @@ impl ControlPanel {
 }
 
 impl ControlPanel {
-    pub async fn run_droplet(&self, name: &str) -> anyhow::Result<DropletExecutionResult> {
-        let droplet = self.droplets.get(name).context("Droplet does not exist.")?;
+    /// Runs the droplet once, recording the invocation in its history.
+    #[tracing::instrument(
+        name = "run_droplet",
+        skip_all,
+        fields(droplet = name, source = origin.source.kind())
+    )]
+    pub async fn run_droplet(
+        &self,
+        name: &str,
+        origin: InvocationOrigin,
+        args: &serde_json::Value,
+    ) -> anyhow::Result<DropletExecutionResult> {
+        self.run_droplet_with_output(name, origin, args, None).await
+    }
+
+    /// Like [`Self::run_droplet`], also sending each line the droplet prints to `output`
+    /// while it runs.
+    pub async fn run_droplet_with_output(
+        &self,
+        name: &str,
+        origin: InvocationOrigin,
+        args: &serde_json::Value,
+        output: Option<mpsc::UnboundedSender<String>>,
+    ) -> anyhow::Result<DropletExecutionResult> {
+        let droplet = self
+            .droplets
+            .get(name)
+            .map(|droplet| droplet.clone())
+            .ok_or_else(|| DropletNotFound(name.to_string()))?;
+        let _permit = self
+            .namespaces
+            .acquire_invocation(namespace::namespace_of(name))?;
+        let _reservation = self
+            .admission
+            .reserve(name, Resources::declared(&droplet.config)?)
+            .await?;
+
+        let started_at = Utc::now();
+        let execution = droplet.run_with_output(args, output).await;
+        let finished_at = Utc::now();
+
+        let mut record = InvocationRecord {
+            id: Uuid::now_v7().to_string(),
+            droplet: name.to_string(),
+            revision: droplet.revision,
+            origin,
+            started_at,
+            finished_at,
+            duration_ms: (finished_at - started_at).num_milliseconds() as u64,
+            usage: Default::default(),
+            outcome: InvocationOutcome::Succeeded,
+            error_class: None,
+            error: None,
+            output: String::new(),
+            output_truncated: false,
+        };
+        match &execution {
+            Ok(result) => {
+                record.usage = result.usage;
+                record.set_output(&result.stdout);
+            }
+            Err(e) => {
+                record.usage = e.usage;
+                record.outcome = InvocationOutcome::Failed;
+                record.error_class = Some(retry::classify_error(&e.error));
+                record.error = Some(format!("{:#}", e.error));
+            }
+        }
+        metrics::INVOCATIONS
+            .with_label_values(&[
+                name,
+                if execution.is_ok() {
+                    "succeeded"
+                } else {
+                    "failed"
+                },
+            ])
+            .inc();
+        if let Err(e) = self.history.insert(&record) {
+            tracing::error!("Failed to record invocation of {name}: {e}");
+        }
+
+        execution.map_err(|e| e.error)
+    }
+
+    pub fn droplet_invocations(
+        &self,
+        name: &str,
+        filter: &InvocationFilter,
+    ) -> anyhow::Result<Vec<InvocationRecord>> {
+        if !self.droplets.contains_key(name) {
+            return Err(DropletNotFound(name.to_string()).into());
+        }
+
+        self.history.query(name, filter)
+    }
+
+    /// Runs the droplet according to its retry policy, dead-lettering the
+    /// invocation once the policy is exhausted.
+    pub(crate) async fn run_droplet_with_retry(
+        &self,
+        name: &str,
+        origin: InvocationOrigin,
+        args: serde_json::Value,
+    ) -> anyhow::Result<DropletExecutionResult> {
+        let policy = {
+            let droplet = self
+                .droplets
+                .get(name)
+                .ok_or_else(|| DropletNotFound(name.to_string()))?;
+            let Spec::Droplet { retry, .. } = &droplet.config.spec;
+            match retry {
+                Some(retry) => RetryPolicy::try_from(retry)?,
+                None => RetryPolicy::default(),
+            }
+        };
+
+        let mut attempts = vec![];
+        loop {
+            let error = match self.run_droplet(name, origin.clone(), &args).await {
+                Ok(result) => return Ok(result),
+                Err(e) => e,
+            };
+
+            let class = retry::classify_error(&error);
+            attempts.push(FailedAttempt {
+                at: Utc::now(),
+                class,
+                error: format!("{error:#}"),
+            });
 
-        droplet.run().await
+            let attempt = attempts.len() as u32;
+            if !policy.should_retry(attempt, class) {
+                let letter = self
+                    .dead_letters
+                    .insert(name.to_string(), origin, args, attempts)?;
+                tracing::warn!(
+                    "Droplet {name} failed after {attempt} attempt(s), dead-lettered as {}",
+                    letter.id
+                );
+
+                return Err(error);
+            }
+
+            let backoff = policy.backoff(attempt);
+            tracing::info!("Droplet {name} failed ({class:?}), retrying in {backoff:?}");
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    /// Queues an invocation of the droplet to be run in the background.
+    pub fn enqueue_droplet(
+        &self,
+        name: &str,
+        args: serde_json::Value,
+        webhook: Option<String>,
+        caller: Caller,
+    ) -> anyhow::Result<Job> {
+        let Some(droplet) = self.droplets.get(name) else {
+            return Err(DropletNotFound(name.to_string()).into());
+        };
+        // Rejected now rather than failing every attempt of the job.
+        droplet.check_args(&args)?;
+        drop(droplet);
+
+        self.jobs.enqueue(name.to_string(), args, webhook, caller)
+    }
+
+    pub fn get_job(&self, namespace: &str, id: &str) -> anyhow::Result<Option<Job>> {
+        Ok(self
+            .jobs
+            .get(id)?
+            .filter(|job| namespace::namespace_of(&job.droplet) == namespace))
     }
 
-    pub fn create_droplet(&self, config: RootConfig) -> anyhow::Result<()> {
-        let name = config.metadata.name.clone();
+    pub fn list_jobs(&self, namespace: &str) -> anyhow::Result<Vec<Job>> {
+        let mut jobs = self.jobs.list()?;
+        jobs.retain(|job| namespace::namespace_of(&job.droplet) == namespace);
+
+        Ok(jobs)
+    }
+
+    pub async fn create_droplet(&self, config: RootConfig) -> anyhow::Result<()> {
+        let name = config.metadata.id();
+        // Held until the droplet is inserted, so concurrent creates cannot both fit the quota.
+        let _lock = self.namespaces.lock(&config.metadata.namespace).await;
+        self.check_quota(&config)?;
+        let Spec::Droplet { runtime, .. } = &config.spec;
+        self.cx.maximums().check(&runtime.resources)?;
+        self.admission
+            .check_fits(&name, Resources::declared(&config)?)?;
+
+        let Spec::Droplet {
+            triggers, retry, ..
+        } = &config.spec;
+        for trigger in triggers.iter().flatten() {
+            scheduler::next_occurrence(trigger, Utc::now())
+                .with_context(|| format!("Invalid trigger \"{}\"", trigger.name))?;
+        }
+        if let Some(retry) = retry {
+            RetryPolicy::try_from(retry).context("Invalid retry policy")?;
+        }
+
+        let handle = DropletHandle::new(&self.cx, config.clone()).await?;
 
         self.db.insert(name.clone(), serde_json::to_vec(&config)?)?;
+        let revision = self
+            .revisions
+            .update_and_fetch(&name, |revision| {
+                let revision =
+                    revision.map_or(0, |revision| decode_revision(revision).unwrap_or(0));
+                Some((revision + 1).to_be_bytes().to_vec())
+            })?
+            .map_or(Ok(1), |revision| decode_revision(&revision))?;
 
-        let handle = DropletHandle::new(&self.cx, config)?;
-        self.droplets.insert(name, handle);
+        metrics::set_droplet_labels(&name, &config.metadata.labels);
+        let replaced = self
+            .droplets
+            .insert(name, Arc::new(handle.with_revision(revision)));
+        if let Some(replaced) = replaced {
+            self.remove_artifact_if_unused(&replaced.artifact);
+        }
 
         Ok(())
     }
+
+    /// Refuses a config that would take the namespace over its quota, counting the
+    /// droplet it replaces as gone.
+    fn check_quota(&self, config: &RootConfig) -> anyhow::Result<()> {
+        let namespace = &config.metadata.namespace;
+        let quota = self
+            .namespaces
+            .get(namespace)?
+            .ok_or_else(|| NamespaceNotFound(namespace.clone()))?
+            .quota;
+        let exceeded = |reason: String| QuotaExceeded {
+            namespace: namespace.clone(),
+            reason,
+        };
+
+        let others = self
+            .droplets
+            .iter()
+            .filter(|droplet| {
+                droplet.config.metadata.namespace == *namespace
+                    && droplet.config.metadata.name != config.metadata.name
+            })
+            .map(|droplet| droplet.config.clone())
+            .collect::<Vec<_>>();
+
+        if let Some(limit) = quota.droplets
+            && others.len() + 1 > limit
+        {
+            return Err(exceeded(format!("at most {limit} droplet(s)")).into());
+        }
+
+        if let Some(limit) = quota.memory_bytes()? {
+            let mut total = 0;
+            for config in others.iter().chain([config]) {
+                total += Resources::declared(config)?.memory;
+            }
+            if total > limit {
+                return Err(exceeded(format!(
+                    "droplets would declare {total} bytes of memory, more than {}",
+                    quota.memory.unwrap_or_default()
+                ))
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recompiles a droplet from its source and swaps it in, keeping its revision.
+    ///
+    /// Invocations already running finish on the old code; if the droplet is re-created or
+    /// deleted while compiling, the reload is dropped.
+    pub async fn reload_droplet(&self, name: &str) -> anyhow::Result<()> {
+        let Some(current) = self.droplets.get(name).map(|droplet| droplet.clone()) else {
+            return Ok(());
+        };
+
+        let handle = Arc::new(
+            DropletHandle::new(&self.cx, current.config.clone())
+                .await?
+                .with_revision(current.revision),
+        );
+        let swapped = match self.droplets.get_mut(name) {
+            Some(mut droplet) if Arc::ptr_eq(&droplet, &current) => {
+                *droplet = handle.clone();
+                true
+            }
+            _ => false,
+        };
+
+        // Whichever code is not in use any more may have been the last user of its artifact.
+        let unused = if swapped { &current } else { &handle };
+        self.remove_artifact_if_unused(&unused.artifact);
+
+        Ok(())
+    }
+
+    /// Deletes the cached artifact unless another droplet was loaded from it.
+    fn remove_artifact_if_unused(&self, artifact: &Path) {
+        if self
+            .droplets
+            .iter()
+            .any(|droplet| droplet.artifact == artifact)
+        {
+            return;
+        }
+
+        match fs::remove_file(artifact) {
+            Ok(()) => tracing::debug!("Removed unused artifact {}", artifact.display()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to remove artifact {}: {e}", artifact.display()),
+        }
+    }
+
+    /// Configs of the namespace's droplets matching `selector`, sorted by name.
+    pub fn list_droplets(&self, namespace: &str, selector: &LabelSelector) -> Vec<RootConfig> {
+        let mut configs = self
+            .droplets
+            .iter()
+            .filter(|droplet| {
+                droplet.config.metadata.namespace == namespace
+                    && selector.matches(&droplet.config.metadata.labels)
+            })
+            .map(|droplet| droplet.config.clone())
+            .collect::<Vec<_>>();
+        configs.sort_by(|a, b| a.metadata.name.cmp(&b.metadata.name));
+
+        configs
+    }
+
+    /// Removes the droplet and the state of its triggers. Its revision counter and
+    /// invocation history are kept, so re-creating it continues where it left off.
+    pub fn delete_droplet(&self, name: &str) -> anyhow::Result<bool> {
+        let Some((_, droplet)) = self.droplets.remove(name) else {
+            return Ok(false);
+        };
+
+        self.db.remove(name)?;
+        metrics::remove_droplet_labels(name);
+        self.remove_artifact_if_unused(&droplet.artifact);
+        let Spec::Droplet { triggers, .. } = &droplet.config.spec;
+        for trigger in triggers.iter().flatten() {
+            self.triggers
+                .remove(scheduler::trigger_key(name, &trigger.name))?;
+        }
+
+        Ok(true)
+    }
+
+    /// Removes every droplet of the namespace matching `selector`, returning their names.
+    pub fn delete_droplets(
+        &self,
+        namespace: &str,
+        selector: &LabelSelector,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut deleted = vec![];
+        for config in self.list_droplets(namespace, selector) {
+            if self.delete_droplet(&config.metadata.id())? {
+                deleted.push(config.metadata.name);
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    pub fn blobs(&self) -> &BlobStore {
+        self.cx.blobs()
+    }
+
+    pub fn secrets(&self) -> &SecretStore {
+        self.cx.secrets()
+    }
+
+    /// Compiles `bytes` on a blocking thread and reports what they import and export.
+    pub async fn inspect_wasm(&self, bytes: Vec<u8>) -> anyhow::Result<WasmReport> {
+        let engine = self.cx.engine().clone();
+        tokio::task::spawn_blocking(move || inspect::inspect(&engine, &bytes)).await?
+    }
+
+    pub fn get_dead_letter(&self, namespace: &str, id: &str) -> anyhow::Result<Option<DeadLetter>> {
+        Ok(self
+            .dead_letters
+            .get(id)?
+            .filter(|letter| namespace::namespace_of(&letter.droplet) == namespace))
+    }
+
+    pub fn list_dead_letters(&self, namespace: &str) -> anyhow::Result<Vec<DeadLetter>> {
+        let mut letters = self.dead_letters.list()?;
+        letters.retain(|letter| namespace::namespace_of(&letter.droplet) == namespace);
+
+        Ok(letters)
+    }
+
+    /// Queues a dead-lettered invocation again as a new job, removing it from the list.
+    pub fn replay_dead_letter(
+        &self,
+        namespace: &str,
+        id: &str,
+        caller: Caller,
+    ) -> anyhow::Result<Option<Job>> {
+        let Some(letter) = self.get_dead_letter(namespace, id)? else {
+            return Ok(None);
+        };
+
+        let job = self.enqueue_droplet(&letter.droplet, letter.args, None, caller)?;
+        self.dead_letters.remove(id)?;
+
+        Ok(Some(job))
+    }
+
+    pub fn list_triggers(&self, namespace: &str) -> anyhow::Result<Vec<TriggerInfo>> {
+        let mut triggers = scheduler::list_triggers(self)?;
+        triggers.retain(|trigger| namespace::namespace_of(&trigger.droplet) == namespace);
+
+        Ok(triggers)
+    }
+
+    /// The node's resource budget and the invocations currently holding part of it.
+    pub fn admission(&self) -> AdmissionReport {
+        self.admission.report()
+    }
+
+    pub fn namespaces(&self) -> &NamespaceStore {
+        &self.namespaces
+    }
+
+    /// Creates a namespace, returning it with its access token.
+    pub fn create_namespace(
+        &self,
+        name: &str,
+        quota: NamespaceQuota,
+    ) -> anyhow::Result<(Namespace, String)> {
+        self.namespaces.create(name, quota)
+    }
+
+    /// Deletes the namespace with all of its droplets and secrets.
+    pub fn delete_namespace(&self, name: &str) -> anyhow::Result<bool> {
+        if name == Metadata::DEFAULT_NAMESPACE {
+            anyhow::bail!("The default namespace cannot be deleted.");
+        }
+        if self.namespaces.get(name)?.is_none() {
+            return Ok(false);
+        }
+
+        for config in self.list_droplets(name, &LabelSelector::default()) {
+            self.delete_droplet(&config.metadata.id())?;
+        }
+        self.secrets().remove_namespace(name)?;
+
+        self.namespaces.remove(name)
+    }
+}
+
+/// Deletes the artifacts under `dir`, one directory per namespace, that are not in
+/// `referenced`, returning how many were deleted. Catches artifacts left behind by a
+/// crash or built with other compiler settings.
+fn remove_unused_artifacts(dir: &Path, referenced: &HashSet<PathBuf>) -> anyhow::Result<usize> {
+    let mut removed = 0;
+    for namespace in fs::read_dir(dir)? {
+        let namespace = namespace?;
+        if !namespace.file_type()?.is_dir() {
+            continue;
+        }
+
+        for artifact in fs::read_dir(namespace.path())? {
+            let path = artifact?.path();
+            if !referenced.contains(&path) {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+    }
+
+    Ok(removed)
+}
+
+fn decode_revision(bytes: &[u8]) -> anyhow::Result<u64> {
+    Ok(u64::from_be_bytes(
+        bytes.try_into().context("Malformed droplet revision.")?,
+    ))
 }
 
 impl Drop for ControlPanel {
@@ -77,3 +680,29 @@ impl Drop for ControlPanel {
         self.db.flush().expect("failed to flush db");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unreferenced_artifacts_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        for namespace in ["default", "team"] {
+            fs::create_dir(dir.path().join(namespace)).unwrap();
+            for artifact in ["current", "stale"] {
+                fs::write(dir.path().join(namespace).join(artifact), b"").unwrap();
+            }
+        }
+        let referenced = HashSet::from([
+            dir.path().join("default/current"),
+            dir.path().join("team/current"),
+        ]);
+
+        assert_eq!(remove_unused_artifacts(dir.path(), &referenced).unwrap(), 2);
+        for namespace in ["default", "team"] {
+            assert!(dir.path().join(namespace).join("current").exists());
+            assert!(!dir.path().join(namespace).join("stale").exists());
+        }
+    }
+}
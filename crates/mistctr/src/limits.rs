@@ -1,9 +1,180 @@
+use std::{env, time::Duration};
+
+use config::{RuntimeResources, quantity::ResourceQuantity};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use wasmtime::{
     DEFAULT_INSTANCE_LIMIT, DEFAULT_MEMORY_LIMIT, DEFAULT_TABLE_LIMIT, ResourceLimiter,
     ResourceLimiterAsync,
 };
 use wasmtime_wasi::async_trait;
 
+use crate::metrics::LimiterMetrics;
+
+pub use config::api::ResourceUsage;
+
+/// How long an invocation may run by default before it is interrupted.
+const DEFAULT_EXECUTION_TIME: Duration = Duration::from_secs(300);
+
+/// Largest limits a droplet may request in `spec.runtime.resources`, set by the operator.
+///
+/// Limits a droplet leaves unset are capped by these.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResourceMaximums {
+    /// Bytes each linear memory may grow to; unbounded when unset.
+    pub memory: Option<u64>,
+    /// Elements each table may grow to; unbounded when unset.
+    pub table_elements: Option<usize>,
+    pub instances: usize,
+    pub tables: usize,
+    pub memories: usize,
+    /// Wall-clock time an invocation may run before it is interrupted.
+    pub execution_time: Duration,
+}
+
+impl Default for ResourceMaximums {
+    fn default() -> Self {
+        Self {
+            memory: None,
+            table_elements: None,
+            instances: DEFAULT_INSTANCE_LIMIT,
+            tables: DEFAULT_TABLE_LIMIT,
+            memories: DEFAULT_MEMORY_LIMIT,
+            execution_time: DEFAULT_EXECUTION_TIME,
+        }
+    }
+}
+
+impl ResourceMaximums {
+    /// Reads the maximums from `MIST_MAX_MEMORY` (e.g. `1Gi`), `MIST_MAX_TABLE_ELEMENTS`,
+    /// `MIST_MAX_INSTANCES`, `MIST_MAX_TABLES`, `MIST_MAX_MEMORIES` and
+    /// `MIST_MAX_EXECUTION_TIME` (e.g. `30s`).
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let count = |name: &str| -> anyhow::Result<Option<usize>> {
+            var(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("Invalid {name} \"{value}\": {e}"))
+                })
+                .transpose()
+        };
+        let mut maximums = Self::default();
+
+        if let Some(memory) = var("MIST_MAX_MEMORY") {
+            maximums.memory = ResourceQuantity::try_from(memory.as_str())?.as_memory();
+        }
+        if let Some(table_elements) = count("MIST_MAX_TABLE_ELEMENTS")? {
+            maximums.table_elements = Some(table_elements);
+        }
+        if let Some(instances) = count("MIST_MAX_INSTANCES")? {
+            maximums.instances = instances;
+        }
+        if let Some(tables) = count("MIST_MAX_TABLES")? {
+            maximums.tables = tables;
+        }
+        if let Some(memories) = count("MIST_MAX_MEMORIES")? {
+            maximums.memories = memories;
+        }
+        if let Some(execution_time) = var("MIST_MAX_EXECUTION_TIME") {
+            maximums.execution_time = humantime::parse_duration(&execution_time).map_err(|e| {
+                anyhow::anyhow!("Invalid MIST_MAX_EXECUTION_TIME \"{execution_time}\": {e}")
+            })?;
+        }
+
+        Ok(maximums)
+    }
+
+    /// Refuses resources that ask for more than the operator allows.
+    pub fn check(&self, resources: &RuntimeResources) -> anyhow::Result<()> {
+        let not_allowed = |field: &'static str, requested: u64, maximum: u64| {
+            if requested > maximum {
+                Err(LimitNotAllowed {
+                    field,
+                    requested,
+                    maximum,
+                })
+            } else {
+                Ok(())
+            }
+        };
+
+        if let Some(maximum) = self.memory {
+            let memory = ResourceQuantity::try_from(resources.memory.as_str())?
+                .as_memory()
+                .unwrap_or(0);
+            not_allowed("memory", memory, maximum)?;
+        }
+        if let (Some(requested), Some(maximum)) = (resources.table_elements, self.table_elements) {
+            not_allowed("table_elements", requested as u64, maximum as u64)?;
+        }
+        for (field, requested, maximum) in [
+            ("instances", resources.instances, self.instances),
+            ("tables", resources.tables, self.tables),
+            ("memories", resources.memories, self.memories),
+        ] {
+            if let Some(requested) = requested {
+                not_allowed(field, requested as u64, maximum as u64)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Limits for a store running a droplet with `resources`, clamped to the maximums so
+    /// droplets created before the maximums were lowered cannot exceed them either.
+    pub fn limits(&self, resources: &RuntimeResources) -> anyhow::Result<StoreLimitsAsyncBuilder> {
+        let memory = ResourceQuantity::try_from(resources.memory.as_str())?
+            .as_memory()
+            .unwrap_or(0);
+        let memory = self.memory.map_or(memory, |maximum| memory.min(maximum));
+        let table_elements = match (resources.table_elements, self.table_elements) {
+            (Some(requested), Some(maximum)) => Some(requested.min(maximum)),
+            (requested, maximum) => requested.or(maximum),
+        };
+        let count =
+            |requested: Option<usize>, maximum: usize| requested.unwrap_or(maximum).min(maximum);
+
+        let mut builder = StoreLimitsAsyncBuilder::new()
+            .memory_size(memory as usize)
+            .instances(count(resources.instances, self.instances))
+            .tables(count(resources.tables, self.tables))
+            .memories(count(resources.memories, self.memories))
+            .trap_on_grow_failure(resources.trap_on_oom);
+        if let Some(table_elements) = table_elements {
+            builder = builder.table_elements(table_elements);
+        }
+
+        Ok(builder)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("spec.runtime.resources.{field} is {requested} but the daemon allows at most {maximum}.")]
+pub struct LimitNotAllowed {
+    pub field: &'static str,
+    pub requested: u64,
+    pub maximum: u64,
+}
+
+/// An invocation ran into one of its limits, named as in the droplet's config, or the
+/// maximum the guest declared itself.
+#[derive(Debug, Clone, Error)]
+#[error("Droplet exceeded {limit}: {detail}")]
+pub struct LimitExceeded {
+    pub limit: String,
+    pub detail: String,
+}
+
+/// Instances, tables and memories instantiating a droplet creates, at least.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceCounts {
+    pub instances: usize,
+    pub tables: usize,
+    pub memories: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct StoreLimitsAsync {
     memory_size: Option<usize>,
@@ -12,6 +183,70 @@ pub struct StoreLimitsAsync {
     tables: usize,
     memories: usize,
     trap_on_grow_failure: bool,
+    usage: ResourceUsage,
+    /// The most recent growth refused by the limits.
+    denied: Option<LimitExceeded>,
+    metrics: Option<LimiterMetrics>,
+}
+
+impl StoreLimitsAsync {
+    pub fn usage(&self) -> ResourceUsage {
+        self.usage
+    }
+
+    /// Refuses to instantiate code that creates more instances, tables or memories than the
+    /// limits allow, which the store itself only reports as an untyped error.
+    pub fn check_counts(&self, counts: &ResourceCounts) -> Result<(), LimitExceeded> {
+        for (kind, limit, required, maximum) in [
+            (
+                "instances",
+                "resources.instances",
+                counts.instances,
+                self.instances,
+            ),
+            ("tables", "resources.tables", counts.tables, self.tables),
+            (
+                "memories",
+                "resources.memories",
+                counts.memories,
+                self.memories,
+            ),
+        ] {
+            if required > maximum {
+                return Err(LimitExceeded {
+                    limit: limit.to_string(),
+                    detail: format!(
+                        "the droplet creates {required} {kind} but {maximum} are allowed"
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Names the growth the limits refused during a failed instantiation, if any.
+    pub fn instantiation_error(&self, error: anyhow::Error) -> anyhow::Error {
+        match &self.denied {
+            Some(denied) => error.context(denied.clone()),
+            None => error,
+        }
+    }
+
+    fn deny(&mut self, limit: impl Into<String>, detail: String) -> wasmtime::Result<bool> {
+        let denied = LimitExceeded {
+            limit: limit.into(),
+            detail,
+        };
+        tracing::debug!("{denied}");
+        self.usage.denied_growths += 1;
+        self.denied = Some(denied.clone());
+        if self.trap_on_grow_failure {
+            Err(denied.into())
+        } else {
+            Ok(false)
+        }
+    }
 }
 
 impl Default for StoreLimitsAsync {
@@ -23,6 +258,9 @@ impl Default for StoreLimitsAsync {
             tables: DEFAULT_TABLE_LIMIT,
             memories: DEFAULT_MEMORY_LIMIT,
             trap_on_grow_failure: false,
+            usage: ResourceUsage::default(),
+            denied: None,
+            metrics: None,
         }
     }
 }
@@ -34,17 +272,28 @@ impl ResourceLimiter for StoreLimitsAsync {
         desired: usize,
         maximum: Option<usize>,
     ) -> wasmtime::Result<bool> {
-        let allow = match self.memory_size {
-            Some(limit) if desired > limit => false,
-            _ => match maximum {
-                Some(max) if desired > max => false,
-                _ => true,
-            },
+        let denied = match (self.memory_size, maximum) {
+            (Some(limit), _) if desired > limit => Some((
+                "resources.memory",
+                format!("growing memory to {desired} bytes is over the limit of {limit} bytes"),
+            )),
+            (_, Some(max)) if desired > max => Some((
+                "the memory's declared maximum",
+                format!("growing memory to {desired} bytes is over its maximum of {max} bytes"),
+            )),
+            _ => None,
         };
-        if !allow && self.trap_on_grow_failure {
-            anyhow::bail!("forcing trap when growing memory to {desired} bytes")
-        } else {
-            Ok(allow)
+        match denied {
+            Some((limit, detail)) => {
+                if let Some(ref metrics) = self.metrics {
+                    metrics.memory_denials.inc();
+                }
+                self.deny(limit, detail)
+            }
+            None => {
+                self.usage.peak_memory = self.usage.peak_memory.max(desired);
+                Ok(true)
+            }
         }
     }
 
@@ -63,17 +312,28 @@ impl ResourceLimiter for StoreLimitsAsync {
         desired: usize,
         maximum: Option<usize>,
     ) -> wasmtime::Result<bool> {
-        let allow = match self.table_elements {
-            Some(limit) if desired > limit => false,
-            _ => match maximum {
-                Some(max) if desired > max => false,
-                _ => true,
-            },
+        let denied = match (self.table_elements, maximum) {
+            (Some(limit), _) if desired > limit => Some((
+                "resources.table_elements",
+                format!("growing a table to {desired} elements is over the limit of {limit}"),
+            )),
+            (_, Some(max)) if desired > max => Some((
+                "the table's declared maximum",
+                format!("growing a table to {desired} elements is over its maximum of {max}"),
+            )),
+            _ => None,
         };
-        if !allow && self.trap_on_grow_failure {
-            anyhow::bail!("forcing trap when growing table to {desired} elements")
-        } else {
-            Ok(allow)
+        match denied {
+            Some((limit, detail)) => {
+                if let Some(ref metrics) = self.metrics {
+                    metrics.table_denials.inc();
+                }
+                self.deny(limit, detail)
+            }
+            None => {
+                self.usage.peak_table_elements = self.usage.peak_table_elements.max(desired);
+                Ok(true)
+            }
         }
     }
 
@@ -119,9 +379,30 @@ impl ResourceLimiterAsync for StoreLimitsAsync {
     ) -> wasmtime::Result<bool> {
         <Self as ResourceLimiter>::table_growing(self, current, desired, maximum)
     }
+
+    fn memory_grow_failed(&mut self, error: anyhow::Error) -> wasmtime::Result<()> {
+        <Self as ResourceLimiter>::memory_grow_failed(self, error)
+    }
+
+    fn table_grow_failed(&mut self, error: anyhow::Error) -> wasmtime::Result<()> {
+        <Self as ResourceLimiter>::table_grow_failed(self, error)
+    }
+
+    fn instances(&self) -> usize {
+        <Self as ResourceLimiter>::instances(self)
+    }
+
+    fn tables(&self) -> usize {
+        <Self as ResourceLimiter>::tables(self)
+    }
+
+    fn memories(&self) -> usize {
+        <Self as ResourceLimiter>::memories(self)
+    }
 }
 
 /// Used to build [`StoreLimits`].
+#[derive(Default)]
 pub struct StoreLimitsAsyncBuilder(StoreLimitsAsync);
 
 impl StoreLimitsAsyncBuilder {
@@ -198,8 +479,164 @@ impl StoreLimitsAsyncBuilder {
         self
     }
 
+    /// Counters that denied memory and table growths are reported to.
+    pub fn metrics(mut self, metrics: LimiterMetrics) -> Self {
+        self.0.metrics = Some(metrics);
+        self
+    }
+
     /// Consumes this builder and returns the [`StoreLimits`].
     pub fn build(self) -> StoreLimitsAsync {
         self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resources(memory: &str) -> RuntimeResources {
+        RuntimeResources {
+            memory: memory.to_string(),
+            cpu: "100m".to_string(),
+            table_elements: None,
+            instances: None,
+            tables: None,
+            memories: None,
+            trap_on_oom: false,
+        }
+    }
+
+    fn maximums() -> ResourceMaximums {
+        ResourceMaximums {
+            memory: Some(64 * 1024 * 1024),
+            table_elements: Some(1000),
+            instances: 10,
+            tables: 5,
+            memories: 2,
+            execution_time: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn check_allows_resources_within_the_maximums() {
+        let mut requested = resources("64Mi");
+        requested.table_elements = Some(1000);
+        requested.instances = Some(10);
+        requested.tables = Some(5);
+        requested.memories = Some(2);
+        maximums().check(&requested).unwrap();
+        maximums().check(&resources("1Mi")).unwrap();
+        ResourceMaximums::default()
+            .check(&resources("1Gi"))
+            .unwrap();
+    }
+
+    #[test]
+    fn check_names_the_field_over_its_maximum() {
+        let field = |requested: &RuntimeResources| {
+            let error = maximums().check(requested).unwrap_err();
+            error.downcast::<LimitNotAllowed>().unwrap().field
+        };
+
+        assert_eq!(field(&resources("65Mi")), "memory");
+        let mut requested = resources("1Mi");
+        requested.table_elements = Some(1001);
+        assert_eq!(field(&requested), "table_elements");
+        let mut requested = resources("1Mi");
+        requested.instances = Some(11);
+        assert_eq!(field(&requested), "instances");
+        let mut requested = resources("1Mi");
+        requested.tables = Some(6);
+        assert_eq!(field(&requested), "tables");
+        let mut requested = resources("1Mi");
+        requested.memories = Some(3);
+        assert_eq!(field(&requested), "memories");
+    }
+
+    #[test]
+    fn limits_are_clamped_to_the_maximums() {
+        let mut requested = resources("1Gi");
+        requested.table_elements = Some(5000);
+        requested.instances = Some(100);
+        requested.trap_on_oom = true;
+        let limits = maximums().limits(&requested).unwrap().build();
+
+        assert_eq!(limits.memory_size, Some(64 * 1024 * 1024));
+        assert_eq!(limits.table_elements, Some(1000));
+        assert_eq!(limits.instances, 10);
+        assert_eq!(limits.tables, 5);
+        assert_eq!(limits.memories, 2);
+        assert!(limits.trap_on_grow_failure);
+    }
+
+    #[test]
+    fn limits_keep_requests_below_the_maximums() {
+        let mut requested = resources("16Mi");
+        requested.memories = Some(1);
+        let limits = maximums().limits(&requested).unwrap().build();
+        assert_eq!(limits.memory_size, Some(16 * 1024 * 1024));
+        assert_eq!(limits.memories, 1);
+
+        let limits = ResourceMaximums::default()
+            .limits(&resources("16Mi"))
+            .unwrap()
+            .build();
+        assert_eq!(limits.table_elements, None);
+        assert_eq!(limits.instances, DEFAULT_INSTANCE_LIMIT);
+    }
+
+    #[test]
+    fn counts_over_the_limits_are_named() {
+        let limits = maximums().limits(&resources("1Mi")).unwrap().build();
+        let counts = ResourceCounts {
+            instances: 10,
+            tables: 5,
+            memories: 2,
+        };
+        limits.check_counts(&counts).unwrap();
+
+        for (counts, limit) in [
+            (
+                ResourceCounts {
+                    instances: 11,
+                    ..counts
+                },
+                "resources.instances",
+            ),
+            (
+                ResourceCounts {
+                    tables: 6,
+                    ..counts
+                },
+                "resources.tables",
+            ),
+            (
+                ResourceCounts {
+                    memories: 3,
+                    ..counts
+                },
+                "resources.memories",
+            ),
+        ] {
+            assert_eq!(limits.check_counts(&counts).unwrap_err().limit, limit);
+        }
+    }
+
+    #[test]
+    fn refused_growth_is_recorded() {
+        let mut limits = maximums().limits(&resources("1Mi")).unwrap().build();
+        assert!(ResourceLimiter::memory_growing(&mut limits, 0, 65536, None).unwrap());
+        assert!(
+            !ResourceLimiter::memory_growing(&mut limits, 65536, 2 * 1024 * 1024, None).unwrap()
+        );
+
+        assert_eq!(limits.usage().peak_memory, 65536);
+        assert_eq!(limits.usage().denied_growths, 1);
+        let error = limits.instantiation_error(anyhow::anyhow!("instantiation failed"));
+        assert_eq!(
+            error.downcast::<LimitExceeded>().unwrap().limit,
+            "resources.memory"
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
+
+use dashmap::DashMap;
+use prometheus::{
+    Encoder, Gauge, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    TextEncoder, core::Collector, proto::MetricFamily, register_histogram_vec,
+    register_int_counter, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+};
+
+/// Buckets for latencies ranging from sub-millisecond instantiation to long-running guests.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+pub static INVOCATIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "mist_invocations_total",
+        "Droplet invocations by outcome.",
+        &["droplet", "outcome"]
+    )
+    .unwrap()
+});
+
+pub static INSTANTIATE_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "mist_instantiate_seconds",
+        "Time spent instantiating a droplet before calling into it.",
+        &["droplet"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+pub static EXECUTION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "mist_execution_seconds",
+        "Time spent executing a droplet's entrypoint.",
+        &["droplet"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+pub static COMPILE_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "mist_compile_seconds",
+        "Time spent compiling droplet sources into artifacts.",
+        &["droplet"],
+        LATENCY_BUCKETS.to_vec()
+    )
+    .unwrap()
+});
+
+pub static MEMORY_DENIALS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "mist_memory_grow_denied_total",
+        "Linear memory growths denied by the droplet's limits.",
+        &["droplet"]
+    )
+    .unwrap()
+});
+
+pub static TABLE_DENIALS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "mist_table_grow_denied_total",
+        "Table growths denied by the droplet's limits.",
+        &["droplet"]
+    )
+    .unwrap()
+});
+
+pub static JOB_QUEUE_DEPTH: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "mist_job_queue_depth",
+        "Asynchronous invocations waiting to be picked up."
+    )
+    .unwrap()
+});
+
+pub static ACTIVE_INSTANCES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "mist_active_instances",
+        "Droplet instances currently alive.",
+        &["droplet"]
+    )
+    .unwrap()
+});
+
+pub static RESERVED_MEMORY_BYTES: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "mist_reserved_memory_bytes",
+        "Memory reserved from the node budget by running invocations."
+    )
+    .unwrap()
+});
+
+pub static RESERVED_CPU_MILLICORES: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "mist_reserved_cpu_millicores",
+        "CPU reserved from the node budget by running invocations."
+    )
+    .unwrap()
+});
+
+pub static ADMISSION_REJECTIONS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "mist_admission_rejected_total",
+        "Invocations rejected for lack of node capacity."
+    )
+    .unwrap()
+});
+
+/// Labels of every droplet, exported as `mist_droplet_labels` to be joined onto the
+/// per-droplet metrics, which only carry the droplet name.
+static DROPLET_LABELS: LazyLock<DashMap<String, BTreeMap<String, String>>> =
+    LazyLock::new(DashMap::new);
+
+pub fn set_droplet_labels(droplet: &str, labels: &BTreeMap<String, String>) {
+    DROPLET_LABELS.insert(droplet.to_string(), labels.clone());
+}
+
+pub fn remove_droplet_labels(droplet: &str) {
+    DROPLET_LABELS.remove(droplet);
+}
+
+/// One `mist_droplet_labels{droplet="...", label_<key>="..."} 1` sample per droplet.
+///
+/// Label names differ between droplets, so the family is assembled on every scrape
+/// instead of being registered up front.
+fn droplet_labels_family() -> anyhow::Result<Option<MetricFamily>> {
+    let mut family: Option<MetricFamily> = None;
+    for droplet in DROPLET_LABELS.iter() {
+        let mut labels = label_names(droplet.value());
+        labels.insert("droplet".to_string(), droplet.key().clone());
+
+        let gauge = Gauge::with_opts(
+            Opts::new("mist_droplet_labels", "Labels of each droplet, always 1.")
+                .const_labels(labels),
+        )?;
+        gauge.set(1.0);
+
+        for mut collected in gauge.collect() {
+            match &mut family {
+                Some(family) => family.mut_metric().extend(collected.take_metric()),
+                None => family = Some(collected),
+            }
+        }
+    }
+
+    Ok(family)
+}
+
+/// Droplet labels keyed by Prometheus label name: `label_` followed by the key with
+/// anything but ASCII letters and digits replaced by `_`.
+///
+/// Keys that map to a name already taken, like `a.b` after `a-b`, get a `_2`, `_3`, ...
+/// suffix in key order, so no label is dropped.
+fn label_names(labels: &BTreeMap<String, String>) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    for (key, value) in labels {
+        let base = format!(
+            "label_{}",
+            key.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+        );
+        let mut name = base.clone();
+        let mut suffix = 1;
+        while names.contains_key(&name) {
+            suffix += 1;
+            name = format!("{base}_{suffix}");
+        }
+        names.insert(name, value.clone());
+    }
+
+    names
+}
+
+/// Per-droplet counters fed by [`StoreLimitsAsync`](crate::limits::StoreLimitsAsync).
+#[derive(Clone, Debug)]
+pub struct LimiterMetrics {
+    pub memory_denials: IntCounter,
+    pub table_denials: IntCounter,
+}
+
+impl LimiterMetrics {
+    pub fn for_droplet(droplet: &str) -> Self {
+        Self {
+            memory_denials: MEMORY_DENIALS.with_label_values(&[droplet]),
+            table_denials: TABLE_DENIALS.with_label_values(&[droplet]),
+        }
+    }
+}
+
+/// Keeps a droplet counted in [`ACTIVE_INSTANCES`] for as long as it is alive.
+pub struct ActiveInstanceGuard(IntGauge);
+
+impl ActiveInstanceGuard {
+    pub fn new(droplet: &str) -> Self {
+        let gauge = ACTIVE_INSTANCES.with_label_values(&[droplet]);
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for ActiveInstanceGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Renders every registered metric, process metrics included, in the Prometheus text format.
+pub fn gather() -> anyhow::Result<String> {
+    // Unlabelled metrics are exported from the start rather than on first use.
+    LazyLock::force(&JOB_QUEUE_DEPTH);
+    LazyLock::force(&RESERVED_MEMORY_BYTES);
+    LazyLock::force(&RESERVED_CPU_MILLICORES);
+    LazyLock::force(&ADMISSION_REJECTIONS);
+
+    let mut families = prometheus::gather();
+    families.extend(droplet_labels_family()?);
+    families.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&families, &mut buffer)?;
+
+    Ok(String::from_utf8(buffer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_names_are_sanitized() {
+        let labels = BTreeMap::from([
+            ("team".to_string(), "payments".to_string()),
+            ("app.kubernetes.io/name".to_string(), "shop".to_string()),
+        ]);
+        assert_eq!(
+            label_names(&labels),
+            HashMap::from([
+                ("label_team".to_string(), "payments".to_string()),
+                (
+                    "label_app_kubernetes_io_name".to_string(),
+                    "shop".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn colliding_label_names_are_suffixed() {
+        let labels = BTreeMap::from([
+            ("a-b".to_string(), "1".to_string()),
+            ("a.b".to_string(), "2".to_string()),
+            ("a_b".to_string(), "3".to_string()),
+            ("a_b_2".to_string(), "4".to_string()),
+        ]);
+        assert_eq!(
+            label_names(&labels),
+            HashMap::from([
+                ("label_a_b".to_string(), "1".to_string()),
+                ("label_a_b_2".to_string(), "2".to_string()),
+                ("label_a_b_3".to_string(), "3".to_string()),
+                ("label_a_b_2_2".to_string(), "4".to_string()),
+            ])
+        );
+    }
+}
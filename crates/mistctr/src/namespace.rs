@@ -0,0 +1,255 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use config::{Metadata, validate};
+use dashmap::DashMap;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::{Mutex, OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+pub use config::api::{Namespace, NamespaceQuota};
+
+#[derive(Debug, Error)]
+#[error("Namespace {namespace} has reached its quota: {reason}")]
+pub struct QuotaExceeded {
+    pub namespace: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Error)]
+#[error("Namespace {0} does not exist.")]
+pub struct NamespaceNotFound(pub String);
+
+/// Namespaces and their access tokens, stored in the `namespaces` and `namespace_tokens` trees.
+///
+/// Only a hash of each token is kept, so a lost token has to be replaced by re-creating
+/// the namespace.
+pub struct NamespaceStore {
+    tree: sled::Tree,
+    /// Token hash to the namespace it grants access to.
+    tokens: sled::Tree,
+    invocations: DashMap<String, Arc<Semaphore>>,
+    /// Serializes changes to each namespace's droplets; see [`NamespaceStore::lock`].
+    locks: DashMap<String, Arc<Mutex<()>>>,
+}
+
+impl NamespaceStore {
+    /// Opens the store, creating the `default` namespace on first start.
+    pub fn open(db: &sled::Db) -> anyhow::Result<Self> {
+        let store = Self {
+            tree: db.open_tree("namespaces")?,
+            tokens: db.open_tree("namespace_tokens")?,
+            invocations: DashMap::new(),
+            locks: DashMap::new(),
+        };
+
+        if store.get(Metadata::DEFAULT_NAMESPACE)?.is_none() {
+            store.store(&Namespace {
+                name: Metadata::DEFAULT_NAMESPACE.to_string(),
+                quota: NamespaceQuota::default(),
+                created_at: Utc::now(),
+            })?;
+        }
+        for namespace in store.list()? {
+            store.limit_invocations(&namespace);
+        }
+
+        Ok(store)
+    }
+
+    /// Creates a namespace, returning it with its access token.
+    pub fn create(&self, name: &str, quota: NamespaceQuota) -> anyhow::Result<(Namespace, String)> {
+        validate::check_name(name).map_err(anyhow::Error::msg)?;
+        quota.memory_bytes()?;
+        if self.get(name)?.is_some() {
+            anyhow::bail!("Namespace {name} already exists.");
+        }
+
+        let namespace = Namespace {
+            name: name.to_string(),
+            quota,
+            created_at: Utc::now(),
+        };
+        let token = format!(
+            "hm_{}",
+            rand::random::<[u8; 32]>()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
+
+        self.store(&namespace)?;
+        self.tokens.insert(hash_token(&token), name.as_bytes())?;
+        self.limit_invocations(&namespace);
+
+        Ok((namespace, token))
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<Option<Namespace>> {
+        self.tree
+            .get(name)?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<Namespace>> {
+        self.tree
+            .iter()
+            .map(|item| {
+                let (_, value) = item?;
+                Ok(serde_json::from_slice(&value)?)
+            })
+            .collect()
+    }
+
+    /// Removes the namespace and revokes its tokens; returns whether it existed.
+    pub fn remove(&self, name: &str) -> anyhow::Result<bool> {
+        if self.tree.remove(name)?.is_none() {
+            return Ok(false);
+        }
+
+        for item in self.tokens.iter() {
+            let (hash, namespace) = item?;
+            if namespace == name.as_bytes() {
+                self.tokens.remove(hash)?;
+            }
+        }
+        self.invocations.remove(name);
+        self.locks.remove(name);
+
+        Ok(true)
+    }
+
+    /// Namespace the token grants access to, if any.
+    pub fn authenticate(&self, token: &str) -> anyhow::Result<Option<String>> {
+        Ok(self
+            .tokens
+            .get(hash_token(token))?
+            .map(|namespace| String::from_utf8_lossy(&namespace).into_owned()))
+    }
+
+    /// Waits until no other change to the namespace's droplets is in progress, so a quota
+    /// check and the change it allows happen as one step.
+    pub async fn lock(&self, namespace: &str) -> OwnedMutexGuard<()> {
+        let lock = self.locks.entry(namespace.to_string()).or_default().clone();
+        lock.lock_owned().await
+    }
+
+    /// Takes one of the namespace's concurrent invocation slots, held until the permit is dropped.
+    pub fn acquire_invocation(
+        &self,
+        namespace: &str,
+    ) -> Result<Option<OwnedSemaphorePermit>, QuotaExceeded> {
+        let Some(semaphore) = self
+            .invocations
+            .get(namespace)
+            .map(|semaphore| semaphore.clone())
+        else {
+            return Ok(None);
+        };
+
+        semaphore
+            .try_acquire_owned()
+            .map(Some)
+            .map_err(|_| QuotaExceeded {
+                namespace: namespace.to_string(),
+                reason: "too many concurrent invocations".to_string(),
+            })
+    }
+
+    fn limit_invocations(&self, namespace: &Namespace) {
+        if let Some(limit) = namespace.quota.concurrent_invocations {
+            self.invocations
+                .insert(namespace.name.clone(), Arc::new(Semaphore::new(limit)));
+        }
+    }
+
+    fn store(&self, namespace: &Namespace) -> anyhow::Result<()> {
+        self.tree
+            .insert(&namespace.name, serde_json::to_vec(namespace)?)?;
+        Ok(())
+    }
+}
+
+/// Namespace part of a droplet id, `<namespace>/<name>`.
+pub fn namespace_of(droplet: &str) -> &str {
+    droplet
+        .split_once('/')
+        .map_or(Metadata::DEFAULT_NAMESPACE, |(namespace, _)| namespace)
+}
+
+pub fn droplet_id(namespace: &str, name: &str) -> String {
+    format!("{namespace}/{name}")
+}
+
+fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+/// Moves state written before namespaces existed into the `default` namespace.
+///
+/// Droplets used to be identified by their bare name; every key and `droplet` field
+/// holding one is rewritten to `default/<name>`. Runs once, guarded by a marker.
+pub fn migrate_legacy_state(db: &sled::Db) -> anyhow::Result<()> {
+    const MARKER: &str = "namespaces_migrated";
+
+    let meta = db.open_tree("meta")?;
+    if meta.contains_key(MARKER)? {
+        return Ok(());
+    }
+
+    let qualify = |droplet: &str| droplet_id(Metadata::DEFAULT_NAMESPACE, droplet);
+
+    // Droplet configs and revisions, keyed by name.
+    for tree in [&**db, &db.open_tree("revisions")?] {
+        for item in tree.iter() {
+            let (key, value) = item?;
+            let key = String::from_utf8_lossy(&key).into_owned();
+            if !key.contains('/') {
+                tree.insert(qualify(&key), value)?;
+                tree.remove(key)?;
+            }
+        }
+    }
+
+    // Trigger state and invocation history, keyed by `<droplet>/<id>`.
+    for tree in [db.open_tree("triggers")?, db.open_tree("invocations")?] {
+        for item in tree.iter() {
+            let (key, value) = item?;
+            let key = String::from_utf8_lossy(&key).into_owned();
+            if key.matches('/').count() == 1 {
+                tree.insert(qualify(&key), qualify_droplet_field(&value, qualify)?)?;
+                tree.remove(key)?;
+            }
+        }
+    }
+
+    // Jobs and dead letters, keyed by their own id.
+    for tree in [db.open_tree("jobs")?, db.open_tree("dead_letters")?] {
+        for item in tree.iter() {
+            let (key, value) = item?;
+            tree.insert(key, qualify_droplet_field(&value, qualify)?)?;
+        }
+    }
+
+    meta.insert(MARKER, &[])?;
+
+    Ok(())
+}
+
+fn qualify_droplet_field(
+    value: &[u8],
+    qualify: impl Fn(&str) -> String,
+) -> anyhow::Result<Vec<u8>> {
+    let mut value: Value = serde_json::from_slice(value)?;
+    if let Some(droplet) = value.get_mut("droplet")
+        && let Some(name) = droplet.as_str()
+        && !name.contains('/')
+    {
+        *droplet = qualify(name).into();
+    }
+
+    Ok(serde_json::to_vec(&value)?)
+}
@@ -0,0 +1,118 @@
+//! HTTP requests the daemon sends itself, to fetch droplet sources and deliver webhooks.
+//!
+//! These are the only outbound calls that show up in traces. Guests cannot make HTTP
+//! requests of their own: `wasi:http` is not linked and no sockets are granted to them.
+
+use opentelemetry::global;
+use opentelemetry_http::HeaderInjector;
+use reqwest::{Client, Request, Response};
+use tracing::{Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Sends `request` in an `http.client` span, continuing the current trace on the server
+/// through a `traceparent` header.
+pub async fn send(client: &Client, mut request: Request) -> reqwest::Result<Response> {
+    let mut url = request.url().clone();
+    // Credentials in the URL are not for the trace.
+    url.set_password(None).ok();
+    let span = tracing::info_span!(
+        "http.client",
+        otel.name = request.method().as_str(),
+        otel.kind = "client",
+        http.request.method = %request.method(),
+        url.full = %url,
+        http.response.status_code = tracing::field::Empty,
+    );
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut HeaderInjector(request.headers_mut()))
+    });
+
+    async move {
+        let response = client.execute(request).await?;
+        Span::current().record(
+            "http.response.status_code",
+            i64::from(response.status().as_u16()),
+        );
+        Ok(response)
+    }
+    .instrument(span)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{Router, http::HeaderMap, routing::get};
+    use opentelemetry::trace::{SpanKind, TraceContextExt, TracerProvider};
+    use opentelemetry_sdk::{
+        propagation::TraceContextPropagator,
+        trace::{InMemorySpanExporter, SdkTracerProvider},
+    };
+    use tokio::net::TcpListener;
+    use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn requests_are_client_spans_continued_by_the_server() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let _subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .set_default();
+
+        let received = Arc::new(Mutex::new(None));
+        let router = Router::new().route(
+            "/hook",
+            get({
+                let received = received.clone();
+                move |headers: HeaderMap| async move {
+                    *received.lock().unwrap() = headers
+                        .get("traceparent")
+                        .and_then(|value| value.to_str().ok())
+                        .map(ToString::to_string);
+                }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let parent = tracing::info_span!("parent");
+        let client = Client::new();
+        let request = client
+            .get(format!("http://user:hunter2@{address}/hook"))
+            .build()
+            .unwrap();
+        let response = send(&client, request).instrument(parent.clone()).await;
+        assert!(response.unwrap().status().is_success());
+        let trace_id = parent.context().span().span_context().trace_id();
+        drop(parent);
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+        let span = spans
+            .iter()
+            .find(|span| span.span_kind == SpanKind::Client)
+            .expect("no client span");
+        assert_eq!(span.name, "GET");
+        assert_eq!(span.span_context.trace_id(), trace_id);
+        assert!(span.attributes.iter().any(|attribute| {
+            attribute.key.as_str() == "url.full" && !attribute.value.as_str().contains("hunter2")
+        }));
+        assert!(span.attributes.iter().any(|attribute| {
+            attribute.key.as_str() == "http.response.status_code"
+                && attribute.value == 200i64.into()
+        }));
+
+        let traceparent = received.lock().unwrap().clone().expect("no traceparent");
+        assert_eq!(
+            traceparent,
+            format!("00-{trace_id}-{}-01", span.span_context.span_id())
+        );
+    }
+}
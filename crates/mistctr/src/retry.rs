@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use config::{RetryErrorClass, SpecRetry};
+use uuid::Uuid;
+use wasmtime::Trap;
+
+use crate::{invocation::InvocationOrigin, limits::LimitExceeded};
+
+pub use config::api::{DeadLetter, FailedAttempt};
+
+/// Sorts an invocation error into the classes a retry policy can select.
+pub fn classify_error(error: &anyhow::Error) -> RetryErrorClass {
+    // Running into a limit fails the same way on every attempt, so it is never a trap.
+    if error.is::<LimitExceeded>() {
+        return RetryErrorClass::Host;
+    }
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::Interrupt | Trap::OutOfFuel) => RetryErrorClass::Timeout,
+        Some(_) => RetryErrorClass::Trap,
+        None => RetryErrorClass::Host,
+    }
+}
+
+/// Parsed form of [`SpecRetry`]; droplets without a policy get a single attempt.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial: Duration,
+    pub multiplier: f64,
+    pub max: Duration,
+    pub retry_on: Vec<RetryErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial: Duration::ZERO,
+            multiplier: 1.0,
+            max: Duration::ZERO,
+            retry_on: vec![],
+        }
+    }
+}
+
+impl TryFrom<&SpecRetry> for RetryPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: &SpecRetry) -> Result<Self, Self::Error> {
+        if spec.max_attempts == 0 {
+            anyhow::bail!("Retry policy needs at least one attempt.");
+        }
+        if !spec.backoff.multiplier.is_finite() || spec.backoff.multiplier < 1.0 {
+            anyhow::bail!("Retry backoff multiplier must be a finite number of at least 1.");
+        }
+
+        Ok(Self {
+            max_attempts: spec.max_attempts,
+            initial: humantime::parse_duration(&spec.backoff.initial)?,
+            multiplier: spec.backoff.multiplier,
+            max: humantime::parse_duration(&spec.backoff.max)?,
+            retry_on: spec.retry_on.clone(),
+        })
+    }
+}
+
+impl RetryPolicy {
+    /// Whether another attempt should follow the `attempt`-th (1-based) failure.
+    pub fn should_retry(&self, attempt: u32, class: RetryErrorClass) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&class)
+    }
+
+    /// Delay before the attempt following the `attempt`-th (1-based) failure.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let factor = self.multiplier.powi(exponent);
+        // Large attempt counts overflow the factor to infinity, which caps at `max`.
+        Duration::try_from_secs_f64(self.initial.as_secs_f64() * factor)
+            .unwrap_or(self.max)
+            .min(self.max)
+    }
+}
+
+/// Dead-letter list, stored in the `dead_letters` tree.
+pub struct DeadLetterStore {
+    tree: sled::Tree,
+}
+
+impl DeadLetterStore {
+    pub fn open(db: &sled::Db) -> anyhow::Result<Self> {
+        Ok(Self {
+            tree: db.open_tree("dead_letters")?,
+        })
+    }
+
+    pub fn insert(
+        &self,
+        droplet: String,
+        origin: InvocationOrigin,
+        args: serde_json::Value,
+        attempts: Vec<FailedAttempt>,
+    ) -> anyhow::Result<DeadLetter> {
+        let letter = DeadLetter {
+            id: Uuid::now_v7().to_string(),
+            droplet,
+            origin,
+            args,
+            attempts,
+            created_at: Utc::now(),
+        };
+        self.tree.insert(&letter.id, serde_json::to_vec(&letter)?)?;
+
+        Ok(letter)
+    }
+
+    pub fn get(&self, id: &str) -> anyhow::Result<Option<DeadLetter>> {
+        self.tree
+            .get(id)?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    pub fn list(&self) -> anyhow::Result<Vec<DeadLetter>> {
+        self.tree
+            .iter()
+            .map(|item| {
+                let (_, value) = item?;
+                Ok(serde_json::from_slice(&value)?)
+            })
+            .collect()
+    }
+
+    pub fn remove(&self, id: &str) -> anyhow::Result<Option<DeadLetter>> {
+        self.tree
+            .remove(id)?
+            .map(|value| serde_json::from_slice(&value))
+            .transpose()
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{RetryBackoff, SpecRetry};
+
+    use super::*;
+
+    fn policy(multiplier: f64) -> anyhow::Result<RetryPolicy> {
+        RetryPolicy::try_from(&SpecRetry {
+            max_attempts: 100,
+            backoff: RetryBackoff {
+                initial: "1s".to_string(),
+                multiplier,
+                max: "1h".to_string(),
+            },
+            retry_on: vec![RetryErrorClass::Trap],
+        })
+    }
+
+    #[test]
+    fn backoff_grows_until_max() {
+        let policy = policy(2.0).unwrap();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(5), Duration::from_secs(16));
+        assert_eq!(policy.backoff(20), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn backoff_caps_instead_of_overflowing() {
+        let policy = policy(10.0).unwrap();
+        for attempt in [30, 99, 400, u32::MAX] {
+            assert_eq!(policy.backoff(attempt), Duration::from_secs(3600));
+        }
+    }
+
+    #[test]
+    fn limits_are_not_retried_by_default() {
+        let spec: SpecRetry =
+            serde_json::from_value(serde_json::json!({ "max_attempts": 3 })).unwrap();
+        let policy = RetryPolicy::try_from(&spec).unwrap();
+        let exceeded = anyhow::Error::new(LimitExceeded {
+            limit: "memory".to_string(),
+            detail: "grow to 2 MiB".to_string(),
+        });
+
+        assert_eq!(classify_error(&exceeded), RetryErrorClass::Host);
+        assert!(!policy.should_retry(1, classify_error(&exceeded)));
+        for trap in [Trap::Interrupt, Trap::OutOfFuel] {
+            let class = classify_error(&anyhow::Error::new(trap));
+            assert_eq!(class, RetryErrorClass::Timeout);
+            assert!(policy.should_retry(1, class));
+        }
+        assert_eq!(
+            classify_error(&anyhow::Error::new(Trap::UnreachableCodeReached)),
+            RetryErrorClass::Trap
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_multipliers() {
+        for multiplier in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 0.5] {
+            assert!(policy(multiplier).is_err(), "{multiplier}");
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use std::{collections::HashSet, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{Context, anyhow};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use config::{Spec, SpecTrigger, TriggerCatchUp, TriggerSchedule};
+use dashmap::DashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ControlPanel,
+    invocation::{Caller, InvocationOrigin, InvocationSource},
+};
+
+pub use config::api::TriggerInfo;
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Persisted state of a single trigger, stored in the `triggers` tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerState {
+    pub schedule: TriggerSchedule,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub jitter: Option<String>,
+    pub last_run: Option<DateTime<Utc>>,
+    /// When the next run is due, jitter included.
+    pub next_run: DateTime<Utc>,
+    /// The occurrence `next_run` was computed from, before jitter. The schedule advances
+    /// from it, so runs neither drift nor accumulate jitter.
+    #[serde(default)]
+    pub scheduled: Option<DateTime<Utc>>,
+}
+
+impl TriggerState {
+    fn new(trigger: &SpecTrigger, now: DateTime<Utc>) -> anyhow::Result<Self> {
+        let scheduled = scheduled_after(trigger, now, now)?;
+
+        Ok(Self {
+            schedule: trigger.schedule.clone(),
+            timezone: trigger.timezone.clone(),
+            jitter: trigger.jitter.clone(),
+            last_run: None,
+            next_run: scheduled + jitter(trigger)?,
+            scheduled: Some(scheduled),
+        })
+    }
+
+    /// Whether the state was computed for the trigger as it is now configured.
+    fn matches(&self, trigger: &SpecTrigger) -> bool {
+        self.schedule == trigger.schedule
+            && self.timezone == trigger.timezone
+            && self.jitter == trigger.jitter
+    }
+
+    /// Moves on from the due occurrence to the next one after `now`, dropping any in between
+    /// since missed runs are either skipped or caught up on at once.
+    fn advance(self, trigger: &SpecTrigger, now: DateTime<Utc>, ran: bool) -> anyhow::Result<Self> {
+        let scheduled = scheduled_after(trigger, self.scheduled.unwrap_or(self.next_run), now)?;
+
+        Ok(Self {
+            last_run: if ran { Some(now) } else { self.last_run },
+            next_run: scheduled + jitter(trigger)?,
+            scheduled: Some(scheduled),
+            ..self
+        })
+    }
+}
+
+pub struct Scheduler {
+    panel: Arc<ControlPanel>,
+    /// Triggers observed since the scheduler started, used to tell runs missed
+    /// during downtime apart from runs that are merely due.
+    seen: HashSet<String>,
+    /// Triggers whose run is still going; they are not fired again until it ends.
+    running: Arc<DashSet<String>>,
+}
+
+impl Scheduler {
+    pub fn new(panel: Arc<ControlPanel>) -> Self {
+        Self {
+            panel,
+            seen: HashSet::new(),
+            running: Arc::new(DashSet::new()),
+        }
+    }
+
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(TICK);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            interval.tick().await;
+
+            for (droplet, trigger) in droplet_triggers(&self.panel) {
+                if let Err(e) = self.tick(&droplet, &trigger) {
+                    tracing::error!("Trigger {droplet}/{}: {e}", trigger.name);
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self, droplet: &str, trigger: &SpecTrigger) -> anyhow::Result<()> {
+        let key = trigger_key(droplet, &trigger.name);
+        let now = Utc::now();
+
+        let state = match load_state(&self.panel.triggers, &key)? {
+            Some(state) if state.matches(trigger) => state,
+            _ => {
+                let state = TriggerState::new(trigger, now)?;
+                store_state(&self.panel.triggers, &key, &state)?;
+                self.seen.insert(key);
+                return Ok(());
+            }
+        };
+
+        let first_seen = self.seen.insert(key.clone());
+        if state.next_run > now {
+            return Ok(());
+        }
+
+        let missed = first_seen && now - state.next_run > TimeDelta::from_std(TICK * 2)?;
+        let skip = missed && trigger.catch_up == TriggerCatchUp::Skip;
+        let ran = if skip {
+            tracing::info!(
+                "Skipping missed run of trigger {key} scheduled at {}",
+                state.next_run
+            );
+            false
+        } else {
+            self.fire(droplet, trigger, &key)
+        };
+
+        store_state(
+            &self.panel.triggers,
+            &key,
+            &state.advance(trigger, now, ran)?,
+        )
+    }
+
+    /// Starts a run of the trigger unless its previous run is still going, returning
+    /// whether it did.
+    fn fire(&self, droplet: &str, trigger: &SpecTrigger, key: &str) -> bool {
+        if !self.running.insert(key.to_string()) {
+            tracing::warn!("Skipping run of trigger {key}, its previous run is still going");
+            return false;
+        }
+        tracing::info!("Trigger {key} fired");
+
+        let panel = self.panel.clone();
+        let droplet = droplet.to_string();
+        let origin = InvocationOrigin::new(
+            InvocationSource::Trigger {
+                name: trigger.name.clone(),
+            },
+            Caller::default(),
+        );
+        let args = trigger.args.clone().unwrap_or_default();
+        let key = key.to_string();
+        let running = self.running.clone();
+        tokio::spawn(async move {
+            if let Err(e) = panel.run_droplet_with_retry(&droplet, origin, args).await {
+                tracing::error!("Scheduled run of {key} failed: {e}");
+            }
+            running.remove(&key);
+        });
+
+        true
+    }
+}
+
+/// Lists every trigger of every droplet together with its upcoming run.
+pub fn list_triggers(panel: &ControlPanel) -> anyhow::Result<Vec<TriggerInfo>> {
+    droplet_triggers(panel)
+        .into_iter()
+        .map(|(droplet, trigger)| {
+            let state = load_state(&panel.triggers, &trigger_key(&droplet, &trigger.name))?
+                .filter(|state| state.matches(&trigger));
+            let (last_run, next_run) = match state {
+                Some(state) => (state.last_run, state.next_run),
+                None => (None, next_occurrence(&trigger, Utc::now())?),
+            };
+
+            Ok(TriggerInfo {
+                droplet,
+                trigger: trigger.name,
+                schedule: trigger.schedule,
+                last_run,
+                next_run,
+            })
+        })
+        .collect()
+}
+
+/// Computes the first run of `trigger` strictly after `after`, jitter included.
+pub fn next_occurrence(
+    trigger: &SpecTrigger,
+    after: DateTime<Utc>,
+) -> anyhow::Result<DateTime<Utc>> {
+    Ok(scheduled_after(trigger, after, after)? + jitter(trigger)?)
+}
+
+/// Computes the first occurrence of `trigger` following `previous` in its schedule that is
+/// also strictly after `now`, without jitter.
+fn scheduled_after(
+    trigger: &SpecTrigger,
+    previous: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> anyhow::Result<DateTime<Utc>> {
+    Ok(match &trigger.schedule {
+        TriggerSchedule::Cron(expression) => {
+            let schedule = parse_cron(expression)?;
+            let timezone: Tz = match &trigger.timezone {
+                Some(timezone) => timezone
+                    .parse()
+                    .map_err(|_| anyhow!("Unknown timezone: {timezone}"))?,
+                None => Tz::UTC,
+            };
+
+            schedule
+                .after(&previous.max(now).with_timezone(&timezone))
+                .next()
+                .context("Cron expression has no upcoming occurrences.")?
+                .with_timezone(&Utc)
+        }
+        TriggerSchedule::Interval(every) => {
+            let every = humantime::parse_duration(every)?;
+            if every.is_zero() {
+                anyhow::bail!("Trigger interval must be greater than zero.");
+            }
+            let every = TimeDelta::from_std(every)?;
+
+            // Whole intervals elapsed since `previous`, keeping runs in phase with it.
+            let behind = (now - previous)
+                .num_nanoseconds()
+                .zip(every.num_nanoseconds())
+                .map_or(0, |(behind, every)| behind.max(0) / every);
+            i32::try_from(behind + 1)
+                .ok()
+                .and_then(|periods| every.checked_mul(periods))
+                .and_then(|delay| previous.checked_add_signed(delay))
+                .context("Trigger interval is out of range.")?
+        }
+    })
+}
+
+/// Draws the random delay added to a run of `trigger`.
+fn jitter(trigger: &SpecTrigger) -> anyhow::Result<TimeDelta> {
+    let jitter = match &trigger.jitter {
+        Some(jitter) => humantime::parse_duration(jitter)?.as_millis() as i64,
+        None => 0,
+    };
+    let jitter = if jitter > 0 {
+        rand::random_range(0..=jitter)
+    } else {
+        0
+    };
+
+    Ok(TimeDelta::milliseconds(jitter))
+}
+
+/// Parses a cron expression, accepting the classic 5-field form by pinning seconds to zero.
+fn parse_cron(expression: &str) -> anyhow::Result<cron::Schedule> {
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {expression}")
+    } else {
+        expression.to_string()
+    };
+
+    cron::Schedule::from_str(&expression).map_err(|e| anyhow!("Invalid cron expression: {e}"))
+}
+
+fn droplet_triggers(panel: &ControlPanel) -> Vec<(String, SpecTrigger)> {
+    panel
+        .droplets
+        .iter()
+        .flat_map(|droplet| {
+            let Spec::Droplet { triggers, .. } = &droplet.config.spec;
+            triggers
+                .iter()
+                .flatten()
+                .map(|trigger| (droplet.key().clone(), trigger.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+pub(crate) fn trigger_key(droplet: &str, trigger: &str) -> String {
+    format!("{droplet}/{trigger}")
+}
+
+fn load_state(tree: &sled::Tree, key: &str) -> anyhow::Result<Option<TriggerState>> {
+    tree.get(key)?
+        .map(|value| serde_json::from_slice(&value))
+        .transpose()
+        .map_err(Into::into)
+}
+
+fn store_state(tree: &sled::Tree, key: &str, state: &TriggerState) -> anyhow::Result<()> {
+    tree.insert(key, serde_json::to_vec(state)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(schedule: TriggerSchedule) -> SpecTrigger {
+        SpecTrigger {
+            name: "tick".to_string(),
+            schedule,
+            timezone: None,
+            jitter: None,
+            catch_up: TriggerCatchUp::Skip,
+            args: None,
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn intervals_advance_from_the_due_occurrence() {
+        let trigger = trigger(TriggerSchedule::Interval("10m".to_string()));
+        let state = TriggerState::new(&trigger, at("2026-01-15T12:00:00Z")).unwrap();
+        assert_eq!(state.next_run, at("2026-01-15T12:10:00Z"));
+
+        // Noticed a second late, the run after is still ten minutes after the due one.
+        let state = state
+            .advance(&trigger, at("2026-01-15T12:10:01Z"), true)
+            .unwrap();
+        assert_eq!(state.next_run, at("2026-01-15T12:20:00Z"));
+        assert_eq!(state.last_run, Some(at("2026-01-15T12:10:01Z")));
+    }
+
+    #[test]
+    fn missed_runs_advance_past_now_in_phase() {
+        let trigger = trigger(TriggerSchedule::Interval("10m".to_string()));
+        let state = TriggerState::new(&trigger, at("2026-01-15T12:00:00Z")).unwrap();
+
+        let state = state
+            .advance(&trigger, at("2026-01-15T12:45:00Z"), false)
+            .unwrap();
+        assert_eq!(state.next_run, at("2026-01-15T12:50:00Z"));
+        assert_eq!(state.last_run, None);
+
+        let trigger = self::trigger(TriggerSchedule::Cron("0 * * * *".to_string()));
+        let state = TriggerState::new(&trigger, at("2026-01-15T12:00:00Z")).unwrap();
+        assert_eq!(state.next_run, at("2026-01-15T13:00:00Z"));
+        let state = state
+            .advance(&trigger, at("2026-01-16T08:30:00Z"), true)
+            .unwrap();
+        assert_eq!(state.next_run, at("2026-01-16T09:00:00Z"));
+    }
+
+    #[test]
+    fn cron_occurrences_follow_the_timezone() {
+        let mut trigger = trigger(TriggerSchedule::Cron("0 9 * * *".to_string()));
+        trigger.timezone = Some("Europe/Paris".to_string());
+
+        let winter = next_occurrence(&trigger, at("2026-01-15T00:00:00Z")).unwrap();
+        assert_eq!(winter, at("2026-01-15T08:00:00Z"));
+        let summer = next_occurrence(&trigger, at("2026-07-15T00:00:00Z")).unwrap();
+        assert_eq!(summer, at("2026-07-15T07:00:00Z"));
+
+        trigger.timezone = Some("Mars/Olympus".to_string());
+        assert!(next_occurrence(&trigger, at("2026-01-15T00:00:00Z")).is_err());
+    }
+
+    #[test]
+    fn jitter_delays_runs_without_moving_the_schedule() {
+        let mut trigger = trigger(TriggerSchedule::Interval("1m".to_string()));
+        trigger.jitter = Some("30s".to_string());
+
+        let mut state = TriggerState::new(&trigger, at("2026-01-15T12:00:00Z")).unwrap();
+        for minute in 1..=20 {
+            let scheduled = at("2026-01-15T12:00:00Z") + TimeDelta::minutes(minute);
+            assert_eq!(state.scheduled, Some(scheduled));
+            assert!(state.next_run >= scheduled);
+            assert!(state.next_run <= scheduled + TimeDelta::seconds(30));
+
+            let now = state.next_run;
+            state = state.advance(&trigger, now, true).unwrap();
+        }
+    }
+
+    #[test]
+    fn changing_the_schedule_timezone_or_jitter_resets_the_state() {
+        let trigger = trigger(TriggerSchedule::Cron("0 9 * * *".to_string()));
+        let state = TriggerState::new(&trigger, at("2026-01-15T00:00:00Z")).unwrap();
+        assert!(state.matches(&trigger));
+
+        let mut changed = trigger.clone();
+        changed.schedule = TriggerSchedule::Cron("0 10 * * *".to_string());
+        assert!(!state.matches(&changed));
+
+        let mut changed = trigger.clone();
+        changed.timezone = Some("Europe/Paris".to_string());
+        assert!(!state.matches(&changed));
+
+        let mut changed = trigger.clone();
+        changed.jitter = Some("5s".to_string());
+        assert!(!state.matches(&changed));
+
+        let mut changed = trigger.clone();
+        changed.args = Some(serde_json::json!([1]));
+        assert!(state.matches(&changed));
+    }
+
+    #[test]
+    fn states_stored_without_the_scheduled_occurrence_advance_from_the_next_run() {
+        let trigger = trigger(TriggerSchedule::Interval("10m".to_string()));
+        let state: TriggerState = serde_json::from_value(serde_json::json!({
+            "schedule": { "interval": "10m" },
+            "last_run": null,
+            "next_run": "2026-01-15T12:10:00Z",
+        }))
+        .unwrap();
+        assert!(state.matches(&trigger));
+
+        let state = state
+            .advance(&trigger, at("2026-01-15T12:10:02Z"), true)
+            .unwrap();
+        assert_eq!(state.next_run, at("2026-01-15T12:20:00Z"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use config::{Metadata, SpecSecret, validate};
+use uuid::Uuid;
+
+/// Secrets stored one file per name in a directory per namespace, readable only by the
+/// daemon's user.
+///
+/// The daemon has no API for writing secrets; they are provisioned by placing files at
+/// `<root>/secrets/<namespace>/<name>`.
+#[derive(Clone)]
+pub struct SecretStore {
+    dir: PathBuf,
+    /// Where the secrets of running invocations are mounted from, see [`SecretMounts`].
+    mount_dir: PathBuf,
+}
+
+impl SecretStore {
+    pub fn open(dir: PathBuf, mount_dir: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        restrict_permissions(&dir, 0o700)?;
+        // Left behind by invocations that were running when the daemon stopped.
+        if fs::exists(&mount_dir)? {
+            fs::remove_dir_all(&mount_dir)?;
+        }
+        fs::create_dir_all(&mount_dir)?;
+        restrict_permissions(&mount_dir, 0o700)?;
+
+        // Secrets created before namespaces existed belong to the default one.
+        let default = dir.join(Metadata::DEFAULT_NAMESPACE);
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                fs::create_dir_all(&default)?;
+                fs::rename(entry.path(), default.join(entry.file_name()))?;
+            }
+        }
+
+        Ok(Self { dir, mount_dir })
+    }
+
+    pub fn set(&self, namespace: &str, name: &str, value: &[u8]) -> anyhow::Result<()> {
+        let path = self.path(namespace, name)?;
+        fs::create_dir_all(self.dir.join(namespace))?;
+        fs::write(&path, value)?;
+        restrict_permissions(&path, 0o600)?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, namespace: &str, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let path = self.path(namespace, name)?;
+        if !fs::exists(&path)? {
+            return Ok(None);
+        }
+
+        Ok(Some(fs::read(path)?))
+    }
+
+    pub fn list(&self, namespace: &str) -> anyhow::Result<Vec<String>> {
+        let dir = self.dir.join(namespace);
+        if !fs::exists(&dir)? {
+            return Ok(vec![]);
+        }
+
+        let mut names = fs::read_dir(dir)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Removes every secret of the namespace.
+    pub fn remove_namespace(&self, namespace: &str) -> anyhow::Result<()> {
+        let dir = self.dir.join(namespace);
+        if fs::exists(&dir)? {
+            fs::remove_dir_all(dir)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a secret holding credentials for pulling droplet sources.
+    pub fn credentials(&self, namespace: &str, name: &str) -> anyhow::Result<Credentials> {
+        let value = self
+            .get(namespace, name)?
+            .ok_or_else(|| anyhow::anyhow!("Secret {name} does not exist."))?;
+        let value = String::from_utf8(value)?;
+        let value = value.trim();
+
+        Ok(match value.split_once(':') {
+            Some((username, password)) => Credentials::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            },
+            None => Credentials::Bearer(value.to_string()),
+        })
+    }
+
+    /// Copies the namespace's `secrets` into a directory of their own for one invocation,
+    /// laid out so each is at its mount path once the directories are preopened.
+    pub fn mount(&self, namespace: &str, secrets: &[SpecSecret]) -> anyhow::Result<SecretMounts> {
+        let mut mounts = SecretMounts {
+            dir: self.mount_dir.join(Uuid::now_v7().to_string()),
+            dirs: BTreeMap::new(),
+        };
+        if secrets.is_empty() {
+            return Ok(mounts);
+        }
+        fs::create_dir(&mounts.dir)?;
+        restrict_permissions(&mounts.dir, 0o700)?;
+
+        for secret in secrets {
+            let (Some(guest_dir), Some(file_name)) =
+                (secret.mount_path.parent(), secret.mount_path.file_name())
+            else {
+                anyhow::bail!(
+                    "Mount path {} of secret {} does not name a file.",
+                    secret.mount_path.display(),
+                    secret.name
+                );
+            };
+            let value = self
+                .get(namespace, &secret.name)?
+                .ok_or_else(|| anyhow::anyhow!("Secret {} does not exist.", secret.name))?;
+
+            let count = mounts.dirs.len();
+            let host_dir = mounts
+                .dirs
+                .entry(guest_dir.to_path_buf())
+                .or_insert_with(|| mounts.dir.join(count.to_string()));
+            fs::create_dir_all(&*host_dir)?;
+            let path = host_dir.join(file_name);
+            fs::write(&path, value)
+                .with_context(|| format!("Failed to mount secret {}", secret.name))?;
+            restrict_permissions(&path, 0o400)?;
+        }
+
+        Ok(mounts)
+    }
+
+    fn path(&self, namespace: &str, name: &str) -> anyhow::Result<PathBuf> {
+        validate::check_name(namespace).map_err(anyhow::Error::msg)?;
+        if name.is_empty()
+            || name.starts_with('.')
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        {
+            anyhow::bail!("Invalid secret name \"{name}\".");
+        }
+
+        Ok(self.dir.join(namespace).join(name))
+    }
+}
+
+/// Secrets copied out for a single invocation, removed again once it is dropped.
+pub struct SecretMounts {
+    dir: PathBuf,
+    /// Host directory holding the secrets mounted in each guest directory.
+    dirs: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl SecretMounts {
+    /// Pairs of guest directory and the host directory to preopen read-only at it.
+    pub fn dirs(&self) -> impl Iterator<Item = (&Path, &Path)> {
+        self.dirs
+            .iter()
+            .map(|(guest, host)| (guest.as_path(), host.as_path()))
+    }
+}
+
+impl Drop for SecretMounts {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Credentials presented to HTTP servers and OCI registries.
+#[derive(Clone)]
+pub enum Credentials {
+    Basic { username: String, password: String },
+    Bearer(String),
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path, mode: u32) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path, _mode: u32) -> anyhow::Result<()> {
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use crate::limits::StoreLimitsAsync;
+use wasmtime::{Engine, component::Linker};
 use wasmtime_wasi::{
     ResourceTable,
     p2::{IoView, WasiCtx, WasiView},
+    preview1::{self, WasiP1Ctx},
 };
 
 pub struct HostState {
@@ -10,6 +12,16 @@ pub struct HostState {
     pub limits: StoreLimitsAsync,
 }
 
+impl HostState {
+    /// Linker providing every host interface a droplet may import.
+    pub fn linker(engine: &Engine) -> anyhow::Result<Linker<Self>> {
+        let mut linker = Linker::new(engine);
+        wasmtime_wasi::p2::add_to_linker_async(&mut linker)?;
+
+        Ok(linker)
+    }
+}
+
 impl IoView for HostState {
     fn table(&mut self) -> &mut ResourceTable {
         &mut self.table
@@ -20,3 +32,18 @@ impl WasiView for HostState {
         &mut self.ctx
     }
 }
+
+/// Store state for core modules, which get WASI preview 1 only.
+pub struct ModuleState {
+    pub ctx: WasiP1Ctx,
+    pub limits: StoreLimitsAsync,
+}
+
+impl ModuleState {
+    pub fn linker(engine: &Engine) -> anyhow::Result<wasmtime::Linker<Self>> {
+        let mut linker = wasmtime::Linker::new(engine);
+        preview1::add_to_linker_async(&mut linker, |state: &mut Self| &mut state.ctx)?;
+
+        Ok(linker)
+    }
+}
@@ -0,0 +1,684 @@
+use anyhow::{Context, anyhow};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use wasmtime::{
+    FuncType, ValType,
+    component::{Type, Val, types::ComponentFunc},
+};
+
+/// Invocation arguments that do not fit the entrypoint's signature.
+#[derive(Debug, Error)]
+#[error("Invalid arguments: {0:#}")]
+pub struct InvalidArguments(pub anyhow::Error);
+
+/// Converts JSON invocation arguments into the parameters of `func`.
+///
+/// Arguments are either an array of positional values or an object keyed by
+/// parameter name; `null` stands for no arguments.
+pub fn json_to_params(func: &ComponentFunc, args: &Value) -> anyhow::Result<Vec<Val>> {
+    let params = func.params();
+    let count = params.len();
+
+    match args {
+        Value::Null if count == 0 => Ok(vec![]),
+        Value::Array(values) => {
+            if values.len() != count {
+                anyhow::bail!("Expected {count} argument(s), got {}.", values.len());
+            }
+
+            params
+                .zip(values)
+                .map(|((name, ty), value)| {
+                    json_to_val(&ty, value).with_context(|| format!("Invalid argument `{name}`"))
+                })
+                .collect()
+        }
+        Value::Object(values) => {
+            if let Some(unknown) = values
+                .keys()
+                .find(|key| !func.params().any(|(name, _)| name == key.as_str()))
+            {
+                anyhow::bail!("Unknown argument `{unknown}`.");
+            }
+
+            params
+                .map(|(name, ty)| {
+                    let value = values
+                        .get(name)
+                        .with_context(|| format!("Missing argument `{name}`."))?;
+                    json_to_val(&ty, value).with_context(|| format!("Invalid argument `{name}`"))
+                })
+                .collect()
+        }
+        _ => anyhow::bail!("Expected {count} argument(s) as an array or an object."),
+    }
+}
+
+/// Converts the results of a call back to JSON: `null` for none, the value
+/// itself for one, and an array otherwise.
+pub fn results_to_json(results: &[Val]) -> Value {
+    match results {
+        [] => Value::Null,
+        [result] => val_to_json(result),
+        results => Value::Array(results.iter().map(val_to_json).collect()),
+    }
+}
+
+pub fn json_to_val(ty: &Type, value: &Value) -> anyhow::Result<Val> {
+    let mismatch = || anyhow!("Expected {}, got {value}.", render_type(ty));
+
+    Ok(match ty {
+        Type::Bool => Val::Bool(value.as_bool().ok_or_else(mismatch)?),
+        Type::S8 => Val::S8(int(value).ok_or_else(mismatch)?),
+        Type::U8 => Val::U8(int(value).ok_or_else(mismatch)?),
+        Type::S16 => Val::S16(int(value).ok_or_else(mismatch)?),
+        Type::U16 => Val::U16(int(value).ok_or_else(mismatch)?),
+        Type::S32 => Val::S32(int(value).ok_or_else(mismatch)?),
+        Type::U32 => Val::U32(int(value).ok_or_else(mismatch)?),
+        Type::S64 => Val::S64(int(value).ok_or_else(mismatch)?),
+        Type::U64 => Val::U64(int(value).ok_or_else(mismatch)?),
+        Type::Float32 => Val::Float32(value.as_f64().ok_or_else(mismatch)? as f32),
+        Type::Float64 => Val::Float64(value.as_f64().ok_or_else(mismatch)?),
+        Type::Char => {
+            let mut chars = value.as_str().ok_or_else(mismatch)?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Val::Char(c),
+                _ => return Err(mismatch()),
+            }
+        }
+        Type::String => Val::String(value.as_str().ok_or_else(mismatch)?.to_string()),
+        Type::List(list) => Val::List(
+            value
+                .as_array()
+                .ok_or_else(mismatch)?
+                .iter()
+                .map(|value| json_to_val(&list.ty(), value))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Type::Record(record) => {
+            let values = value.as_object().ok_or_else(mismatch)?;
+            Val::Record(
+                record
+                    .fields()
+                    .map(|field| {
+                        let value = values.get(field.name).unwrap_or(&Value::Null);
+                        let value = json_to_val(&field.ty, value)
+                            .with_context(|| format!("Invalid field `{}`", field.name))?;
+                        Ok((field.name.to_string(), value))
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        }
+        Type::Tuple(tuple) => {
+            let values = value.as_array().ok_or_else(mismatch)?;
+            if values.len() != tuple.types().len() {
+                return Err(mismatch());
+            }
+
+            Val::Tuple(
+                tuple
+                    .types()
+                    .zip(values)
+                    .map(|(ty, value)| json_to_val(&ty, value))
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        }
+        Type::Variant(variant) => {
+            let (name, payload) = tagged(value).ok_or_else(mismatch)?;
+            let case = variant
+                .cases()
+                .find(|case| case.name == name)
+                .ok_or_else(|| anyhow!("Unknown case `{name}`."))?;
+
+            let payload = match (case.ty, payload) {
+                (Some(ty), Some(payload)) => Some(Box::new(json_to_val(&ty, payload)?)),
+                (None, None | Some(Value::Null)) => None,
+                _ => return Err(mismatch()),
+            };
+            Val::Variant(name.to_string(), payload)
+        }
+        Type::Enum(enumeration) => {
+            let name = value.as_str().ok_or_else(mismatch)?;
+            if !enumeration.names().any(|case| case == name) {
+                anyhow::bail!("Unknown case `{name}`.");
+            }
+
+            Val::Enum(name.to_string())
+        }
+        Type::Option(option) => match value {
+            Value::Null => Val::Option(None),
+            value => Val::Option(Some(Box::new(json_to_val(&option.ty(), value)?))),
+        },
+        Type::Result(result) => {
+            let (name, payload) = tagged(value).ok_or_else(mismatch)?;
+            let (ty, wrap): (_, fn(_) -> _) = match name {
+                "ok" => (result.ok(), Ok),
+                "err" => (result.err(), Err),
+                _ => return Err(mismatch()),
+            };
+
+            let payload = match (ty, payload) {
+                (Some(ty), Some(payload)) => Some(Box::new(json_to_val(&ty, payload)?)),
+                (None, None | Some(Value::Null)) => None,
+                _ => return Err(mismatch()),
+            };
+            Val::Result(wrap(payload))
+        }
+        Type::Flags(flags) => Val::Flags(
+            value
+                .as_array()
+                .ok_or_else(mismatch)?
+                .iter()
+                .map(|flag| {
+                    let flag = flag.as_str().ok_or_else(mismatch)?;
+                    if !flags.names().any(|name| name == flag) {
+                        anyhow::bail!("Unknown flag `{flag}`.");
+                    }
+
+                    Ok(flag.to_string())
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Type::Own(_) | Type::Borrow(_) => {
+            anyhow::bail!("Resources cannot be passed as invocation arguments.")
+        }
+    })
+}
+
+pub fn val_to_json(val: &Val) -> Value {
+    match val {
+        Val::Bool(value) => Value::from(*value),
+        Val::S8(value) => Value::from(*value),
+        Val::U8(value) => Value::from(*value),
+        Val::S16(value) => Value::from(*value),
+        Val::U16(value) => Value::from(*value),
+        Val::S32(value) => Value::from(*value),
+        Val::U32(value) => Value::from(*value),
+        Val::S64(value) => Value::from(*value),
+        Val::U64(value) => Value::from(*value),
+        Val::Float32(value) => Value::from(*value),
+        Val::Float64(value) => Value::from(*value),
+        Val::Char(value) => Value::from(value.to_string()),
+        Val::String(value) => Value::from(value.as_str()),
+        Val::List(values) | Val::Tuple(values) => {
+            Value::Array(values.iter().map(val_to_json).collect())
+        }
+        Val::Record(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.clone(), val_to_json(value)))
+                .collect(),
+        ),
+        Val::Variant(name, None) | Val::Enum(name) => Value::from(name.as_str()),
+        Val::Variant(name, Some(payload)) => {
+            Value::Object(Map::from_iter([(name.clone(), val_to_json(payload))]))
+        }
+        Val::Option(value) => value.as_deref().map_or(Value::Null, val_to_json),
+        Val::Result(result) => {
+            let (name, payload) = match result {
+                Ok(payload) => ("ok", payload),
+                Err(payload) => ("err", payload),
+            };
+            Value::Object(Map::from_iter([(
+                name.to_string(),
+                payload.as_deref().map_or(Value::Null, val_to_json),
+            )]))
+        }
+        Val::Flags(flags) => Value::Array(
+            flags
+                .iter()
+                .map(|flag| Value::from(flag.as_str()))
+                .collect(),
+        ),
+        Val::Resource(_) => Value::String("<resource>".to_string()),
+    }
+}
+
+/// Placeholder values a call writes its results into.
+pub fn result_slots(func: &ComponentFunc) -> Vec<Val> {
+    vec![Val::Bool(false); func.results().len()]
+}
+
+/// Fails if `func` cannot be called with JSON arguments, i.e. it takes or returns resources.
+pub fn check_signature(func: &ComponentFunc) -> anyhow::Result<()> {
+    for (name, ty) in func.params() {
+        if contains_resource(&ty) {
+            anyhow::bail!(
+                "Parameter `{name}` has unsupported type {}.",
+                render_type(&ty)
+            );
+        }
+    }
+    for ty in func.results() {
+        if contains_resource(&ty) {
+            anyhow::bail!("Result has unsupported type {}.", render_type(&ty));
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders a function signature the way WIT spells it, e.g. `func(name: string) -> u32`.
+pub fn render_signature(func: &ComponentFunc) -> String {
+    let params = func
+        .params()
+        .map(|(name, ty)| format!("{name}: {}", render_type(&ty)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let results = func
+        .results()
+        .map(|ty| render_type(&ty))
+        .collect::<Vec<_>>();
+
+    match results.as_slice() {
+        [] => format!("func({params})"),
+        [result] => format!("func({params}) -> {result}"),
+        results => format!("func({params}) -> ({})", results.join(", ")),
+    }
+}
+
+/// Renders a type the way WIT spells it. Named types are rendered structurally,
+/// since the component model does not keep their names.
+pub fn render_type(ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".to_string(),
+        Type::S8 => "s8".to_string(),
+        Type::U8 => "u8".to_string(),
+        Type::S16 => "s16".to_string(),
+        Type::U16 => "u16".to_string(),
+        Type::S32 => "s32".to_string(),
+        Type::U32 => "u32".to_string(),
+        Type::S64 => "s64".to_string(),
+        Type::U64 => "u64".to_string(),
+        Type::Float32 => "f32".to_string(),
+        Type::Float64 => "f64".to_string(),
+        Type::Char => "char".to_string(),
+        Type::String => "string".to_string(),
+        Type::List(list) => format!("list<{}>", render_type(&list.ty())),
+        Type::Record(record) => format!(
+            "record {{ {} }}",
+            record
+                .fields()
+                .map(|field| format!("{}: {}", field.name, render_type(&field.ty)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Type::Tuple(tuple) => format!(
+            "tuple<{}>",
+            tuple
+                .types()
+                .map(|ty| render_type(&ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Type::Variant(variant) => format!(
+            "variant {{ {} }}",
+            variant
+                .cases()
+                .map(|case| match case.ty {
+                    Some(ty) => format!("{}({})", case.name, render_type(&ty)),
+                    None => case.name.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Type::Enum(enumeration) => format!(
+            "enum {{ {} }}",
+            enumeration.names().collect::<Vec<_>>().join(", ")
+        ),
+        Type::Option(option) => format!("option<{}>", render_type(&option.ty())),
+        Type::Result(result) => match (result.ok(), result.err()) {
+            (None, None) => "result".to_string(),
+            (Some(ok), None) => format!("result<{}>", render_type(&ok)),
+            (None, Some(err)) => format!("result<_, {}>", render_type(&err)),
+            (Some(ok), Some(err)) => {
+                format!("result<{}, {}>", render_type(&ok), render_type(&err))
+            }
+        },
+        Type::Flags(flags) => format!(
+            "flags {{ {} }}",
+            flags.names().collect::<Vec<_>>().join(", ")
+        ),
+        Type::Own(_) => "own<resource>".to_string(),
+        Type::Borrow(_) => "borrow<resource>".to_string(),
+    }
+}
+
+/// Converts positional JSON arguments into the parameters of a core function.
+///
+/// Integers may be given in either their signed or unsigned range.
+pub fn json_to_core_params(func: &FuncType, args: &Value) -> anyhow::Result<Vec<wasmtime::Val>> {
+    let values = match args {
+        Value::Null => &vec![],
+        Value::Array(values) => values,
+        _ => anyhow::bail!("Core functions only take positional arguments."),
+    };
+    if values.len() != func.params().len() {
+        anyhow::bail!(
+            "Expected {} argument(s), got {}.",
+            func.params().len(),
+            values.len()
+        );
+    }
+
+    func.params()
+        .zip(values)
+        .enumerate()
+        .map(|(index, (ty, value))| {
+            let mismatch = || anyhow!("Invalid argument {index}: expected {ty}, got {value}.");
+            Ok(match ty {
+                ValType::I32 => wasmtime::Val::I32(
+                    int::<i32>(value)
+                        .or_else(|| int::<u32>(value).map(|value| value as i32))
+                        .ok_or_else(mismatch)?,
+                ),
+                ValType::I64 => wasmtime::Val::I64(
+                    int::<i64>(value)
+                        .or_else(|| int::<u64>(value).map(|value| value as i64))
+                        .ok_or_else(mismatch)?,
+                ),
+                ValType::F32 => {
+                    wasmtime::Val::F32((value.as_f64().ok_or_else(mismatch)? as f32).to_bits())
+                }
+                ValType::F64 => wasmtime::Val::F64(value.as_f64().ok_or_else(mismatch)?.to_bits()),
+                _ => return Err(mismatch()),
+            })
+        })
+        .collect()
+}
+
+pub fn core_results_to_json(results: &[wasmtime::Val]) -> Value {
+    let to_json = |val: &wasmtime::Val| match val {
+        wasmtime::Val::I32(value) => Value::from(*value),
+        wasmtime::Val::I64(value) => Value::from(*value),
+        wasmtime::Val::F32(bits) => Value::from(f32::from_bits(*bits)),
+        wasmtime::Val::F64(bits) => Value::from(f64::from_bits(*bits)),
+        _ => Value::Null,
+    };
+
+    match results {
+        [] => Value::Null,
+        [result] => to_json(result),
+        results => Value::Array(results.iter().map(to_json).collect()),
+    }
+}
+
+pub fn core_result_slots(func: &FuncType) -> Vec<wasmtime::Val> {
+    vec![wasmtime::Val::I32(0); func.results().len()]
+}
+
+/// Fails if `func` takes or returns anything but numbers.
+pub fn check_core_signature(func: &FuncType) -> anyhow::Result<()> {
+    if let Some(ty) = func.params().chain(func.results()).find(|ty| {
+        !matches!(
+            ty,
+            ValType::I32 | ValType::I64 | ValType::F32 | ValType::F64
+        )
+    }) {
+        anyhow::bail!("Type {ty} is not supported, only numbers can be passed.");
+    }
+
+    Ok(())
+}
+
+/// Renders a core function signature in the text format, e.g. `(func (param i32) (result i32))`.
+pub fn render_core_signature(func: &FuncType) -> String {
+    let mut rendered = "(func".to_string();
+    if func.params().len() > 0 {
+        let params = func.params().map(|ty| ty.to_string()).collect::<Vec<_>>();
+        rendered += &format!(" (param {})", params.join(" "));
+    }
+    if func.results().len() > 0 {
+        let results = func.results().map(|ty| ty.to_string()).collect::<Vec<_>>();
+        rendered += &format!(" (result {})", results.join(" "));
+    }
+    rendered + ")"
+}
+
+fn contains_resource(ty: &Type) -> bool {
+    match ty {
+        Type::Own(_) | Type::Borrow(_) => true,
+        Type::List(list) => contains_resource(&list.ty()),
+        Type::Record(record) => record.fields().any(|field| contains_resource(&field.ty)),
+        Type::Tuple(tuple) => tuple.types().any(|ty| contains_resource(&ty)),
+        Type::Variant(variant) => variant
+            .cases()
+            .any(|case| case.ty.is_some_and(|ty| contains_resource(&ty))),
+        Type::Option(option) => contains_resource(&option.ty()),
+        Type::Result(result) => {
+            result.ok().is_some_and(|ty| contains_resource(&ty))
+                || result.err().is_some_and(|ty| contains_resource(&ty))
+        }
+        _ => false,
+    }
+}
+
+fn int<T: TryFrom<i64> + TryFrom<u64>>(value: &Value) -> Option<T> {
+    match value.as_i64() {
+        Some(value) => T::try_from(value).ok(),
+        None => T::try_from(value.as_u64()?).ok(),
+    }
+}
+
+/// Splits `"case"` or `{"case": payload}` into the case name and its payload.
+fn tagged(value: &Value) -> Option<(&str, Option<&Value>)> {
+    match value {
+        Value::String(name) => Some((name, None)),
+        Value::Object(map) if map.len() == 1 => {
+            let (name, payload) = map.iter().next()?;
+            Some((name, Some(payload)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wasmtime::{
+        Engine,
+        component::{Component, types::ComponentItem},
+    };
+
+    use super::*;
+
+    /// Functions imported by a component, one per kind of parameter type.
+    const TYPES: &str = r#"(component
+        (import "types" (instance
+            (type $person' (record
+                (field "name" string)
+                (field "age" u8)
+                (field "nick" (option string))))
+            (export "person" (type $person (eq $person')))
+            (type $shape' (variant (case "point") (case "circle" f64) (case "owned" $person)))
+            (export "shape" (type $shape (eq $shape')))
+            (type $color' (enum "red" "green"))
+            (export "color" (type $color (eq $color')))
+            (type $access' (flags "read" "write"))
+            (export "access" (type $access (eq $access')))
+            (export "record" (func (param "x" $person)))
+            (export "variant" (func (param "x" $shape)))
+            (export "enum" (func (param "x" $color)))
+            (export "flags" (func (param "x" $access)))
+            (export "option" (func (param "x" (option u32))))
+            (export "result" (func (param "x" (result string (error u8)))))
+            (export "unit-result" (func (param "x" (result))))
+            (export "list" (func (param "x" (list (tuple s64 char)))))
+            (export "ints" (func
+                (param "a" s8) (param "b" u8) (param "c" s16) (param "d" u16)
+                (param "e" s32) (param "f" u32) (param "g" s64) (param "h" u64)))
+        ))
+    )"#;
+
+    fn func(name: &str) -> ComponentFunc {
+        let engine = Engine::new(&crate::engine_config()).unwrap();
+        let component = Component::new(&engine, TYPES).unwrap();
+        let ty = component.component_type();
+        let Some((_, ComponentItem::ComponentInstance(instance))) = ty.imports(&engine).next()
+        else {
+            panic!("types are not imported");
+        };
+        match instance.get_export(&engine, name) {
+            Some(ComponentItem::ComponentFunc(func)) => func,
+            _ => panic!("{name} is not a function"),
+        }
+    }
+
+    /// Type of the first parameter of `name`.
+    fn param(name: &str) -> Type {
+        func(name).params().next().unwrap().1
+    }
+
+    #[track_caller]
+    fn round_trip(ty: &Type, value: Value) {
+        let val = json_to_val(ty, &value).unwrap();
+        assert_eq!(val_to_json(&val), value);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let ty = param("record");
+        round_trip(&ty, json!({"name": "ada", "age": 36, "nick": null}));
+        round_trip(&ty, json!({"name": "ada", "age": 36, "nick": "countess"}));
+
+        // Missing fields are null, which only options accept.
+        let val = json_to_val(&ty, &json!({"name": "ada", "age": 36})).unwrap();
+        assert_eq!(
+            val_to_json(&val),
+            json!({"name": "ada", "age": 36, "nick": null})
+        );
+        assert!(json_to_val(&ty, &json!({"name": "ada"})).is_err());
+        assert!(json_to_val(&ty, &json!(["ada", 36, null])).is_err());
+    }
+
+    #[test]
+    fn variants_round_trip() {
+        let ty = param("variant");
+        round_trip(&ty, json!("point"));
+        round_trip(&ty, json!({"circle": 1.5}));
+        round_trip(
+            &ty,
+            json!({"owned": {"name": "ada", "age": 36, "nick": null}}),
+        );
+
+        // Cases without a payload may also be written as objects.
+        let val = json_to_val(&ty, &json!({"point": null})).unwrap();
+        assert_eq!(val_to_json(&val), json!("point"));
+
+        assert!(json_to_val(&ty, &json!("square")).is_err());
+        assert!(json_to_val(&ty, &json!("circle")).is_err());
+        assert!(json_to_val(&ty, &json!({"point": 1})).is_err());
+    }
+
+    #[test]
+    fn enums_and_flags_round_trip() {
+        let ty = param("enum");
+        round_trip(&ty, json!("red"));
+        assert!(json_to_val(&ty, &json!("blue")).is_err());
+
+        let ty = param("flags");
+        round_trip(&ty, json!([]));
+        round_trip(&ty, json!(["read", "write"]));
+        assert!(json_to_val(&ty, &json!(["execute"])).is_err());
+    }
+
+    #[test]
+    fn options_round_trip() {
+        let ty = param("option");
+        round_trip(&ty, json!(null));
+        round_trip(&ty, json!(7));
+        assert!(json_to_val(&ty, &json!("7")).is_err());
+    }
+
+    #[test]
+    fn results_round_trip() {
+        let ty = param("result");
+        round_trip(&ty, json!({"ok": "done"}));
+        round_trip(&ty, json!({"err": 4}));
+        assert!(json_to_val(&ty, &json!({"ok": 4})).is_err());
+        assert!(json_to_val(&ty, &json!({"maybe": "done"})).is_err());
+        assert!(json_to_val(&ty, &json!("ok")).is_err());
+
+        let ty = param("unit-result");
+        round_trip(&ty, json!({"ok": null}));
+        round_trip(&ty, json!({"err": null}));
+        let val = json_to_val(&ty, &json!("err")).unwrap();
+        assert_eq!(val_to_json(&val), json!({"err": null}));
+    }
+
+    #[test]
+    fn lists_round_trip() {
+        let ty = param("list");
+        round_trip(&ty, json!([]));
+        round_trip(&ty, json!([[-1, "a"], [i64::MAX, "ß"]]));
+        assert!(json_to_val(&ty, &json!([[1]])).is_err());
+        assert!(json_to_val(&ty, &json!([[1, "ab"]])).is_err());
+    }
+
+    #[test]
+    fn integers_round_trip_at_their_bounds() {
+        let func = func("ints");
+        let min = json!([
+            i8::MIN,
+            u8::MIN,
+            i16::MIN,
+            u16::MIN,
+            i32::MIN,
+            u32::MIN,
+            i64::MIN,
+            u64::MIN
+        ]);
+        let max = json!([
+            i8::MAX,
+            u8::MAX,
+            i16::MAX,
+            u16::MAX,
+            i32::MAX,
+            u32::MAX,
+            i64::MAX,
+            u64::MAX
+        ]);
+        for args in [min, max] {
+            let params = json_to_params(&func, &args).unwrap();
+            assert_eq!(results_to_json(&params), args);
+        }
+    }
+
+    #[test]
+    fn integers_out_of_range_are_rejected() {
+        for (name, value) in [
+            ("a", json!(128)),
+            ("a", json!(-129)),
+            ("b", json!(256)),
+            ("b", json!(-1)),
+            ("f", json!(-1)),
+            ("g", json!(u64::MAX)),
+            ("h", json!(-1)),
+            ("h", json!(1.5)),
+            ("h", json!("1")),
+        ] {
+            let ty = func("ints")
+                .params()
+                .find(|(param, _)| *param == name)
+                .unwrap()
+                .1;
+            assert!(
+                json_to_val(&ty, &value).is_err(),
+                "{value} accepted for {name}"
+            );
+        }
+    }
+
+    #[test]
+    fn arguments_are_positional_or_named() {
+        let func = func("result");
+        assert_eq!(
+            json_to_params(&func, &json!([{"ok": "done"}])).unwrap(),
+            json_to_params(&func, &json!({"x": {"ok": "done"}})).unwrap()
+        );
+        assert!(json_to_params(&func, &json!(null)).is_err());
+        assert!(json_to_params(&func, &json!([])).is_err());
+        assert!(json_to_params(&func, &json!({})).is_err());
+        assert!(json_to_params(&func, &json!({"x": {"ok": "done"}, "y": 1})).is_err());
+    }
+}
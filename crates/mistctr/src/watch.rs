@@ -0,0 +1,138 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{self, Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use config::{Spec, SpecSource};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+use crate::ControlPanel;
+
+/// How often the set of watched files is brought in line with the droplets.
+const SYNC: Duration = Duration::from_secs(1);
+/// Quiet period after a change before recompiling, so a build writing the file in several
+/// steps causes a single reload.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Recompiles droplets whose `File` source sets `watch: true` when the file changes.
+///
+/// Parent directories are watched rather than the files, since build tools usually replace
+/// the file instead of writing to it.
+pub struct SourceWatcher {
+    panel: Arc<ControlPanel>,
+    watcher: RecommendedWatcher,
+    changes: UnboundedReceiver<PathBuf>,
+    /// Droplet ids by the absolute path of the source they watch.
+    sources: HashMap<PathBuf, BTreeSet<String>>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl SourceWatcher {
+    pub fn new(panel: Arc<ControlPanel>) -> anyhow::Result<Self> {
+        let (sender, changes) = mpsc::unbounded_channel();
+        let watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    for path in event.paths {
+                        sender.send(path).ok();
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Watching droplet sources: {e}"),
+            })?;
+
+        Ok(Self {
+            panel,
+            watcher,
+            changes,
+            sources: HashMap::new(),
+            dirs: HashSet::new(),
+        })
+    }
+
+    pub async fn run(mut self) {
+        let mut interval = tokio::time::interval(SYNC);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => self.sync(),
+                Some(path) = self.changes.recv() => {
+                    tokio::time::sleep(SETTLE).await;
+                    let mut changed = BTreeSet::from([path]);
+                    while let Ok(path) = self.changes.try_recv() {
+                        changed.insert(path);
+                    }
+
+                    // Droplets created since the last sync are picked up before reloading.
+                    self.sync();
+                    for path in changed {
+                        self.reload(&path).await;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn reload(&self, path: &Path) {
+        for name in self.sources.get(path).into_iter().flatten() {
+            match self.panel.reload_droplet(name).await {
+                Ok(()) => tracing::info!("Reloaded droplet {name} from {}", path.display()),
+                Err(e) => tracing::error!("Failed to reload droplet {name}: {e:#}"),
+            }
+        }
+    }
+
+    /// Watches the directories of sources that droplets currently ask to watch, and stops
+    /// watching the ones no droplet needs anymore.
+    fn sync(&mut self) {
+        self.sources = watched_sources(&self.panel);
+        let dirs = self
+            .sources
+            .keys()
+            .filter_map(|path| path.parent())
+            .map(Path::to_path_buf)
+            .collect::<HashSet<_>>();
+
+        for dir in self.dirs.difference(&dirs) {
+            if let Err(e) = self.watcher.unwatch(dir) {
+                tracing::debug!("Unwatching {}: {e}", dir.display());
+            }
+        }
+        self.dirs.retain(|dir| dirs.contains(dir));
+
+        // Directories that cannot be watched yet, e.g. before the first build, are retried
+        // on the next sync.
+        for dir in dirs {
+            if self.dirs.contains(&dir) {
+                continue;
+            }
+            match self.watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.dirs.insert(dir);
+                }
+                Err(e) => tracing::debug!("Cannot watch {} yet: {e}", dir.display()),
+            }
+        }
+    }
+}
+
+fn watched_sources(panel: &ControlPanel) -> HashMap<PathBuf, BTreeSet<String>> {
+    let mut sources = HashMap::<_, BTreeSet<_>>::new();
+    for droplet in panel.droplets.iter() {
+        let Spec::Droplet { source, .. } = &droplet.config.spec;
+        if let SpecSource::File { path, watch: true } = source
+            && let Ok(path) = path::absolute(path)
+        {
+            sources
+                .entry(path)
+                .or_default()
+                .insert(droplet.key().clone());
+        }
+    }
+
+    sources
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    env, fmt, fs,
+    net::SocketAddr,
+    path::{Path as FsPath, PathBuf},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Path, Request, State},
+    http::{StatusCode, header, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use mistctr::invocation::Caller;
+use subtle::ConstantTimeEq;
+
+use crate::state::AppState;
+
+/// How requests are authenticated.
+#[derive(Clone)]
+pub enum Authentication {
+    /// The admin token grants access to everything, a namespace token to its namespace.
+    Tokens {
+        admin_token: String,
+        /// Directories namespace tokens may use `File` sources and filesystem mounts in.
+        file_source_roots: Vec<PathBuf>,
+    },
+    /// Every request is let through, for local development only.
+    Disabled,
+}
+
+impl Authentication {
+    /// Reads the admin token from `MIST_ADMIN_TOKEN`, and the directories namespace tokens
+    /// may use `File` sources and filesystem mounts in from `MIST_FILE_SOURCE_ROOTS`, a list
+    /// in the platform's `PATH` format.
+    ///
+    /// Authentication is only turned off when `MIST_AUTH_DISABLED=true` asks for it; a
+    /// daemon that is merely missing its token refuses to start.
+    pub fn from_env() -> anyhow::Result<Self> {
+        if let Ok(admin_token) = env::var("MIST_ADMIN_TOKEN")
+            && !admin_token.is_empty()
+        {
+            let file_source_roots = env::var_os("MIST_FILE_SOURCE_ROOTS")
+                .map(|roots| {
+                    env::split_paths(&roots)
+                        .filter(|root| !root.as_os_str().is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            return Ok(Self::Tokens {
+                admin_token,
+                file_source_roots,
+            });
+        }
+
+        match env::var("MIST_AUTH_DISABLED").as_deref() {
+            Ok("true" | "1") => Ok(Self::Disabled),
+            _ => anyhow::bail!(
+                "MIST_ADMIN_TOKEN is not set. Set it, or set MIST_AUTH_DISABLED=true to serve requests without authentication."
+            ),
+        }
+    }
+}
+
+/// Who a request's bearer token belongs to, added to the request's extensions once
+/// authenticated.
+#[derive(Debug, Clone)]
+enum Principal {
+    Admin,
+    Namespace(String),
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Admin => write!(f, "admin"),
+            Self::Namespace(namespace) => write!(f, "namespace:{namespace}"),
+        }
+    }
+}
+
+/// The [`Caller`] of a request: the principal it authenticated as and its peer address.
+pub struct RequestCaller(pub Caller);
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestCaller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(Caller {
+            principal: parts.extensions.get::<Principal>().map(ToString::to_string),
+            peer: parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(peer)| peer.to_string()),
+        }))
+    }
+}
+
+/// Which host paths a request may create droplets with, as `File` sources or filesystem
+/// mounts.
+///
+/// The daemon opens those paths with its own permissions, so only the admin may name any
+/// path; namespace tokens are held to the configured roots, and to none by default.
+pub enum FileSourceAccess {
+    Any,
+    Within(Vec<PathBuf>),
+}
+
+impl FileSourceAccess {
+    /// Explains why `path`, used as `what`, may not be used, if it may not.
+    pub fn check(&self, what: &str, path: &FsPath) -> Result<(), String> {
+        let Self::Within(roots) = self else {
+            return Ok(());
+        };
+
+        // Resolved first, so `..` and symlinks cannot lead out of a root.
+        let resolved = fs::canonicalize(path)
+            .map_err(|e| format!("{what} {} cannot be read: {e}", path.display()))?;
+        if roots
+            .iter()
+            .filter_map(|root| fs::canonicalize(root).ok())
+            .any(|root| resolved.starts_with(root))
+        {
+            return Ok(());
+        }
+
+        Err(format!(
+            "{what} {} is outside the directories namespace tokens may use.",
+            path.display()
+        ))
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for FileSourceAccess {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Authentication::Tokens {
+            file_source_roots, ..
+        } = state.authentication()
+        else {
+            return Ok(Self::Any);
+        };
+
+        Ok(match parts.extensions.get::<Principal>() {
+            Some(Principal::Admin) => Self::Any,
+            _ => Self::Within(file_source_roots.clone()),
+        })
+    }
+}
+
+/// Only lets the admin through, e.g. to manage namespaces.
+pub async fn require_admin(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Authentication::Tokens { admin_token, .. } = state.authentication() else {
+        return next.run(request).await;
+    };
+
+    match authenticate(&state, admin_token, &request) {
+        Ok(Some(Principal::Admin)) => run_as(Principal::Admin, request, next).await,
+        Ok(Some(Principal::Namespace(_))) => forbidden(),
+        Ok(None) => unauthorized(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Lets through any valid token, for routes that are not tied to a namespace.
+pub async fn require_token(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Authentication::Tokens { admin_token, .. } = state.authentication() else {
+        return next.run(request).await;
+    };
+
+    match authenticate(&state, admin_token, &request) {
+        Ok(Some(principal)) => run_as(principal, request, next).await,
+        Ok(None) => unauthorized(),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Lets through the admin and the token of the `{namespace}` in the path, once the
+/// namespace is known to exist.
+pub async fn require_namespace(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(namespace) = params.get("namespace") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match state.control_panel().namespaces().get(namespace) {
+        Ok(Some(_)) => {}
+        Ok(None) => return (StatusCode::NOT_FOUND, "Namespace does not exist.").into_response(),
+        Err(e) => return internal_error(e),
+    }
+
+    let Authentication::Tokens { admin_token, .. } = state.authentication() else {
+        return next.run(request).await;
+    };
+
+    match authenticate(&state, admin_token, &request) {
+        Ok(Some(Principal::Admin)) => run_as(Principal::Admin, request, next).await,
+        Ok(Some(Principal::Namespace(granted))) if granted == *namespace => {
+            run_as(Principal::Namespace(granted), request, next).await
+        }
+        Ok(Some(Principal::Namespace(_))) => forbidden(),
+        Ok(None) => unauthorized(),
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn run_as(principal: Principal, mut request: Request, next: Next) -> Response {
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+fn authenticate(
+    state: &AppState,
+    admin_token: &str,
+    request: &Request,
+) -> anyhow::Result<Option<Principal>> {
+    let Some(token) = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return Ok(None);
+    };
+
+    if bool::from(token.as_bytes().ct_eq(admin_token.as_bytes())) {
+        return Ok(Some(Principal::Admin));
+    }
+
+    Ok(state
+        .control_panel()
+        .namespaces()
+        .authenticate(token)?
+        .map(Principal::Namespace))
+}
+
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, "Missing or invalid bearer token.").into_response()
+}
+
+fn forbidden() -> Response {
+    (
+        StatusCode::FORBIDDEN,
+        "Token does not grant access to this resource.",
+    )
+        .into_response()
+}
+
+fn internal_error(e: anyhow::Error) -> Response {
+    tracing::error!("{e}");
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespace_tokens_only_use_files_within_the_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("echo.wasm"), b"").unwrap();
+        fs::write(outside.path().join("secret"), b"").unwrap();
+        let access = FileSourceAccess::Within(vec![root.path().to_path_buf()]);
+
+        assert!(
+            access
+                .check("File source", &root.path().join("echo.wasm"))
+                .is_ok()
+        );
+        assert!(
+            access
+                .check("File source", &outside.path().join("secret"))
+                .is_err()
+        );
+        // Leaving the root through `..` is noticed once the path is resolved.
+        let escape = root.path().join("..").join(
+            outside
+                .path()
+                .strip_prefix(root.path().parent().unwrap())
+                .unwrap(),
+        );
+        assert!(access.check("File source", &escape.join("secret")).is_err());
+        assert!(
+            access
+                .check("File source", &root.path().join("missing.wasm"))
+                .is_err()
+        );
+
+        assert!(
+            FileSourceAccess::Within(vec![])
+                .check("File source", &root.path().join("echo.wasm"))
+                .is_err()
+        );
+        assert!(
+            FileSourceAccess::Any
+                .check("File source", &outside.path().join("secret"))
+                .is_ok()
+        );
+    }
+}
@@ -1,2 +1,4 @@
+pub mod auth;
 pub mod routes;
 pub mod state;
+pub mod telemetry;
@@ -1,34 +1,74 @@
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
 use anyhow::anyhow;
-use axum::Router;
-use mistctr::ControlPanel;
-use mistd::{routes, state::AppState};
+use axum::{Router, ServiceExt, extract::Request, middleware, routing::get};
+use mistctr::{
+    ControlPanel,
+    admission::ResourceBudget,
+    invocation::{HistoryPruner, HistoryRetention},
+    jobs::{JobPruner, JobSettings, JobWorker},
+    limits::ResourceMaximums,
+    scheduler::Scheduler,
+    watch::SourceWatcher,
+};
+use mistd::{auth::Authentication, routes, state::AppState, telemetry};
 use tokio::net::TcpListener;
-use tracing::log::LevelFilter;
+use tower::{Layer, util::MapRequestLayer};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::Builder::default()
-        .filter_level(LevelFilter::Info)
-        .parse_env("APP_LOG")
-        .init();
+    let tracer_provider = telemetry::init()?;
 
-    let control_panel = Arc::new(ControlPanel::default()?);
+    let authentication = Authentication::from_env()?;
+    if let Authentication::Disabled = authentication {
+        tracing::warn!("Authentication is disabled, every request is let through");
+    }
 
-    tokio::spawn(async move {
-        let state = AppState::new(control_panel);
+    let control_panel = Arc::new(
+        ControlPanel::new(ResourceMaximums::from_env()?)
+            .await?
+            .with_budget(ResourceBudget::from_env()?)
+            .with_history_retention(HistoryRetention::from_env()?)
+            .with_job_settings(JobSettings::from_env()?),
+    );
+
+    tokio::spawn(Scheduler::new(control_panel.clone()).run());
+    tokio::spawn(JobWorker::new(control_panel.clone()).run());
+    tokio::spawn(SourceWatcher::new(control_panel.clone())?.run());
+    tokio::spawn(HistoryPruner::new(control_panel.clone()).run());
+    tokio::spawn(JobPruner::new(control_panel.clone()).run());
+
+    let served = tokio::spawn(async move {
+        let state = AppState::new(control_panel, authentication);
         let state = Arc::new(state);
 
         let router = Router::new()
-            .nest("/ctr", routes::router())
+            .nest("/ctr", routes::router(state.clone()))
+            .route("/metrics", get(routes::metrics::handler))
+            .layer(middleware::from_fn(telemetry::trace_request))
             .with_state(state);
+        // Wraps the router rather than being one of its layers, so the rewritten path is routed.
+        let app = MapRequestLayer::new(routes::alias_legacy_routes).layer(router);
 
         let listener = TcpListener::bind(("0.0.0.0", 8080)).await?;
 
         tracing::info!("Listening on 0.0.0.0:8080");
 
-        axum::serve(listener, router).await.map_err(|e| anyhow!(e))
+        axum::serve(
+            listener,
+            ServiceExt::<Request>::into_make_service_with_connect_info::<SocketAddr>(app),
+        )
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await
+        .map_err(|e| anyhow!(e))
     })
-    .await?
+    .await?;
+
+    if let Some(tracer_provider) = tracer_provider {
+        tracer_provider.shutdown()?;
+    }
+
+    served
 }
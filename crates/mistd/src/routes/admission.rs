@@ -0,0 +1,10 @@
+use std::sync::Arc;
+
+use axum::{Json, extract::State, response::IntoResponse};
+
+use crate::state::AppState;
+
+/// Reports the node's resource budget and the invocations holding part of it.
+pub async fn handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.control_panel().admission())
+}
@@ -0,0 +1,11 @@
+pub mod upload;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::post};
+
+use crate::state::AppState;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new().route("/", post(upload::handler))
+}
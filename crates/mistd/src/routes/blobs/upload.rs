@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use axum::{Json, body::Body, extract::State, http::StatusCode, response::IntoResponse};
+use futures_util::StreamExt;
+use mistctr::blobs::BlobTooLarge;
+
+use crate::state::AppState;
+
+/// Streams the request body into the blob store.
+pub async fn handler(State(state): State<Arc<AppState>>, body: Body) -> impl IntoResponse {
+    let upload = async {
+        let mut writer = state.control_panel().blobs().writer().await?;
+
+        let mut stream = body.into_data_stream();
+        while let Some(chunk) = stream.next().await {
+            writer.write(&chunk?).await?;
+        }
+
+        writer.finish().await
+    }
+    .await;
+
+    match upload {
+        Ok(blob) => {
+            tracing::info!("Stored blob {} ({} bytes)", blob.digest, blob.size);
+            (StatusCode::CREATED, Json(blob)).into_response()
+        }
+        Err(e) if e.is::<BlobTooLarge>() => {
+            (StatusCode::PAYLOAD_TOO_LARGE, e.to_string()).into_response()
+        }
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path((namespace, id)): Path<(String, String)>,
+) -> impl IntoResponse {
+    match state.control_panel().get_dead_letter(&namespace, &id) {
+        Ok(Some(letter)) => Json(letter).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Dead letter does not exist.").into_response(),
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
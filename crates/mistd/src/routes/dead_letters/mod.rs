@@ -0,0 +1,19 @@
+pub mod get;
+pub mod list;
+pub mod replay;
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{get, post},
+};
+
+use crate::state::AppState;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list::handler))
+        .route("/{id}", get(get::handler))
+        .route("/{id}/replay", post(replay::handler))
+}
@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use crate::{auth::RequestCaller, state::AppState};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use mistctr::droplet::DropletNotFound;
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path((namespace, id)): Path<(String, String)>,
+    RequestCaller(caller): RequestCaller,
+) -> impl IntoResponse {
+    match state
+        .control_panel()
+        .replay_dead_letter(&namespace, &id, caller)
+    {
+        Ok(Some(job)) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Dead letter does not exist.").into_response(),
+        Err(e) if e.is::<DropletNotFound>() => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -1,32 +1,78 @@
-use std::sync::Arc;
+use std::{collections::BTreeSet, sync::Arc};
 
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
-use config::RootConfig;
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use config::{
+    Spec, SpecSource, validate,
+    versions::{self, MigrationError},
+};
+use mistctr::{limits::LimitNotAllowed, namespace::QuotaExceeded};
 use serde::Deserialize;
 
-use crate::state::AppState;
+use crate::{auth::FileSourceAccess, state::AppState};
 
 #[derive(Debug, Deserialize)]
 pub struct DropletCreatePayload {
-    config: RootConfig,
+    /// Config in any supported schema, migrated to the latest one.
+    config: serde_json::Value,
 }
 
 pub async fn handler(
     State(state): State<Arc<AppState>>,
+    Path(namespace): Path<String>,
+    file_sources: FileSourceAccess,
     Json(payload): Json<DropletCreatePayload>,
 ) -> impl IntoResponse {
-    if payload.config.spec.as_droplet().is_none() {
-        panic!("Not a droplet config.");
+    // Configs without a namespace land in the one they are created in.
+    let config = match versions::migrate_into(payload.config, &namespace) {
+        Ok(config) => config,
+        Err(e @ MigrationError::NamespaceMismatch { .. }) => {
+            return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+        }
+        Err(e) => return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    };
+    let secrets = match state.control_panel().secrets().list(&namespace) {
+        Ok(secrets) => secrets.into_iter().collect::<BTreeSet<_>>(),
+        Err(e) => {
+            tracing::error!("{e}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if let Err(e) = validate::validate(&config, Some(&secrets)) {
+        return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response();
+    }
+    let Spec::Droplet {
+        source, runtime, ..
+    } = &config.spec;
+    if let SpecSource::File { path, .. } = source
+        && let Err(reason) = file_sources.check("File source", path)
+    {
+        return (StatusCode::FORBIDDEN, reason).into_response();
+    }
+    for mount in runtime.filesystem.iter().flatten() {
+        if let Err(reason) = file_sources.check("Mount", mount.host_path.as_ref()) {
+            return (StatusCode::FORBIDDEN, reason).into_response();
+        }
     }
 
-    let name = payload.config.metadata.name.clone();
+    let name = config.metadata.id();
 
-    state
-        .control_panel()
-        .create_droplet(payload.config)
-        .unwrap();
+    if let Err(e) = state.control_panel().create_droplet(config).await {
+        if e.is::<QuotaExceeded>() {
+            return (StatusCode::FORBIDDEN, e.to_string()).into_response();
+        }
+        if e.is::<LimitNotAllowed>() {
+            return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response();
+        }
+        tracing::error!("{e:#}");
+        return (StatusCode::BAD_REQUEST, format!("{e:#}")).into_response();
+    }
 
     tracing::info!("Created droplet: {}", name);
 
-    StatusCode::OK
+    StatusCode::OK.into_response()
 }
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use crate::{routes::droplet::list::DropletSelectorQuery, state::AppState};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use mistctr::namespace;
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> impl IntoResponse {
+    let id = namespace::droplet_id(&namespace, &name);
+    match state.control_panel().delete_droplet(&id) {
+        Ok(true) => {
+            tracing::info!("Deleted droplet: {id}");
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, "Droplet does not exist.").into_response(),
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Deletes every droplet of the namespace matching the selector, returning their names.
+pub async fn selector_handler(
+    State(state): State<Arc<AppState>>,
+    Path(namespace): Path<String>,
+    Query(query): Query<DropletSelectorQuery>,
+) -> impl IntoResponse {
+    if query.selector.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            "A non-empty selector is required to delete several droplets.",
+        )
+            .into_response();
+    }
+
+    match state
+        .control_panel()
+        .delete_droplets(&namespace, &query.selector)
+    {
+        Ok(names) => {
+            tracing::info!(
+                "Deleted droplets in {namespace} matching {}: {names:?}",
+                query.selector
+            );
+            Json(names).into_response()
+        }
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -1,6 +1,14 @@
 use std::sync::Arc;
 
-use crate::state::AppState;
+use mistctr::{
+    admission::CapacityExceeded,
+    droplet::DropletNotFound,
+    invocation::{InvocationOrigin, InvocationSource},
+    namespace::{self, QuotaExceeded},
+    values::InvalidArguments,
+};
+
+use crate::{auth::RequestCaller, state::AppState};
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -9,13 +17,32 @@ use axum::{
 
 pub async fn handler(
     State(state): State<Arc<AppState>>,
-    Path(id): Path<String>,
+    Path((namespace, name)): Path<(String, String)>,
+    RequestCaller(caller): RequestCaller,
 ) -> impl IntoResponse {
-    let output = match state.control_panel().run_droplet(&id).await {
+    let id = namespace::droplet_id(&namespace, &name);
+    let origin = InvocationOrigin::new(InvocationSource::Api, caller);
+    let output = match state
+        .control_panel()
+        .run_droplet(&id, origin, &Default::default())
+        .await
+    {
         Ok(result) => result,
+        Err(e) if e.is::<DropletNotFound>() => {
+            return (StatusCode::NOT_FOUND, e.to_string());
+        }
+        Err(e) if e.is::<InvalidArguments>() => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, e.to_string());
+        }
+        Err(e) if e.is::<QuotaExceeded>() => {
+            return (StatusCode::TOO_MANY_REQUESTS, e.to_string());
+        }
+        Err(e) if e.is::<CapacityExceeded>() => {
+            return (StatusCode::SERVICE_UNAVAILABLE, e.to_string());
+        }
         Err(e) => {
-            tracing::error!("{e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+            tracing::error!("{e:#}");
+            return (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}"));
         }
     };
 
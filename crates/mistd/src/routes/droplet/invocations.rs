@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use mistctr::{droplet::DropletNotFound, invocation::InvocationFilter, namespace};
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(filter): Query<InvocationFilter>,
+) -> impl IntoResponse {
+    match state
+        .control_panel()
+        .droplet_invocations(&namespace::droplet_id(&namespace, &name), &filter)
+    {
+        Ok(records) => Json(records).into_response(),
+        Err(e) if e.is::<DropletNotFound>() => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
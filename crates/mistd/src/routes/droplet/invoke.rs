@@ -0,0 +1,156 @@
+use std::{convert::Infallible, sync::Arc};
+
+use crate::{auth::RequestCaller, state::AppState};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{
+        IntoResponse,
+        sse::{Event, Sse},
+    },
+};
+use futures_util::{Stream, stream};
+use mistctr::{
+    admission::CapacityExceeded,
+    droplet::DropletNotFound,
+    invocation::{InvocationOrigin, InvocationSource},
+    jobs::WebhookRefused,
+    namespace::{self, QuotaExceeded},
+    values::InvalidArguments,
+};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+
+#[derive(Debug, Deserialize)]
+pub struct InvokeQuery {
+    #[serde(rename = "async", default)]
+    detach: bool,
+    /// Stream the droplet's output while it runs, see [`stream`].
+    #[serde(default)]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvokePayload {
+    webhook: Option<String>,
+    /// Arguments for the entrypoint, positional (array) or by name (object).
+    #[serde(default)]
+    args: serde_json::Value,
+}
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(query): Query<InvokeQuery>,
+    RequestCaller(caller): RequestCaller,
+    payload: Option<Json<InvokePayload>>,
+) -> impl IntoResponse {
+    let (args, webhook) = match payload {
+        Some(Json(payload)) => (payload.args, payload.webhook),
+        None => Default::default(),
+    };
+    let id = namespace::droplet_id(&namespace, &name);
+
+    if query.detach {
+        return match state
+            .control_panel()
+            .enqueue_droplet(&id, args, webhook, caller)
+        {
+            Ok(job) => (StatusCode::ACCEPTED, Json(job)).into_response(),
+            Err(e) if e.is::<DropletNotFound>() => {
+                (StatusCode::NOT_FOUND, e.to_string()).into_response()
+            }
+            Err(e) if e.is::<InvalidArguments>() || e.is::<WebhookRefused>() => {
+                (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response()
+            }
+            Err(e) => {
+                tracing::error!("{e:#}");
+                (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")).into_response()
+            }
+        };
+    }
+
+    let origin = InvocationOrigin::new(InvocationSource::Api, caller);
+    if query.stream {
+        return stream(state, id, origin, args).into_response();
+    }
+
+    match state.control_panel().run_droplet(&id, origin, &args).await {
+        Ok(result) => Json(result).into_response(),
+        Err(e) if e.is::<DropletNotFound>() => {
+            (StatusCode::NOT_FOUND, e.to_string()).into_response()
+        }
+        Err(e) if e.is::<InvalidArguments>() => {
+            (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response()
+        }
+        Err(e) if e.is::<QuotaExceeded>() => {
+            (StatusCode::TOO_MANY_REQUESTS, e.to_string()).into_response()
+        }
+        Err(e) if e.is::<CapacityExceeded>() => {
+            (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response()
+        }
+        Err(e) => {
+            tracing::error!("{e:#}");
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("{e:#}")).into_response()
+        }
+    }
+}
+
+/// Runs the droplet, sending each line it prints as an `output` event while it runs and
+/// then a `result` event with its [`DropletExecutionResult`], or an `error` event with why
+/// it failed. Every event's data is JSON.
+///
+/// The response has started by the time the droplet runs, so failures are reported as
+/// `error` events rather than through the status code.
+///
+/// [`DropletExecutionResult`]: mistctr::droplet::DropletExecutionResult
+fn stream(
+    state: Arc<AppState>,
+    id: String,
+    origin: InvocationOrigin,
+    args: serde_json::Value,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (events, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(
+        async move {
+            let (lines, mut output) = mpsc::unbounded_channel::<String>();
+            let forwarded = tokio::spawn({
+                let events = events.clone();
+                async move {
+                    while let Some(line) = output.recv().await {
+                        if let Ok(event) = Event::default().event("output").json_data(line) {
+                            events.send(event).ok();
+                        }
+                    }
+                }
+            });
+
+            let outcome = state
+                .control_panel()
+                .run_droplet_with_output(&id, origin, &args, Some(lines))
+                .await;
+            // The droplet has dropped its end of `lines`, so this ends once all are sent.
+            forwarded.await.ok();
+
+            let event = match outcome {
+                Ok(result) => Event::default().event("result").json_data(result),
+                Err(e) => Event::default().event("error").json_data(format!("{e:#}")),
+            };
+            match event {
+                Ok(event) => {
+                    events.send(event).ok();
+                }
+                Err(e) => tracing::error!("Failed to stream the result of {id}: {e}"),
+            }
+        }
+        .in_current_span(),
+    );
+
+    Sse::new(stream::unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok(event), receiver))
+    }))
+}
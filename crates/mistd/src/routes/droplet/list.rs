@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use mistctr::selector::LabelSelector;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct DropletSelectorQuery {
+    /// Label selector, e.g. `team=payments,env!=prod`; every droplet when omitted.
+    #[serde(default)]
+    pub selector: LabelSelector,
+}
+
+/// Lists the configs of the namespace's droplets matching the selector, sorted by name.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path(namespace): Path<String>,
+    Query(query): Query<DropletSelectorQuery>,
+) -> impl IntoResponse {
+    Json(
+        state
+            .control_panel()
+            .list_droplets(&namespace, &query.selector),
+    )
+}
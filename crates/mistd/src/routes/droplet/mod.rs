@@ -1,17 +1,29 @@
 pub mod create;
+pub mod delete;
 pub mod execute;
+pub mod invocations;
+pub mod invoke;
+pub mod list;
 
 use std::sync::Arc;
 
 use axum::{
     Router,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 
 use crate::state::AppState;
 
 pub fn router() -> Router<Arc<AppState>> {
     Router::new()
-        .route("/", post(create::handler))
+        .route(
+            "/",
+            get(list::handler)
+                .post(create::handler)
+                .delete(delete::selector_handler),
+        )
+        .route("/{id}", delete(delete::handler))
         .route("/{id}/execute", get(execute::handler))
+        .route("/{id}/invoke", post(invoke::handler))
+        .route("/{id}/invocations", get(invocations::handler))
 }
@@ -0,0 +1,15 @@
+use std::sync::Arc;
+
+use axum::{Json, body::Bytes, extract::State, http::StatusCode, response::IntoResponse};
+
+use crate::state::AppState;
+
+pub async fn handler(State(state): State<Arc<AppState>>, body: Bytes) -> impl IntoResponse {
+    match state.control_panel().inspect_wasm(body.to_vec()).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => {
+            tracing::error!("{e:#}");
+            (StatusCode::BAD_REQUEST, format!("{e:#}")).into_response()
+        }
+    }
+}
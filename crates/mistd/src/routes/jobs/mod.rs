@@ -0,0 +1,14 @@
+pub mod get;
+pub mod list;
+
+use std::sync::Arc;
+
+use axum::{Router, routing::get};
+
+use crate::state::AppState;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list::handler))
+        .route("/{id}", get(get::handler))
+}
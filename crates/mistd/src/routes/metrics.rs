@@ -0,0 +1,19 @@
+use axum::{
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+
+pub async fn handler() -> impl IntoResponse {
+    match mistctr::metrics::gather() {
+        Ok(metrics) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+            metrics,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
@@ -1,11 +1,126 @@
 use std::sync::Arc;
 
-use axum::Router;
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, Request},
+    http::Uri,
+    middleware,
+    routing::{get, post},
+};
+use config::Metadata;
 
-use crate::state::AppState;
+use crate::{auth, state::AppState};
 
+pub mod admission;
+pub mod blobs;
+pub mod dead_letters;
 pub mod droplet;
+pub mod inspect;
+pub mod jobs;
+pub mod metrics;
+pub mod namespaces;
+pub mod schema;
+pub mod trigger;
 
-pub fn router() -> Router<Arc<AppState>> {
-    Router::new().nest("/droplet", droplet::router())
+/// Largest component accepted for inspection.
+const INSPECT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Collections served under `/ctr/<collection>` before namespaces existed.
+const LEGACY_COLLECTIONS: [&str; 4] = ["dead-letters", "droplet", "jobs", "trigger"];
+
+pub fn router(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let namespaced = Router::new()
+        .nest("/dead-letters", dead_letters::router())
+        .nest("/droplet", droplet::router())
+        .nest("/jobs", jobs::router())
+        .nest("/trigger", trigger::router())
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_namespace,
+        ));
+
+    let shared = Router::new()
+        .nest("/blobs", blobs::router())
+        .route(
+            "/inspect",
+            post(inspect::handler).layer(DefaultBodyLimit::max(INSPECT_BODY_LIMIT)),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_token,
+        ));
+
+    let admin = Router::new()
+        .route("/admission", get(admission::handler))
+        .nest("/namespaces", namespaces::router())
+        .route_layer(middleware::from_fn_with_state(state, auth::require_admin));
+
+    Router::new()
+        .merge(shared)
+        .merge(admin)
+        .nest("/namespaces/{namespace}", namespaced)
+        .route("/schema/{*api_version}", get(schema::handler))
+}
+
+/// Rewrites the pre-namespace `/ctr/<collection>/...` routes to the default namespace's, so
+/// clients written before namespaces keep working. Runs before routing.
+pub fn alias_legacy_routes(mut request: Request) -> Request {
+    let Some(rest) = request.uri().path().strip_prefix("/ctr/") else {
+        return request;
+    };
+    let collection = rest.split('/').next().unwrap_or_default();
+    if !LEGACY_COLLECTIONS.contains(&collection) {
+        return request;
+    }
+
+    let mut path = format!("/ctr/namespaces/{}/{rest}", Metadata::DEFAULT_NAMESPACE);
+    if let Some(query) = request.uri().query() {
+        path = format!("{path}?{query}");
+    }
+    let mut parts = request.uri().clone().into_parts();
+    parts.path_and_query = path.parse().ok();
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliased(uri: &str) -> String {
+        let request = Request::builder()
+            .uri(uri)
+            .body(Default::default())
+            .unwrap();
+        alias_legacy_routes(request).uri().to_string()
+    }
+
+    #[test]
+    fn legacy_routes_are_aliased_to_the_default_namespace() {
+        assert_eq!(
+            aliased("/ctr/droplet/echo/invoke?async=true"),
+            "/ctr/namespaces/default/droplet/echo/invoke?async=true"
+        );
+        assert_eq!(
+            aliased("/ctr/droplet/echo/invocations"),
+            "/ctr/namespaces/default/droplet/echo/invocations"
+        );
+        assert_eq!(aliased("/ctr/jobs/42"), "/ctr/namespaces/default/jobs/42");
+        assert_eq!(aliased("/ctr/jobs"), "/ctr/namespaces/default/jobs");
+    }
+
+    #[test]
+    fn other_routes_are_left_alone() {
+        for uri in [
+            "/ctr/namespaces/team/droplet/echo/execute",
+            "/ctr/blobs",
+            "/ctr/droplets",
+            "/metrics",
+        ] {
+            assert_eq!(aliased(uri), uri);
+        }
+    }
 }
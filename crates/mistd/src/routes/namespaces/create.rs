@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use mistctr::namespace::{Namespace, NamespaceQuota};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct NamespaceCreatePayload {
+    name: String,
+    #[serde(default)]
+    quota: NamespaceQuota,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NamespaceCreated {
+    namespace: Namespace,
+    /// Bearer token for the namespace's routes; it is only ever returned here.
+    token: String,
+}
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<NamespaceCreatePayload>,
+) -> impl IntoResponse {
+    match state
+        .control_panel()
+        .create_namespace(&payload.name, payload.quota)
+    {
+        Ok((namespace, token)) => {
+            tracing::info!("Created namespace: {}", namespace.name);
+            (
+                StatusCode::CREATED,
+                Json(NamespaceCreated { namespace, token }),
+            )
+                .into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+
+/// Deletes the namespace along with its droplets and secrets.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Path(namespace): Path<String>,
+) -> impl IntoResponse {
+    match state.control_panel().delete_namespace(&namespace) {
+        Ok(true) => {
+            tracing::info!("Deleted namespace: {namespace}");
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, "Namespace does not exist.").into_response(),
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use std::sync::Arc;
+
+use crate::state::AppState;
+use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+
+pub async fn handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.control_panel().namespaces().list() {
+        Ok(namespaces) => Json(namespaces).into_response(),
+        Err(e) => {
+            tracing::error!("{e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
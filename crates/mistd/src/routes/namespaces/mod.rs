@@ -0,0 +1,18 @@
+pub mod create;
+pub mod delete;
+pub mod list;
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    routing::{delete, get},
+};
+
+use crate::state::AppState;
+
+pub fn router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list::handler).post(create::handler))
+        .route("/{namespace}", delete(delete::handler))
+}
@@ -0,0 +1,10 @@
+use axum::{Json, extract::Path, http::StatusCode, response::IntoResponse};
+use config::{ApiVersion, versions};
+
+/// JSON Schema for config files of an API version, e.g. `/ctr/schema/hm/v2`.
+pub async fn handler(Path(api_version): Path<String>) -> impl IntoResponse {
+    match api_version.parse::<ApiVersion>() {
+        Ok(version) => Json(versions::schema(version)).into_response(),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
@@ -2,17 +2,27 @@ use std::sync::Arc;
 
 use mistctr::ControlPanel;
 
+use crate::auth::Authentication;
+
 #[derive(Clone)]
 pub struct AppState {
     control_panel: Arc<ControlPanel>,
+    authentication: Authentication,
 }
 
 impl AppState {
-    pub fn new(control_panel: Arc<ControlPanel>) -> Self {
-        Self { control_panel }
+    pub fn new(control_panel: Arc<ControlPanel>, authentication: Authentication) -> Self {
+        Self {
+            control_panel,
+            authentication,
+        }
     }
 
     pub fn control_panel(&self) -> &ControlPanel {
         &self.control_panel
     }
+
+    pub fn authentication(&self) -> &Authentication {
+        &self.authentication
+    }
 }